@@ -7,14 +7,42 @@ use dialoguer::theme::ColorfulTheme;
 use dialoguer::Select;
 use engine::Engine;
 use engine::Scene;
+use scenes::broadphase_benchmark::BroadphaseBenchmark;
+use scenes::cloth::ClothScene;
+use scenes::coupled_oscillators::CoupledOscillators;
+use scenes::elastic_pendulum::ElasticPendulum;
+use scenes::electrostatics::Electrostatics;
 use scenes::lorenz_attractor::LorenzAttractor;
+use scenes::lorentz_force::LorentzForce;
+use scenes::molecular_dynamics::MolecularDynamics;
+use scenes::n_body::NBodyScene;
 use scenes::pendulum::Pendulum;
 use scenes::particle_collisions::ParticleCollisionScene;
+use scenes::rigid_bodies::RigidBodies;
+use scenes::spherical_pendulum::SphericalPendulum;
+use scenes::sph_fluid::SphFluid;
+use scenes::storage_benchmark::StorageBenchmark;
 
 fn scene_loader() -> usize {
     let selection = Select::with_theme(&ColorfulTheme::default())
         .with_prompt("Select a simulation:")
-        .items(&["Particle Collisions", "Lorenz Attractor", "Pendulum"])
+        .items(&[
+            "Particle Collisions",
+            "Lorenz Attractor",
+            "Pendulum",
+            "Elastic Pendulum",
+            "Spherical Pendulum",
+            "Coupled Oscillators",
+            "Particle Storage Benchmark",
+            "N-Body Gravity",
+            "Electrostatics",
+            "Charged Particles in E and B Fields",
+            "SPH Fluid",
+            "Molecular Dynamics",
+            "Cloth",
+            "Rigid Bodies",
+            "Broadphase Benchmark",
+        ])
         .default(0)
         .interact()
         .unwrap();
@@ -22,6 +50,18 @@ fn scene_loader() -> usize {
         0 => println!("Loading Particle Collision Scene..."),
         1 => println!("Loading Lorenz Attractor..."),
         2 => println!("Loading Pendulum Scene..."),
+        3 => println!("Loading Elastic Pendulum Scene..."),
+        4 => println!("Loading Spherical Pendulum Scene..."),
+        5 => println!("Loading Coupled Oscillators Scene..."),
+        6 => println!("Loading Particle Storage Benchmark..."),
+        7 => println!("Loading N-Body Gravity Scene..."),
+        8 => println!("Loading Electrostatics Scene..."),
+        9 => println!("Loading Lorentz Force Scene..."),
+        10 => println!("Loading SPH Fluid Scene..."),
+        11 => println!("Loading Molecular Dynamics Scene..."),
+        12 => println!("Loading Cloth Scene..."),
+        13 => println!("Loading Rigid Bodies Scene..."),
+        14 => println!("Loading Broadphase Benchmark..."),
         _ => println!("Invalid selection."),
     }
     selection
@@ -38,6 +78,18 @@ fn main() -> Result<(), String> {
         Box::new(ParticleCollisionScene::new(&engine.global_context)),
         Box::new(LorenzAttractor::new()),
         Box::new(Pendulum::new()),
+        Box::new(ElasticPendulum::new()),
+        Box::new(SphericalPendulum::new()),
+        Box::new(CoupledOscillators::new()),
+        Box::new(StorageBenchmark::new(&engine.global_context)),
+        Box::new(NBodyScene::new()),
+        Box::new(Electrostatics::new(&engine.global_context)),
+        Box::new(LorentzForce::new(&engine.global_context)),
+        Box::new(SphFluid::new(&engine.global_context)),
+        Box::new(MolecularDynamics::new(&engine.global_context)),
+        Box::new(ClothScene::new(&engine.global_context)),
+        Box::new(RigidBodies::new(&engine.global_context)),
+        Box::new(BroadphaseBenchmark::new(&engine.global_context)),
     ];
     let mut selected_scene = options.remove(selection);
 
@@ -0,0 +1,112 @@
+use crate::models::particle::Particle;
+use crate::models::particle_system::ParticleSystem;
+use rand::Rng;
+
+/// What happens to a particle that reaches the edge of the domain along one axis.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum BoundaryMode {
+    /// Bounces off the edge with the particle's restitution and friction
+    #[default]
+    Reflective,
+    /// Leaves through one edge and comes back through the opposite one; contacts across the
+    /// seam use the nearest periodic image
+    Periodic,
+    /// Deleted once fully outside
+    Open,
+    /// Fed in through the low edge at `speed` px/s and deleted once fully outside either edge
+    Inflow { speed: f32 },
+}
+
+impl BoundaryMode {
+    pub fn label(&self) -> String {
+        match self {
+            BoundaryMode::Reflective => "reflective".to_string(),
+            BoundaryMode::Periodic => "periodic".to_string(),
+            BoundaryMode::Open => "open".to_string(),
+            BoundaryMode::Inflow { speed } => format!("inflow at {:.0} px/s", speed),
+        }
+    }
+
+    /// Whether particles are deleted when they leave along this axis.
+    pub fn absorbs(&self) -> bool {
+        matches!(self, BoundaryMode::Open | BoundaryMode::Inflow { .. })
+    }
+}
+
+/// Boundary modes of the two axes of the domain `0..width` by `0..height`.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Boundaries {
+    pub x: BoundaryMode,
+    pub y: BoundaryMode,
+}
+
+impl Boundaries {
+    pub fn label(&self) -> String {
+        if self.x == self.y {
+            self.x.label()
+        } else {
+            format!("x {}, y {}", self.x.label(), self.y.label())
+        }
+    }
+}
+
+/// Feeds particles through the low edge of every inflow axis, at a number density per square
+/// pixel, so that a steady stream crosses the domain.
+pub struct Inflow {
+    pub density: f32,
+    /// Fractional particles owed from earlier steps
+    carry: [f32; 2],
+}
+
+impl Inflow {
+    pub fn new(density: f32) -> Self {
+        Inflow {
+            density,
+            carry: [0.0; 2],
+        }
+    }
+
+    /// Adds the particles that entered over `dt`. `make(x, y, vx, vy)` describes each new
+    /// particle; it is then moved just inside the edge, spread over the distance
+    /// the stream covers in one step so it does not arrive in sheets.
+    pub fn feed<R, F>(
+        &mut self,
+        system: &mut ParticleSystem,
+        dt: f32,
+        (width, height): (f32, f32),
+        rng: &mut R,
+        make: F,
+    ) where
+        R: Rng,
+        F: Fn(f32, f32, f32, f32) -> Particle,
+    {
+        if dt <= 0.0 {
+            return;
+        }
+        let axes = [(system.boundaries.x, height), (system.boundaries.y, width)];
+        for (axis, (mode, edge)) in axes.into_iter().enumerate() {
+            let BoundaryMode::Inflow { speed } = mode else {
+                self.carry[axis] = 0.0;
+                continue;
+            };
+            let expected = self.density * speed * edge * dt + self.carry[axis];
+            let count = expected.floor();
+            self.carry[axis] = expected - count;
+            for _ in 0..count as usize {
+                let along = rng.gen_range(0.0..edge);
+                let depth = rng.gen_range(0.0..=speed * dt);
+                let mut particle = if axis == 0 {
+                    make(0.0, along, speed, 0.0)
+                } else {
+                    make(along, 0.0, 0.0, speed)
+                };
+                if axis == 0 {
+                    particle.x = particle.radius + depth;
+                } else {
+                    particle.y = particle.radius + depth;
+                }
+                system.push(particle);
+            }
+        }
+    }
+}
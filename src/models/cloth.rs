@@ -0,0 +1,332 @@
+use crate::models::force_field::ForceFields;
+use crate::models::particle::Particle;
+use crate::models::particle_system::ParticleSystem;
+use crate::utils::xpbd::{self, Distance};
+use sdl2::gfx::primitives::DrawRenderer;
+use sdl2::render::{Canvas, RenderTarget};
+
+/// Radius of the cloth particles, used only to pick them with the mouse.
+const NODE_RADIUS: f32 = 4.0;
+
+/// Role of a constraint in the cloth grid.
+///
+/// The screen shows a sheet that is free to wrinkle out of its plane, so structural and shear
+/// edges only resist stretching; pushed together, the cloth buckles instead. Bend edges do
+/// resist being pushed together, which is how folding the sheet looks from the front.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum EdgeKind {
+    /// Between horizontal and vertical neighbours, resisting stretch
+    Structural,
+    /// Across the diagonals of a cell, resisting shear
+    Shear,
+    /// Between nodes two apart in a row or column, resisting folding. `via` is the node in
+    /// between; the edge goes when either structural edge through it tears.
+    Bend { via: usize },
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Edge {
+    constraint: Distance,
+    kind: EdgeKind,
+}
+
+/// Compliance of each kind of edge, in px per unit force.
+#[derive(Clone, Copy, Debug)]
+pub struct Material {
+    pub name: &'static str,
+    pub structural: f32,
+    pub shear: f32,
+    pub bend: f32,
+}
+
+impl Material {
+    fn compliance(&self, kind: EdgeKind) -> f32 {
+        match kind {
+            EdgeKind::Structural => self.structural,
+            EdgeKind::Shear => self.shear,
+            EdgeKind::Bend { .. } => self.bend,
+        }
+    }
+}
+
+/// Rectangular sheet of particles held together by XPBD distance constraints.
+pub struct Cloth {
+    pub particles: ParticleSystem,
+    pub columns: usize,
+    pub rows: usize,
+    pub material: Material,
+    /// Structural and shear edges stretched beyond this strain tear, if set
+    pub tear_strain: Option<f32>,
+    /// Edges torn or cut so far
+    pub torn: usize,
+    /// Height of the ground, which stops the nodes without letting them slide
+    pub floor: Option<f32>,
+    edges: Vec<Edge>,
+    lambdas: Vec<f32>,
+    pinned: Vec<bool>,
+    /// Node held by the mouse and where it is held
+    held: Option<(usize, f32, f32)>,
+    weights: Vec<f32>,
+    previous: Vec<(f32, f32)>,
+}
+
+impl Cloth {
+    /// Flat sheet of `columns` by `rows` nodes, `spacing` apart, with its top left node at
+    /// `origin`. Every node has the same mass.
+    pub fn new(
+        origin: (f32, f32),
+        (columns, rows): (usize, usize),
+        spacing: f32,
+        mass: f32,
+        material: Material,
+    ) -> Self {
+        let mut particles = ParticleSystem::new();
+        for row in 0..rows {
+            for column in 0..columns {
+                let (x, y) = (
+                    origin.0 + column as f32 * spacing,
+                    origin.1 + row as f32 * spacing,
+                );
+                particles.push(Particle::new(x, y, 0.0, 0.0, NODE_RADIUS).with_mass(mass));
+            }
+        }
+        let mut cloth = Cloth {
+            particles,
+            columns,
+            rows,
+            material,
+            tear_strain: None,
+            torn: 0,
+            floor: None,
+            edges: Vec::new(),
+            lambdas: Vec::new(),
+            pinned: vec![false; columns * rows],
+            held: None,
+            weights: Vec::new(),
+            previous: Vec::new(),
+        };
+        cloth.connect();
+        cloth
+    }
+
+    pub fn index(&self, column: usize, row: usize) -> usize {
+        row * self.columns + column
+    }
+
+    fn connect(&mut self) {
+        let (columns, rows) = (self.columns, self.rows);
+        for row in 0..rows {
+            for column in 0..columns {
+                let i = self.index(column, row);
+                if column + 1 < columns {
+                    self.add_edge(i, i + 1, EdgeKind::Structural);
+                }
+                if row + 1 < rows {
+                    self.add_edge(i, i + columns, EdgeKind::Structural);
+                }
+                if column + 1 < columns && row + 1 < rows {
+                    self.add_edge(i, i + columns + 1, EdgeKind::Shear);
+                    self.add_edge(i + 1, i + columns, EdgeKind::Shear);
+                }
+                if column + 2 < columns {
+                    self.add_edge(i, i + 2, EdgeKind::Bend { via: i + 1 });
+                }
+                if row + 2 < rows {
+                    self.add_edge(i, i + 2 * columns, EdgeKind::Bend { via: i + columns });
+                }
+            }
+        }
+        self.lambdas = vec![0.0; self.edges.len()];
+    }
+
+    fn add_edge(&mut self, i: usize, j: usize, kind: EdgeKind) {
+        let compliance = self.material.compliance(kind);
+        let mut constraint = Distance::new(&self.particles, i, j, compliance);
+        constraint.tension_only = !matches!(kind, EdgeKind::Bend { .. });
+        self.edges.push(Edge { constraint, kind });
+    }
+
+    /// Changes the compliance of every edge.
+    pub fn set_material(&mut self, material: Material) {
+        self.material = material;
+        for edge in &mut self.edges {
+            edge.constraint.compliance = material.compliance(edge.kind);
+        }
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.edges.len()
+    }
+
+    pub fn toggle_pin(&mut self, i: usize) {
+        self.pinned[i] = !self.pinned[i];
+        self.particles.vx[i] = 0.0;
+        self.particles.vy[i] = 0.0;
+    }
+
+    /// Node closest to `(x, y)`, if within `reach`.
+    pub fn node_near(&self, x: f32, y: f32, reach: f32) -> Option<usize> {
+        let p = &self.particles;
+        (0..p.len())
+            .map(|i| (i, (p.x[i] - x).hypot(p.y[i] - y)))
+            .filter(|&(_, d)| d <= reach)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i)
+    }
+
+    /// Holds node `i` at `(x, y)` like a pin until released.
+    pub fn hold(&mut self, i: usize, x: f32, y: f32) {
+        self.held = Some((i, x, y));
+    }
+
+    pub fn move_held(&mut self, x: f32, y: f32) {
+        if let Some((i, _, _)) = self.held {
+            self.held = Some((i, x, y));
+        }
+    }
+
+    pub fn release(&mut self) {
+        self.held = None;
+    }
+
+    /// Removes every edge crossing the segment from `a` to `b`, as a blade would.
+    pub fn cut(&mut self, a: (f32, f32), b: (f32, f32)) {
+        let p = &self.particles;
+        let before = self.edges.len();
+        self.edges.retain(|edge| {
+            let (i, j) = (edge.constraint.i, edge.constraint.j);
+            !segments_cross(a, b, (p.x[i], p.y[i]), (p.x[j], p.y[j]))
+        });
+        let cut = before - self.edges.len();
+        if cut > 0 {
+            self.torn += cut;
+            self.drop_orphaned_bends();
+        }
+    }
+
+    /// Advances the cloth by `dt` in `substeps` small steps of one constraint iteration each,
+    /// which converges faster than many iterations of one large step.
+    pub fn step(&mut self, fields: &ForceFields, dt: f32, substeps: usize) {
+        if dt <= 0.0 || substeps == 0 {
+            return;
+        }
+        let h = dt / substeps as f32;
+        self.weights.clear();
+        self.weights.extend((0..self.particles.len()).map(|i| {
+            let held = self.held.is_some_and(|(k, _, _)| k == i);
+            if self.pinned[i] || held {
+                0.0
+            } else {
+                self.particles.inverse_mass(i)
+            }
+        }));
+        for _ in 0..substeps {
+            xpbd::predict(
+                &mut self.particles,
+                &self.weights,
+                fields,
+                h,
+                &mut self.previous,
+            );
+            if let Some((i, x, y)) = self.held {
+                self.particles.x[i] = x;
+                self.particles.y[i] = y;
+            }
+            self.lambdas.iter_mut().for_each(|lambda| *lambda = 0.0);
+            for (edge, lambda) in self.edges.iter().zip(&mut self.lambdas) {
+                edge.constraint
+                    .solve(&mut self.particles, &self.weights, h, lambda);
+            }
+            if let Some(floor) = self.floor {
+                self.rest_on_floor(floor);
+            }
+            xpbd::update_velocities(&mut self.particles, &self.previous, h);
+        }
+        self.tear();
+    }
+
+    fn rest_on_floor(&mut self, floor: f32) {
+        let p = &mut self.particles;
+        for (i, &(x, _)) in self.previous.iter().enumerate() {
+            if p.y[i] > floor {
+                p.y[i] = floor;
+                p.x[i] = x;
+            }
+        }
+    }
+
+    fn tear(&mut self) {
+        let Some(limit) = self.tear_strain else {
+            return;
+        };
+        let p = &self.particles;
+        let before = self.edges.len();
+        self.edges.retain(|edge| {
+            matches!(edge.kind, EdgeKind::Bend { .. }) || edge.constraint.strain(p) <= limit
+        });
+        let torn = before - self.edges.len();
+        if torn > 0 {
+            self.torn += torn;
+            self.drop_orphaned_bends();
+        }
+    }
+
+    /// Removes bend edges whose node in between lost one of its structural edges, so that
+    /// torn pieces fold freely at the tear.
+    fn drop_orphaned_bends(&mut self) {
+        let structural: std::collections::HashSet<(usize, usize)> = self
+            .edges
+            .iter()
+            .filter(|edge| edge.kind == EdgeKind::Structural)
+            .map(|edge| (edge.constraint.i, edge.constraint.j))
+            .collect();
+        let linked =
+            |a: usize, b: usize| structural.contains(&(a, b)) || structural.contains(&(b, a));
+        self.edges.retain(|edge| match edge.kind {
+            EdgeKind::Bend { via } => {
+                linked(edge.constraint.i, via) && linked(via, edge.constraint.j)
+            }
+            _ => true,
+        });
+        self.lambdas.truncate(self.edges.len());
+    }
+
+    /// Draws the structural edges, shading from white at rest to red near the tearing strain,
+    /// and the pinned nodes.
+    pub fn render<T: RenderTarget>(&self, canvas: &mut Canvas<T>) {
+        let p = &self.particles;
+        let limit = self.tear_strain.unwrap_or(0.5);
+        for edge in &self.edges {
+            if edge.kind != EdgeKind::Structural {
+                continue;
+            }
+            let (i, j) = (edge.constraint.i, edge.constraint.j);
+            let stress = (edge.constraint.strain(p) / limit).clamp(0.0, 1.0);
+            let fade = (230.0 * (1.0 - stress)) as u8;
+            let _ = canvas.line(
+                p.x[i] as i16,
+                p.y[i] as i16,
+                p.x[j] as i16,
+                p.y[j] as i16,
+                (255, fade, fade, 255),
+            );
+        }
+        for i in (0..p.len()).filter(|&i| self.pinned[i]) {
+            let (x, y) = (p.x[i] as i16, p.y[i] as i16);
+            let _ = canvas.box_(x - 3, y - 3, x + 3, y + 3, (120, 200, 255, 255));
+        }
+        if let Some((i, _, _)) = self.held {
+            let _ = canvas.circle(p.x[i] as i16, p.y[i] as i16, 8, (255, 255, 120, 255));
+        }
+    }
+}
+
+/// Whether the segments `a`-`b` and `c`-`d` cross.
+fn segments_cross(a: (f32, f32), b: (f32, f32), c: (f32, f32), d: (f32, f32)) -> bool {
+    let side = |p: (f32, f32), q: (f32, f32), r: (f32, f32)| {
+        (q.0 - p.0) * (r.1 - p.1) - (q.1 - p.1) * (r.0 - p.0)
+    };
+    let (d1, d2) = (side(a, b, c), side(a, b, d));
+    let (d3, d4) = (side(c, d, a), side(c, d, b));
+    d1 * d2 < 0.0 && d3 * d4 < 0.0
+}
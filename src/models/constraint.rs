@@ -0,0 +1,237 @@
+use crate::models::particle_system::ParticleSystem;
+use sdl2::gfx::primitives::DrawRenderer;
+use sdl2::render::{Canvas, RenderTarget};
+
+/// Projection passes over the rods per step. More passes make long chains stiffer.
+const ROD_ITERATIONS: usize = 8;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum LinkKind {
+    /// Holds the two particles at exactly the rest length
+    Rod,
+    /// Hookean spring with force `stiffness` per pixel of stretch, plus `damping` times the
+    /// rate of stretch
+    Spring { stiffness: f32, damping: f32 },
+}
+
+/// Connection between two particles, named by their ids so that it survives other particles
+/// being removed.
+#[derive(Clone, Copy, Debug)]
+pub struct Link {
+    pub a: u64,
+    pub b: u64,
+    pub rest_length: f32,
+    pub kind: LinkKind,
+}
+
+/// Particle pinned in place.
+#[derive(Clone, Copy, Debug)]
+pub struct Pin {
+    pub id: u64,
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Rods, springs and pins acting on a `ParticleSystem`.
+///
+/// Springs are forces and are applied before integration. Rods and pins are position-based:
+/// after integration the positions are projected back onto the constraints, and the
+/// corrections are turned into velocity changes so the particles move on consistently.
+#[derive(Default)]
+pub struct Constraints {
+    pub links: Vec<Link>,
+    pub pins: Vec<Pin>,
+    /// Current indices of each link's particles, filled by `resolve`
+    indices: Vec<(usize, usize)>,
+    /// Per particle: whether it is pinned, filled by `resolve`
+    pinned: Vec<bool>,
+    /// Positions before projection
+    before: Vec<(f32, f32)>,
+}
+
+impl Constraints {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.links.is_empty() && self.pins.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.links.clear();
+        self.pins.clear();
+    }
+
+    /// Links particles `i` and `j` at their current distance. Returns false if they are the
+    /// same particle or already linked.
+    pub fn link(&mut self, system: &ParticleSystem, i: usize, j: usize, kind: LinkKind) -> bool {
+        let (a, b) = (system.id[i], system.id[j]);
+        let exists = self
+            .links
+            .iter()
+            .any(|l| (l.a, l.b) == (a, b) || (l.a, l.b) == (b, a));
+        if i == j || exists {
+            return false;
+        }
+        let rest_length = (system.x[j] - system.x[i]).hypot(system.y[j] - system.y[i]);
+        self.links.push(Link {
+            a,
+            b,
+            rest_length,
+            kind,
+        });
+        true
+    }
+
+    /// Pins particle `i` where it is, or releases it if it was pinned.
+    pub fn toggle_pin(&mut self, system: &ParticleSystem, i: usize) {
+        let id = system.id[i];
+        if let Some(k) = self.pins.iter().position(|pin| pin.id == id) {
+            self.pins.remove(k);
+        } else {
+            self.pins.push(Pin {
+                id,
+                x: system.x[i],
+                y: system.y[i],
+            });
+        }
+    }
+
+    /// Looks up the current indices and drops links and pins whose particles are gone.
+    fn resolve(&mut self, system: &ParticleSystem) {
+        self.links
+            .retain(|l| system.index_of(l.a).is_some() && system.index_of(l.b).is_some());
+        self.pins.retain(|pin| system.index_of(pin.id).is_some());
+        self.indices.clear();
+        self.indices.extend(self.links.iter().map(|l| {
+            (
+                system.index_of(l.a).unwrap_or_default(),
+                system.index_of(l.b).unwrap_or_default(),
+            )
+        }));
+        self.pinned.clear();
+        self.pinned.resize(system.len(), false);
+        for pin in &self.pins {
+            if let Some(i) = system.index_of(pin.id) {
+                self.pinned[i] = true;
+            }
+        }
+    }
+
+    /// Inverse mass as the constraints see it: pinned particles do not give way.
+    fn weight(&self, system: &ParticleSystem, i: usize) -> f32 {
+        if self.pinned[i] {
+            0.0
+        } else {
+            system.inverse_mass(i)
+        }
+    }
+
+    /// Applies the spring forces over a step of `dt` as velocity changes.
+    pub fn apply_springs(&mut self, system: &mut ParticleSystem, dt: f32) {
+        if self.links.is_empty() {
+            return;
+        }
+        self.resolve(system);
+        for (link, &(i, j)) in self.links.iter().zip(&self.indices) {
+            let LinkKind::Spring { stiffness, damping } = link.kind else {
+                continue;
+            };
+            let (dx, dy) = (system.x[j] - system.x[i], system.y[j] - system.y[i]);
+            let dist = dx.hypot(dy);
+            if dist < 1e-6 {
+                continue;
+            }
+            let (nx, ny) = (dx / dist, dy / dist);
+            let stretch_rate =
+                (system.vx[j] - system.vx[i]) * nx + (system.vy[j] - system.vy[i]) * ny;
+            let force = stiffness * (dist - link.rest_length) + damping * stretch_rate;
+            let (wi, wj) = (self.weight(system, i), self.weight(system, j));
+            system.vx[i] += force * nx * wi * dt;
+            system.vy[i] += force * ny * wi * dt;
+            system.vx[j] -= force * nx * wj * dt;
+            system.vy[j] -= force * ny * wj * dt;
+        }
+    }
+
+    /// Moves the particles back onto the rods and pins after a step of `dt`.
+    pub fn project(&mut self, system: &mut ParticleSystem, dt: f32) {
+        if self.is_empty() {
+            return;
+        }
+        self.resolve(system);
+        self.before.clear();
+        self.before.extend(system.positions());
+
+        for _ in 0..ROD_ITERATIONS {
+            for pin in &self.pins {
+                if let Some(i) = system.index_of(pin.id) {
+                    system.x[i] = pin.x;
+                    system.y[i] = pin.y;
+                }
+            }
+            for (link, &(i, j)) in self.links.iter().zip(&self.indices) {
+                if link.kind != LinkKind::Rod {
+                    continue;
+                }
+                let (wi, wj) = (self.weight(system, i), self.weight(system, j));
+                let (dx, dy) = (system.x[j] - system.x[i], system.y[j] - system.y[i]);
+                let dist = dx.hypot(dy);
+                if wi + wj <= 0.0 || dist < 1e-6 {
+                    continue;
+                }
+                // Split the error in proportion to the inverse masses
+                let correction = (dist - link.rest_length) / (dist * (wi + wj));
+                system.x[i] += dx * correction * wi;
+                system.y[i] += dy * correction * wi;
+                system.x[j] -= dx * correction * wj;
+                system.y[j] -= dy * correction * wj;
+            }
+        }
+
+        for (i, &(x, y)) in self.before.iter().enumerate() {
+            if self.pinned[i] {
+                system.vx[i] = 0.0;
+                system.vy[i] = 0.0;
+            } else if dt > 0.0 {
+                system.vx[i] += (system.x[i] - x) / dt;
+                system.vy[i] += (system.y[i] - y) / dt;
+            }
+        }
+    }
+
+    /// Draws rods grey, springs shading from green when slack to red when stretched, and
+    /// pins as small squares.
+    pub fn render<T: RenderTarget>(&self, canvas: &mut Canvas<T>, system: &ParticleSystem) {
+        for link in &self.links {
+            let (Some(i), Some(j)) = (system.index_of(link.a), system.index_of(link.b)) else {
+                continue;
+            };
+            let color = match link.kind {
+                LinkKind::Rod => (200, 200, 210, 255),
+                LinkKind::Spring { .. } => {
+                    let dist = (system.x[j] - system.x[i]).hypot(system.y[j] - system.y[i]);
+                    let strain = ((dist / link.rest_length.max(1.0) - 1.0) * 4.0).clamp(-1.0, 1.0);
+                    (
+                        (160.0 + 95.0 * strain.max(0.0)) as u8,
+                        (200.0 - 120.0 * strain.max(0.0)) as u8,
+                        (80.0 + 120.0 * (-strain).max(0.0)) as u8,
+                        255,
+                    )
+                }
+            };
+            let _ = canvas.line(
+                system.x[i] as i16,
+                system.y[i] as i16,
+                system.x[j] as i16,
+                system.y[j] as i16,
+                color,
+            );
+        }
+        for pin in &self.pins {
+            let (x, y) = (pin.x as i16, pin.y as i16);
+            let _ = canvas.box_(x - 3, y - 3, x + 3, y + 3, (255, 255, 255, 255));
+        }
+    }
+}
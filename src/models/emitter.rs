@@ -0,0 +1,491 @@
+use crate::models::particle::Particle;
+use crate::models::particle_system::ParticleSystem;
+use rand::Rng;
+use sdl2::gfx::primitives::DrawRenderer;
+use sdl2::render::{Canvas, RenderTarget};
+use std::fmt;
+
+/// How a sampled quantity is spread.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Distribution {
+    Constant(f32),
+    Uniform {
+        min: f32,
+        max: f32,
+    },
+    Gaussian {
+        mean: f32,
+        std_dev: f32,
+    },
+    /// Magnitude of a 2D vector with Gaussian components of standard deviation `scale`, which
+    /// for a speed is the Maxwell–Boltzmann distribution with kT / m = scale²
+    Maxwellian {
+        scale: f32,
+    },
+}
+
+impl Distribution {
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> f32 {
+        match *self {
+            Distribution::Constant(value) => value,
+            Distribution::Uniform { min, max } if min < max => rng.gen_range(min..max),
+            Distribution::Uniform { min, .. } => min,
+            Distribution::Gaussian { mean, std_dev } => mean + std_dev * standard_normal(rng),
+            Distribution::Maxwellian { scale } => {
+                // Inverse transform of the Rayleigh distribution
+                let u: f32 = rng.gen_range(0.0..1.0);
+                scale * (-2.0 * (1.0 - u).ln()).sqrt()
+            }
+        }
+    }
+
+    /// Reads the form written by `Display`: a plain number, `uniform:min:max`,
+    /// `gaussian:mean:std_dev` or `maxwellian:scale`.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut parts = text.split(':');
+        let kind = parts.next().unwrap_or_default();
+        let numbers = parts
+            .map(|p| p.parse::<f32>())
+            .collect::<Result<Vec<f32>, _>>()
+            .map_err(|e| format!("{} in '{}'", e, text))?;
+        match (kind, numbers.as_slice()) {
+            ("uniform", &[min, max]) => Ok(Distribution::Uniform { min, max }),
+            ("gaussian", &[mean, std_dev]) => Ok(Distribution::Gaussian { mean, std_dev }),
+            ("maxwellian", &[scale]) => Ok(Distribution::Maxwellian { scale }),
+            (value, &[]) => value
+                .parse()
+                .map(Distribution::Constant)
+                .map_err(|_| format!("unknown distribution '{}'", text)),
+            _ => Err(format!("wrong number of values in '{}'", text)),
+        }
+    }
+}
+
+impl fmt::Display for Distribution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Distribution::Constant(value) => write!(f, "{}", value),
+            Distribution::Uniform { min, max } => write!(f, "uniform:{}:{}", min, max),
+            Distribution::Gaussian { mean, std_dev } => {
+                write!(f, "gaussian:{}:{}", mean, std_dev)
+            }
+            Distribution::Maxwellian { scale } => write!(f, "maxwellian:{}", scale),
+        }
+    }
+}
+
+/// Normally distributed value with zero mean and unit variance (Box–Muller).
+fn standard_normal<R: Rng>(rng: &mut R) -> f32 {
+    let u: f32 = rng.gen_range(f32::EPSILON..1.0);
+    let v: f32 = rng.gen_range(0.0..1.0);
+    (-2.0 * u.ln()).sqrt() * (2.0 * std::f32::consts::PI * v).cos()
+}
+
+/// Area around an emitter or sink position that particles are spawned in or removed from.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Region {
+    Point,
+    /// Segment from the position to the position plus `(dx, dy)`
+    Line {
+        dx: f32,
+        dy: f32,
+    },
+    Disc {
+        radius: f32,
+    },
+    /// Axis-aligned box centred on the position
+    Box {
+        width: f32,
+        height: f32,
+    },
+}
+
+impl Region {
+    /// Uniformly distributed offset from the position inside the region.
+    fn sample<R: Rng>(&self, rng: &mut R) -> (f32, f32) {
+        match *self {
+            Region::Point => (0.0, 0.0),
+            Region::Line { dx, dy } => {
+                let t: f32 = rng.gen_range(0.0..=1.0);
+                (dx * t, dy * t)
+            }
+            Region::Disc { radius } => {
+                let r = radius * rng.gen_range(0.0f32..=1.0).sqrt();
+                let angle = rng.gen_range(0.0..2.0 * std::f32::consts::PI);
+                (r * angle.cos(), r * angle.sin())
+            }
+            Region::Box { width, height } => (
+                width * rng.gen_range(-0.5..=0.5),
+                height * rng.gen_range(-0.5..=0.5),
+            ),
+        }
+    }
+
+    /// Whether an offset from the position lies inside. Points and lines have no inside.
+    fn contains(&self, dx: f32, dy: f32) -> bool {
+        match *self {
+            Region::Point | Region::Line { .. } => false,
+            Region::Disc { radius } => dx * dx + dy * dy <= radius * radius,
+            Region::Box { width, height } => dx.abs() <= 0.5 * width && dy.abs() <= 0.5 * height,
+        }
+    }
+
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut parts = text.split(':');
+        let kind = parts.next().unwrap_or_default();
+        let numbers = parts
+            .map(|p| p.parse::<f32>())
+            .collect::<Result<Vec<f32>, _>>()
+            .map_err(|e| format!("{} in '{}'", e, text))?;
+        match (kind, numbers.as_slice()) {
+            ("point", &[]) => Ok(Region::Point),
+            ("line", &[dx, dy]) => Ok(Region::Line { dx, dy }),
+            ("disc", &[radius]) => Ok(Region::Disc { radius }),
+            ("box", &[width, height]) => Ok(Region::Box { width, height }),
+            _ => Err(format!("unknown region '{}'", text)),
+        }
+    }
+
+    fn render<T: RenderTarget>(&self, canvas: &mut Canvas<T>, (x, y): (f32, f32), color: Rgba) {
+        match *self {
+            Region::Point => {
+                let _ = canvas.circle(x as i16, y as i16, 4, color);
+            }
+            Region::Line { dx, dy } => {
+                let _ = canvas.line(x as i16, y as i16, (x + dx) as i16, (y + dy) as i16, color);
+            }
+            Region::Disc { radius } => {
+                let _ = canvas.circle(x as i16, y as i16, radius as i16, color);
+            }
+            Region::Box { width, height } => {
+                let _ = canvas.rectangle(
+                    (x - 0.5 * width) as i16,
+                    (y - 0.5 * height) as i16,
+                    (x + 0.5 * width) as i16,
+                    (y + 0.5 * height) as i16,
+                    color,
+                );
+            }
+        }
+    }
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Region::Point => write!(f, "point"),
+            Region::Line { dx, dy } => write!(f, "line:{}:{}", dx, dy),
+            Region::Disc { radius } => write!(f, "disc:{}", radius),
+            Region::Box { width, height } => write!(f, "box:{}:{}", width, height),
+        }
+    }
+}
+
+type Rgba = (u8, u8, u8, u8);
+
+/// Spawns particles at a steady rate with sampled speed, radius, mass and colour.
+///
+/// Emitters follow a simple script in simulated time: they run from `start` to `stop`, can
+/// pulse on for the first `duty` fraction of every `period` seconds, and their direction turns
+/// at `spin` degrees per second.
+#[derive(Clone, Debug)]
+pub struct Emitter {
+    pub x: f32,
+    pub y: f32,
+    pub region: Region,
+    /// Particles per second while active
+    pub rate: f32,
+    /// Launch direction in degrees, clockwise from +x on screen
+    pub direction: f32,
+    /// Full width of the cone of directions in degrees; 360 sends them everywhere
+    pub spread: f32,
+    pub speed: Distribution,
+    pub radius: Distribution,
+    /// Mass of each particle; without one it follows from the radius at the default density
+    pub mass: Option<Distribution>,
+    /// Hue in degrees; without one particles are shaded by density
+    pub hue: Option<Distribution>,
+    pub restitution: f32,
+    pub friction: f32,
+    pub start: f32,
+    pub stop: f32,
+    /// Pulse period in seconds and the fraction of it spent emitting
+    pub pulse: Option<(f32, f32)>,
+    /// Degrees per second the direction turns by
+    pub spin: f32,
+    /// Particles spawned so far
+    pub emitted: usize,
+    /// Fractional particle owed from earlier steps
+    carry: f32,
+}
+
+impl Emitter {
+    /// A point source that starts at once and never stops, launching particles of radius 5
+    /// at 150 px/s along `direction`.
+    pub fn new(x: f32, y: f32, direction: f32) -> Self {
+        Emitter {
+            x,
+            y,
+            region: Region::Point,
+            rate: 20.0,
+            direction,
+            spread: 20.0,
+            speed: Distribution::Constant(150.0),
+            radius: Distribution::Constant(5.0),
+            mass: None,
+            hue: None,
+            restitution: 1.0,
+            friction: 0.0,
+            start: 0.0,
+            stop: f32::INFINITY,
+            pulse: None,
+            spin: 0.0,
+            emitted: 0,
+            carry: 0.0,
+        }
+    }
+
+    /// Whether the script has the emitter running at `time`.
+    pub fn is_active(&self, time: f32) -> bool {
+        if time < self.start || time >= self.stop {
+            return false;
+        }
+        match self.pulse {
+            Some((period, duty)) if period > 0.0 => {
+                (time - self.start).rem_euclid(period) < duty * period
+            }
+            _ => true,
+        }
+    }
+
+    /// Launch direction at `time`, in radians.
+    fn direction_at(&self, time: f32) -> f32 {
+        (self.direction + self.spin * (time - self.start).max(0.0)).to_radians()
+    }
+
+    /// Spawns the particles due over the step of length `dt` ending at `time`.
+    pub fn emit<R: Rng>(&mut self, system: &mut ParticleSystem, time: f32, dt: f32, rng: &mut R) {
+        if dt <= 0.0 || !self.is_active(time) {
+            self.carry = 0.0;
+            return;
+        }
+        let due = self.rate * dt + self.carry;
+        let count = due.floor();
+        self.carry = due - count;
+        let heading = self.direction_at(time);
+        for _ in 0..count as usize {
+            let (ox, oy) = self.region.sample(rng);
+            let half_spread = 0.5 * self.spread.to_radians();
+            let angle = if half_spread > 0.0 {
+                heading + rng.gen_range(-half_spread..=half_spread)
+            } else {
+                heading
+            };
+            let speed = self.speed.sample(rng).max(0.0);
+            let radius = self.radius.sample(rng).max(1.0);
+            let mut particle = Particle::new(
+                self.x + ox,
+                self.y + oy,
+                speed * angle.cos(),
+                speed * angle.sin(),
+                radius,
+            )
+            .with_restitution(self.restitution)
+            .with_friction(self.friction);
+            if let Some(mass) = &self.mass {
+                let mass = mass.sample(rng);
+                if mass > 0.0 {
+                    particle = particle.with_mass(mass);
+                }
+            }
+            if let Some(hue) = &self.hue {
+                particle = particle.with_color(hue_color(hue.sample(rng)));
+            }
+            system.push(particle);
+            self.emitted += 1;
+        }
+    }
+
+    /// Draws the region and an arrow along the current direction, dimmed while inactive.
+    pub fn render<T: RenderTarget>(&self, canvas: &mut Canvas<T>, time: f32) {
+        let color = if self.is_active(time) {
+            (120, 220, 255, 255)
+        } else {
+            (70, 100, 120, 255)
+        };
+        self.region.render(canvas, (self.x, self.y), color);
+        let heading = self.direction_at(time);
+        let (tx, ty) = (self.x + 25.0 * heading.cos(), self.y + 25.0 * heading.sin());
+        let _ = canvas.line(self.x as i16, self.y as i16, tx as i16, ty as i16, color);
+        let _ = canvas.filled_circle(tx as i16, ty as i16, 3, color);
+    }
+
+    /// Reads the `key=value` settings of an `emitter` line; missing keys keep the defaults
+    /// of `Emitter::new`.
+    pub fn parse(words: &[&str]) -> Result<Self, String> {
+        let mut emitter = Emitter::new(0.0, 0.0, 0.0);
+        for word in words {
+            let (key, value) = word
+                .split_once('=')
+                .ok_or_else(|| format!("expected key=value, found '{}'", word))?;
+            let number = || {
+                value
+                    .parse::<f32>()
+                    .map_err(|e| format!("{} in '{}'", e, word))
+            };
+            match key {
+                "x" => emitter.x = number()?,
+                "y" => emitter.y = number()?,
+                "region" => emitter.region = Region::parse(value)?,
+                "rate" => emitter.rate = number()?,
+                "direction" => emitter.direction = number()?,
+                "spread" => emitter.spread = number()?,
+                "speed" => emitter.speed = Distribution::parse(value)?,
+                "radius" => emitter.radius = Distribution::parse(value)?,
+                "mass" => emitter.mass = Some(Distribution::parse(value)?),
+                "hue" => emitter.hue = Some(Distribution::parse(value)?),
+                "restitution" => emitter.restitution = number()?,
+                "friction" => emitter.friction = number()?,
+                "start" => emitter.start = number()?,
+                "stop" => emitter.stop = number()?,
+                "pulse" => {
+                    let (period, duty) = value
+                        .split_once(':')
+                        .and_then(|(p, d)| Some((p.parse().ok()?, d.parse().ok()?)))
+                        .ok_or_else(|| format!("expected pulse=period:duty, found '{}'", word))?;
+                    emitter.pulse = Some((period, duty));
+                }
+                "spin" => emitter.spin = number()?,
+                _ => return Err(format!("unknown emitter setting '{}'", key)),
+            }
+        }
+        Ok(emitter)
+    }
+
+    /// The settings as written to a layout file, without the leading keyword.
+    pub fn to_words(&self) -> String {
+        let mut words = format!(
+            "x={} y={} region={} rate={} direction={} spread={} speed={} radius={}",
+            self.x,
+            self.y,
+            self.region,
+            self.rate,
+            self.direction,
+            self.spread,
+            self.speed,
+            self.radius
+        );
+        if let Some(mass) = &self.mass {
+            words += &format!(" mass={}", mass);
+        }
+        if let Some(hue) = &self.hue {
+            words += &format!(" hue={}", hue);
+        }
+        words += &format!(
+            " restitution={} friction={} start={}",
+            self.restitution, self.friction, self.start
+        );
+        if self.stop.is_finite() {
+            words += &format!(" stop={}", self.stop);
+        }
+        if let Some((period, duty)) = self.pulse {
+            words += &format!(" pulse={}:{}", period, duty);
+        }
+        if self.spin != 0.0 {
+            words += &format!(" spin={}", self.spin);
+        }
+        words
+    }
+}
+
+/// Fully saturated colour for a hue in degrees.
+fn hue_color(hue: f32) -> (u8, u8, u8) {
+    let h = hue.rem_euclid(360.0) / 60.0;
+    let x = 1.0 - (h % 2.0 - 1.0).abs();
+    let (r, g, b) = match h as u32 {
+        0 => (1.0, x, 0.0),
+        1 => (x, 1.0, 0.0),
+        2 => (0.0, 1.0, x),
+        3 => (0.0, x, 1.0),
+        4 => (x, 0.0, 1.0),
+        _ => (1.0, 0.0, x),
+    };
+    ((255.0 * r) as u8, (255.0 * g) as u8, (255.0 * b) as u8)
+}
+
+/// Region that deletes every particle whose centre enters it, keeping count of what it took.
+#[derive(Clone, Debug)]
+pub struct Sink {
+    pub x: f32,
+    pub y: f32,
+    pub region: Region,
+    pub absorbed: usize,
+    pub absorbed_mass: f32,
+}
+
+impl Sink {
+    pub fn new(x: f32, y: f32, region: Region) -> Self {
+        Sink {
+            x,
+            y,
+            region,
+            absorbed: 0,
+            absorbed_mass: 0.0,
+        }
+    }
+
+    /// Removes the particles inside and returns how many there were.
+    pub fn absorb(&mut self, system: &mut ParticleSystem) -> usize {
+        let inside: Vec<bool> = (0..system.len())
+            .map(|i| {
+                self.region
+                    .contains(system.x[i] - self.x, system.y[i] - self.y)
+            })
+            .collect();
+        let count = inside.iter().filter(|&&taken| taken).count();
+        if count == 0 {
+            return 0;
+        }
+        self.absorbed += count;
+        self.absorbed_mass += (0..system.len())
+            .filter(|&i| inside[i])
+            .map(|i| system.mass[i])
+            .sum::<f32>();
+        system.retain(|i| !inside[i]);
+        count
+    }
+
+    pub fn render<T: RenderTarget>(&self, canvas: &mut Canvas<T>) {
+        let color = (255, 120, 200, 255);
+        self.region.render(canvas, (self.x, self.y), color);
+        let _ = canvas.string(
+            self.x as i16 - 12,
+            self.y as i16 - 4,
+            &self.absorbed.to_string(),
+            color,
+        );
+    }
+
+    pub fn parse(words: &[&str]) -> Result<Self, String> {
+        let mut sink = Sink::new(0.0, 0.0, Region::Disc { radius: 20.0 });
+        for word in words {
+            let (key, value) = word
+                .split_once('=')
+                .ok_or_else(|| format!("expected key=value, found '{}'", word))?;
+            match key {
+                "x" => sink.x = value.parse().map_err(|_| format!("bad x in '{}'", word))?,
+                "y" => sink.y = value.parse().map_err(|_| format!("bad y in '{}'", word))?,
+                "region" => sink.region = Region::parse(value)?,
+                _ => return Err(format!("unknown sink setting '{}'", key)),
+            }
+        }
+        if matches!(sink.region, Region::Point | Region::Line { .. }) {
+            return Err("a sink needs a disc or box region".to_string());
+        }
+        Ok(sink)
+    }
+
+    pub fn to_words(&self) -> String {
+        format!("x={} y={} region={}", self.x, self.y, self.region)
+    }
+}
@@ -0,0 +1,452 @@
+use crate::utils::noise::value_noise3;
+use sdl2::gfx::primitives::DrawRenderer;
+use sdl2::{render::Canvas, video::Window};
+
+/// Standard downward gravity in px/s².
+pub const GRAVITY: f32 = 980.0;
+
+/// What a force field sees of one particle.
+#[derive(Clone, Copy)]
+pub struct FieldSample {
+    pub x: f32,
+    pub y: f32,
+    pub vx: f32,
+    pub vy: f32,
+    pub radius: f32,
+    pub inv_mass: f32,
+}
+
+/// A force acting on particles, expressed as the acceleration it causes.
+pub trait ForceField: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Acceleration of the given particle in px/s².
+    fn acceleration(&self, p: &FieldSample) -> (f32, f32);
+
+    /// The acceleration if it is the same for every particle. Such fields are summed once per
+    /// step rather than evaluated per particle.
+    fn uniform(&self) -> Option<(f32, f32)> {
+        None
+    }
+
+    /// Moves time-dependent fields forward.
+    fn advance(&mut self, _dt: f32) {}
+
+    /// Anchor point of fields that can be placed with the mouse.
+    fn position(&self) -> Option<(f32, f32)> {
+        None
+    }
+
+    fn set_position(&mut self, _x: f32, _y: f32) {}
+
+    fn strength(&self) -> f32;
+
+    fn set_strength(&mut self, strength: f32);
+
+    fn render(&self, _canvas: &mut Canvas<Window>) {}
+}
+
+/// Constant acceleration everywhere.
+pub struct UniformGravity {
+    pub gx: f32,
+    pub gy: f32,
+}
+
+impl Default for UniformGravity {
+    fn default() -> Self {
+        UniformGravity {
+            gx: 0.0,
+            gy: GRAVITY,
+        }
+    }
+}
+
+impl ForceField for UniformGravity {
+    fn name(&self) -> &'static str {
+        "gravity"
+    }
+
+    fn acceleration(&self, _p: &FieldSample) -> (f32, f32) {
+        (self.gx, self.gy)
+    }
+
+    fn uniform(&self) -> Option<(f32, f32)> {
+        Some((self.gx, self.gy))
+    }
+
+    fn strength(&self) -> f32 {
+        self.gx.hypot(self.gy)
+    }
+
+    fn set_strength(&mut self, strength: f32) {
+        let current = self.strength();
+        if current > 0.0 {
+            self.gx *= strength / current;
+            self.gy *= strength / current;
+        } else {
+            self.gy = strength;
+        }
+    }
+}
+
+/// Inverse-square pull towards a point, or push away from it for negative strength. The
+/// softening length keeps the acceleration finite at the centre.
+pub struct PointAttractor {
+    pub x: f32,
+    pub y: f32,
+    /// Acceleration times distance squared, in px³/s²
+    pub strength: f32,
+    pub softening: f32,
+}
+
+impl PointAttractor {
+    pub fn attractor(x: f32, y: f32) -> Self {
+        PointAttractor {
+            x,
+            y,
+            strength: 2.0e7,
+            softening: 20.0,
+        }
+    }
+
+    pub fn repulsor(x: f32, y: f32) -> Self {
+        PointAttractor {
+            strength: -2.0e7,
+            ..Self::attractor(x, y)
+        }
+    }
+}
+
+impl ForceField for PointAttractor {
+    fn name(&self) -> &'static str {
+        if self.strength >= 0.0 {
+            "attractor"
+        } else {
+            "repulsor"
+        }
+    }
+
+    fn acceleration(&self, p: &FieldSample) -> (f32, f32) {
+        let (dx, dy) = (self.x - p.x, self.y - p.y);
+        let d_sq = dx * dx + dy * dy + self.softening * self.softening;
+        let scale = self.strength / (d_sq * d_sq.sqrt());
+        (dx * scale, dy * scale)
+    }
+
+    fn position(&self) -> Option<(f32, f32)> {
+        Some((self.x, self.y))
+    }
+
+    fn set_position(&mut self, x: f32, y: f32) {
+        (self.x, self.y) = (x, y);
+    }
+
+    fn strength(&self) -> f32 {
+        self.strength
+    }
+
+    fn set_strength(&mut self, strength: f32) {
+        self.strength = strength;
+    }
+
+    fn render(&self, canvas: &mut Canvas<Window>) {
+        let (x, y) = (self.x as i16, self.y as i16);
+        let color = if self.strength >= 0.0 {
+            (90, 140, 255, 255)
+        } else {
+            (255, 110, 90, 255)
+        };
+        let _ = canvas.circle(x, y, self.softening as i16, color);
+        let _ = canvas.hline(x - 5, x + 5, y, color);
+        if self.strength >= 0.0 {
+            let _ = canvas.vline(x, y - 5, y + 5, color);
+        }
+    }
+}
+
+/// Swirl around a point: tangential acceleration growing linearly inside the core and
+/// falling off as 1/r outside it. Positive strength turns clockwise on screen.
+pub struct Vortex {
+    pub x: f32,
+    pub y: f32,
+    /// Tangential acceleration at the edge of the core, in px/s²
+    pub strength: f32,
+    pub core_radius: f32,
+}
+
+impl Vortex {
+    pub fn new(x: f32, y: f32) -> Self {
+        Vortex {
+            x,
+            y,
+            strength: 800.0,
+            core_radius: 60.0,
+        }
+    }
+}
+
+impl ForceField for Vortex {
+    fn name(&self) -> &'static str {
+        "vortex"
+    }
+
+    fn acceleration(&self, p: &FieldSample) -> (f32, f32) {
+        let (dx, dy) = (p.x - self.x, p.y - self.y);
+        let r = (dx * dx + dy * dy).sqrt();
+        if r < 1e-6 {
+            return (0.0, 0.0);
+        }
+        let magnitude = if r < self.core_radius {
+            self.strength * r / self.core_radius
+        } else {
+            self.strength * self.core_radius / r
+        };
+        (-dy / r * magnitude, dx / r * magnitude)
+    }
+
+    fn position(&self) -> Option<(f32, f32)> {
+        Some((self.x, self.y))
+    }
+
+    fn set_position(&mut self, x: f32, y: f32) {
+        (self.x, self.y) = (x, y);
+    }
+
+    fn strength(&self) -> f32 {
+        self.strength
+    }
+
+    fn set_strength(&mut self, strength: f32) {
+        self.strength = strength;
+    }
+
+    fn render(&self, canvas: &mut Canvas<Window>) {
+        let (x, y) = (self.x as i16, self.y as i16);
+        let r = self.core_radius as i16;
+        let color = (200, 120, 255, 255);
+        let _ = canvas.circle(x, y, r, color);
+        // Tick on the rim pointing along the swirl
+        let tick = if self.strength >= 0.0 { 8 } else { -8 };
+        let _ = canvas.line(x + r, y, x + r, y + tick, color);
+        let _ = canvas.line(x - r, y, x - r, y - tick, color);
+    }
+}
+
+/// Wind blowing along +x with gusts from a smooth noise field that drifts over time.
+pub struct TurbulentWind {
+    /// Mean acceleration along +x, in px/s²
+    pub strength: f32,
+    /// Size of the gusts, in px/s²
+    pub turbulence: f32,
+    /// Typical gust size in px
+    pub scale: f32,
+    /// How fast the gust pattern changes, in noise cells per second
+    pub rate: f32,
+    time: f32,
+}
+
+impl Default for TurbulentWind {
+    fn default() -> Self {
+        TurbulentWind {
+            strength: 300.0,
+            turbulence: 600.0,
+            scale: 150.0,
+            rate: 0.5,
+            time: 0.0,
+        }
+    }
+}
+
+impl ForceField for TurbulentWind {
+    fn name(&self) -> &'static str {
+        "wind"
+    }
+
+    fn acceleration(&self, p: &FieldSample) -> (f32, f32) {
+        let (u, v, w) = (p.x / self.scale, p.y / self.scale, self.time * self.rate);
+        (
+            self.strength + self.turbulence * value_noise3(u, v, w, 1),
+            self.turbulence * value_noise3(u, v, w, 2),
+        )
+    }
+
+    fn advance(&mut self, dt: f32) {
+        self.time += dt;
+    }
+
+    fn strength(&self) -> f32 {
+        self.strength
+    }
+
+    fn set_strength(&mut self, strength: f32) {
+        self.strength = strength;
+    }
+}
+
+/// Stokes drag, a force opposing velocity in proportion to speed and particle size.
+pub struct LinearDrag {
+    pub coefficient: f32,
+}
+
+impl ForceField for LinearDrag {
+    fn name(&self) -> &'static str {
+        "linear drag"
+    }
+
+    fn acceleration(&self, p: &FieldSample) -> (f32, f32) {
+        let k = self.coefficient * p.radius * p.inv_mass;
+        (-k * p.vx, -k * p.vy)
+    }
+
+    fn strength(&self) -> f32 {
+        self.coefficient
+    }
+
+    fn set_strength(&mut self, strength: f32) {
+        self.coefficient = strength;
+    }
+}
+
+/// Air drag at high speed, a force opposing velocity in proportion to speed squared and the
+/// particle's cross-section.
+pub struct QuadraticDrag {
+    pub coefficient: f32,
+}
+
+impl ForceField for QuadraticDrag {
+    fn name(&self) -> &'static str {
+        "quadratic drag"
+    }
+
+    fn acceleration(&self, p: &FieldSample) -> (f32, f32) {
+        let speed = p.vx.hypot(p.vy);
+        let k = self.coefficient * 2.0 * p.radius * speed * p.inv_mass;
+        (-k * p.vx, -k * p.vy)
+    }
+
+    fn strength(&self) -> f32 {
+        self.coefficient
+    }
+
+    fn set_strength(&mut self, strength: f32) {
+        self.coefficient = strength;
+    }
+}
+
+/// The set of fields acting on a particle system.
+pub struct ForceFields {
+    fields: Vec<Box<dyn ForceField>>,
+    /// Sum of the uniform fields, cached when the set changes
+    uniform: (f32, f32),
+    has_local: bool,
+}
+
+impl Default for ForceFields {
+    /// Just standard gravity.
+    fn default() -> Self {
+        let mut fields = Self::none();
+        fields.push(Box::new(UniformGravity::default()));
+        fields
+    }
+}
+
+impl ForceFields {
+    pub fn none() -> Self {
+        ForceFields {
+            fields: Vec::new(),
+            uniform: (0.0, 0.0),
+            has_local: false,
+        }
+    }
+
+    fn refresh(&mut self) {
+        self.uniform = (0.0, 0.0);
+        self.has_local = false;
+        for field in &self.fields {
+            match field.uniform() {
+                Some((ax, ay)) => {
+                    self.uniform.0 += ax;
+                    self.uniform.1 += ay;
+                }
+                None => self.has_local = true,
+            }
+        }
+    }
+
+    pub fn push(&mut self, field: Box<dyn ForceField>) {
+        self.fields.push(field);
+        self.refresh();
+    }
+
+    pub fn remove(&mut self, i: usize) -> Box<dyn ForceField> {
+        let field = self.fields.remove(i);
+        self.refresh();
+        field
+    }
+
+    /// Removes every field with the given name, returning whether there was any.
+    pub fn remove_named(&mut self, name: &str) -> bool {
+        let before = self.fields.len();
+        self.fields.retain(|f| f.name() != name);
+        self.refresh();
+        self.fields.len() != before
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.fields.iter().any(|f| f.name() == name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &dyn ForceField> {
+        self.fields.iter().map(|f| f.as_ref())
+    }
+
+    /// Runs `change` on one field and picks up any change to its uniform acceleration.
+    pub fn modify<F: FnOnce(&mut dyn ForceField)>(&mut self, i: usize, change: F) {
+        change(self.fields[i].as_mut());
+        self.refresh();
+    }
+
+    /// Placed field whose anchor is closest to `(x, y)` and within `max_distance`.
+    pub fn placed_near(&self, x: f32, y: f32, max_distance: f32) -> Option<usize> {
+        self.fields
+            .iter()
+            .enumerate()
+            .filter_map(|(i, f)| f.position().map(|(fx, fy)| (i, (fx - x).hypot(fy - y))))
+            .filter(|&(_, d)| d <= max_distance)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i)
+    }
+
+    pub fn advance(&mut self, dt: f32) {
+        for field in &mut self.fields {
+            field.advance(dt);
+        }
+    }
+
+    /// Summed acceleration of the fields that are the same for every particle.
+    pub fn uniform_acceleration(&self) -> (f32, f32) {
+        self.uniform
+    }
+
+    /// Whether any field depends on the particle, so `local_acceleration` must be evaluated.
+    pub fn has_local(&self) -> bool {
+        self.has_local
+    }
+
+    /// Summed acceleration of the fields that depend on the particle.
+    pub fn local_acceleration(&self, p: &FieldSample) -> (f32, f32) {
+        self.fields
+            .iter()
+            .filter(|f| f.uniform().is_none())
+            .fold((0.0, 0.0), |(ax, ay), f| {
+                let (fx, fy) = f.acceleration(p);
+                (ax + fx, ay + fy)
+            })
+    }
+
+    pub fn render(&self, canvas: &mut Canvas<Window>) {
+        for field in &self.fields {
+            field.render(canvas);
+        }
+    }
+}
@@ -1 +1,12 @@
-pub mod particle;
\ No newline at end of file
+pub mod particle;
+pub mod particle_system;
+pub mod obstacle;
+pub mod force_field;
+pub mod piston;
+pub mod boundary;
+pub mod emitter;
+pub mod layout;
+pub mod constraint;
+pub mod cloth;
+pub mod soft_body;
+pub mod rigid_body;
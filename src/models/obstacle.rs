@@ -0,0 +1,303 @@
+use sdl2::gfx::primitives::DrawRenderer;
+use sdl2::render::{Canvas, RenderTarget};
+use std::fmt::Write as _;
+
+/// Half the thickness of a drawn wall segment; particles collide with the segment's capsule.
+pub const WALL_HALF_WIDTH: f32 = 1.5;
+
+#[derive(Clone, Debug)]
+pub enum Shape {
+    Segment {
+        a: (f32, f32),
+        b: (f32, f32),
+    },
+    Circle {
+        center: (f32, f32),
+        radius: f32,
+    },
+    /// Convex polygon with counter-clockwise vertices (in y-up terms), see `Obstacle::polygon`
+    Polygon {
+        points: Vec<(f32, f32)>,
+    },
+}
+
+/// Static geometry particles bounce off. Obstacles have infinite mass and never move.
+#[derive(Clone, Debug)]
+pub struct Obstacle {
+    pub shape: Shape,
+    pub restitution: f32,
+    pub friction: f32,
+}
+
+/// Penetration of a particle into an obstacle. The normal points from the obstacle towards the
+/// particle centre.
+#[derive(Clone, Copy, Debug)]
+pub struct Contact {
+    pub nx: f32,
+    pub ny: f32,
+    pub depth: f32,
+}
+
+/// Closest point to `p` on the segment `a`-`b`.
+pub fn closest_on_segment(p: (f32, f32), a: (f32, f32), b: (f32, f32)) -> (f32, f32) {
+    let (ex, ey) = (b.0 - a.0, b.1 - a.1);
+    let len_sq = ex * ex + ey * ey;
+    if len_sq <= f32::EPSILON {
+        return a;
+    }
+    let t = (((p.0 - a.0) * ex + (p.1 - a.1) * ey) / len_sq).clamp(0.0, 1.0);
+    (a.0 + ex * t, a.1 + ey * t)
+}
+
+/// Convex hull of a point set (monotone chain), or `None` if the points are collinear.
+pub fn convex_hull(points: &[(f32, f32)]) -> Option<Vec<(f32, f32)>> {
+    let mut sorted = points.to_vec();
+    sorted.sort_by(|p, q| p.0.total_cmp(&q.0).then(p.1.total_cmp(&q.1)));
+    sorted.dedup();
+    if sorted.len() < 3 {
+        return None;
+    }
+
+    let cross = |o: (f32, f32), a: (f32, f32), b: (f32, f32)| {
+        (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+    };
+    let turns_left = |hull: &[(f32, f32)], p: (f32, f32)| {
+        cross(hull[hull.len() - 2], hull[hull.len() - 1], p) > 0.0
+    };
+    // Lower chain left to right, then upper chain back, each dropping right turns
+    let mut hull: Vec<(f32, f32)> = Vec::with_capacity(2 * sorted.len());
+    for &p in &sorted {
+        while hull.len() >= 2 && !turns_left(&hull, p) {
+            hull.pop();
+        }
+        hull.push(p);
+    }
+    let lower_len = hull.len() + 1;
+    for &p in sorted.iter().rev().skip(1) {
+        while hull.len() >= lower_len && !turns_left(&hull, p) {
+            hull.pop();
+        }
+        hull.push(p);
+    }
+    // The upper chain ends on the first point again
+    hull.pop();
+    (hull.len() >= 3).then_some(hull)
+}
+
+/// Edges of a closed polygon, including the one from the last vertex back to the first.
+pub fn polygon_edges(points: &[(f32, f32)]) -> impl Iterator<Item = ((f32, f32), (f32, f32))> + '_ {
+    (0..points.len()).map(move |k| (points[k], points[(k + 1) % points.len()]))
+}
+
+impl Shape {
+    fn keyword(&self) -> &'static str {
+        match self {
+            Shape::Segment { .. } => "segment",
+            Shape::Circle { .. } => "circle",
+            Shape::Polygon { .. } => "polygon",
+        }
+    }
+}
+
+impl Obstacle {
+    pub fn segment(a: (f32, f32), b: (f32, f32), restitution: f32, friction: f32) -> Self {
+        Obstacle {
+            shape: Shape::Segment { a, b },
+            restitution,
+            friction,
+        }
+    }
+
+    pub fn circle(center: (f32, f32), radius: f32, restitution: f32, friction: f32) -> Self {
+        Obstacle {
+            shape: Shape::Circle { center, radius },
+            restitution,
+            friction,
+        }
+    }
+
+    /// Convex polygon around the given points. Points inside the hull are dropped, so any click
+    /// order works; returns `None` if the points do not enclose an area.
+    pub fn polygon(points: &[(f32, f32)], restitution: f32, friction: f32) -> Option<Self> {
+        Some(Obstacle {
+            shape: Shape::Polygon {
+                points: convex_hull(points)?,
+            },
+            restitution,
+            friction,
+        })
+    }
+
+    /// Bounding box as `(min_x, min_y, max_x, max_y)`.
+    pub fn bounds(&self) -> (f32, f32, f32, f32) {
+        match &self.shape {
+            Shape::Segment { a, b } => (
+                a.0.min(b.0) - WALL_HALF_WIDTH,
+                a.1.min(b.1) - WALL_HALF_WIDTH,
+                a.0.max(b.0) + WALL_HALF_WIDTH,
+                a.1.max(b.1) + WALL_HALF_WIDTH,
+            ),
+            Shape::Circle { center, radius } => (
+                center.0 - radius,
+                center.1 - radius,
+                center.0 + radius,
+                center.1 + radius,
+            ),
+            Shape::Polygon { points } => points.iter().fold(
+                (f32::MAX, f32::MAX, f32::MIN, f32::MIN),
+                |(x0, y0, x1, y1), &(x, y)| (x0.min(x), y0.min(y), x1.max(x), y1.max(y)),
+            ),
+        }
+    }
+
+    /// Contact with a circle of the given radius centred at `(x, y)`, if they overlap.
+    pub fn contact(&self, x: f32, y: f32, radius: f32) -> Option<Contact> {
+        let towards = |from: (f32, f32), reach: f32| {
+            let (dx, dy) = (x - from.0, y - from.1);
+            let dist = (dx * dx + dy * dy).sqrt();
+            if dist >= reach {
+                None
+            } else if dist < 1e-6 {
+                // Centre exactly on the surface: push straight up
+                Some(Contact {
+                    nx: 0.0,
+                    ny: -1.0,
+                    depth: reach,
+                })
+            } else {
+                Some(Contact {
+                    nx: dx / dist,
+                    ny: dy / dist,
+                    depth: reach - dist,
+                })
+            }
+        };
+
+        match &self.shape {
+            Shape::Segment { a, b } => {
+                towards(closest_on_segment((x, y), *a, *b), radius + WALL_HALF_WIDTH)
+            }
+            Shape::Circle { center, radius: r } => towards(*center, radius + r),
+            Shape::Polygon { points } => {
+                // Signed distance to each edge's supporting line, positive outside
+                let mut deepest = (f32::MIN, 0.0, 0.0);
+                for (a, b) in polygon_edges(points) {
+                    let (ex, ey) = (b.0 - a.0, b.1 - a.1);
+                    let len = (ex * ex + ey * ey).sqrt();
+                    let (nx, ny) = (ey / len, -ex / len);
+                    let s = (x - a.0) * nx + (y - a.1) * ny;
+                    if s > deepest.0 {
+                        deepest = (s, nx, ny);
+                    }
+                }
+                let (s, nx, ny) = deepest;
+                if s <= 0.0 {
+                    // Centre inside: leave through the nearest face
+                    return Some(Contact {
+                        nx,
+                        ny,
+                        depth: radius - s,
+                    });
+                }
+                let closest = polygon_edges(points)
+                    .map(|(a, b)| closest_on_segment((x, y), a, b))
+                    .min_by(|p, q| {
+                        let dp = (p.0 - x).powi(2) + (p.1 - y).powi(2);
+                        let dq = (q.0 - x).powi(2) + (q.1 - y).powi(2);
+                        dp.total_cmp(&dq)
+                    })?;
+                towards(closest, radius)
+            }
+        }
+    }
+
+    pub fn render<T: RenderTarget>(&self, canvas: &mut Canvas<T>) {
+        let color = (160, 160, 170, 255);
+        match &self.shape {
+            Shape::Segment { a, b } => {
+                let _ = canvas.thick_line(
+                    a.0 as i16,
+                    a.1 as i16,
+                    b.0 as i16,
+                    b.1 as i16,
+                    (2.0 * WALL_HALF_WIDTH) as u8,
+                    color,
+                );
+            }
+            Shape::Circle { center, radius } => {
+                let _ =
+                    canvas.filled_circle(center.0 as i16, center.1 as i16, *radius as i16, color);
+            }
+            Shape::Polygon { points } => {
+                let xs: Vec<i16> = points.iter().map(|p| p.0 as i16).collect();
+                let ys: Vec<i16> = points.iter().map(|p| p.1 as i16).collect();
+                let _ = canvas.filled_polygon(&xs, &ys, color);
+            }
+        }
+    }
+}
+
+impl Obstacle {
+    /// The obstacle as one line of a layout file:
+    ///
+    /// ```text
+    /// segment <restitution> <friction> <x1> <y1> <x2> <y2>
+    /// circle <restitution> <friction> <x> <y> <radius>
+    /// polygon <restitution> <friction> <x1> <y1> <x2> <y2> ...
+    /// ```
+    pub fn to_line(&self) -> String {
+        let mut numbers = vec![self.restitution, self.friction];
+        match &self.shape {
+            Shape::Segment { a, b } => numbers.extend([a.0, a.1, b.0, b.1]),
+            Shape::Circle { center, radius } => numbers.extend([center.0, center.1, *radius]),
+            Shape::Polygon { points } => numbers.extend(points.iter().flat_map(|p| [p.0, p.1])),
+        }
+        let mut line = self.shape.keyword().to_string();
+        for n in numbers {
+            let _ = write!(line, " {}", n);
+        }
+        line
+    }
+
+    /// Whether a layout line starting with `keyword` describes an obstacle.
+    pub fn is_keyword(keyword: &str) -> bool {
+        matches!(keyword, "segment" | "circle" | "polygon")
+    }
+
+    /// Reads the values that follow the keyword of a line written by `to_line`.
+    pub fn parse(keyword: &str, words: &[&str]) -> Result<Self, String> {
+        let numbers = words
+            .iter()
+            .map(|w| w.parse::<f32>())
+            .collect::<Result<Vec<f32>, _>>()
+            .map_err(|e| e.to_string())?;
+        if numbers.len() < 2 {
+            return Err("missing restitution and friction".to_string());
+        }
+        let (restitution, friction, rest) = (numbers[0], numbers[1], &numbers[2..]);
+
+        match (keyword, rest.len()) {
+            ("segment", 4) => Ok(Obstacle::segment(
+                (rest[0], rest[1]),
+                (rest[2], rest[3]),
+                restitution,
+                friction,
+            )),
+            ("circle", 3) => Ok(Obstacle::circle(
+                (rest[0], rest[1]),
+                rest[2],
+                restitution,
+                friction,
+            )),
+            ("polygon", n) if n >= 6 && n % 2 == 0 => {
+                let points: Vec<(f32, f32)> = rest.chunks(2).map(|c| (c[0], c[1])).collect();
+                Obstacle::polygon(&points, restitution, friction)
+                    .ok_or_else(|| "polygon has no area".to_string())
+            }
+            ("segment" | "circle" | "polygon", _) => {
+                Err(format!("wrong number of values for {}", keyword))
+            }
+            _ => Err(format!("unknown obstacle '{}'", keyword)),
+        }
+    }
+}
@@ -1,99 +1,86 @@
-use sdl2::gfx::primitives::DrawRenderer;
-use sdl2::pixels::Color;
-
-const GRAVITY: f32 = 980.0;
-const ELASTICITY: f32 = 1.0;
-const TRACE_LIMIT: usize = 20;
+pub const TRACE_LIMIT: usize = 20;
+/// Mass per square pixel used when a particle's mass is derived from its radius.
+pub const DEFAULT_DENSITY: f32 = 0.01;
+const DEFAULT_RESTITUTION: f32 = 1.0;
+const DEFAULT_FRICTION: f32 = 0.0;
 
+/// A single particle description. Simulated particles live in a `ParticleSystem`; this is the
+/// value used to spawn them.
 #[derive(Clone)]
-pub struct Trace {
-    pub x: f32,
-    pub y: f32,
-}
-
 pub struct Particle {
     pub x: f32,
     pub y: f32,
     pub vx: f32,
     pub vy: f32,
-    pub radius: i32,
-    pub traces: Vec<Trace>,
+    pub radius: f32,
+    pub mass: f32,
+    /// Fraction of normal velocity kept after a bounce (1.0 is perfectly elastic)
+    pub restitution: f32,
+    /// Coulomb friction coefficient applied to the tangential velocity on contact
+    pub friction: f32,
+    /// Electric charge, in the units of the scene using it
+    pub charge: f32,
+    /// Drawing colour; without one the particle is shaded by its density
+    pub color: Option<(u8, u8, u8)>,
 }
 
 impl Particle {
-    pub fn new(x: f32, y: f32, vx: f32, vy: f32, radius: i32) -> Self {
-        let particle = Particle {
+    pub fn new(x: f32, y: f32, vx: f32, vy: f32, radius: f32) -> Self {
+        Particle {
             x,
             y,
             vx,
             vy,
             radius,
-            traces: vec![Trace { x, y }],
-        };
-        particle
+            mass: Self::area(radius) * DEFAULT_DENSITY,
+            restitution: DEFAULT_RESTITUTION,
+            friction: DEFAULT_FRICTION,
+            charge: 0.0,
+            color: None,
+        }
     }
 
-    pub fn update(&mut self, dt: f32, screen_w: u32, screen_h: u32, enable_traces: bool) {
-        self.vy += GRAVITY * dt;
-
-        self.x += self.vx * dt;
-        self.y += self.vy * dt;
-
-        let r = self.radius as f32;
-        if self.x - r < 0.0 {
-            self.x = r;
-            self.vx *= -ELASTICITY;
-        } else if self.x + r > screen_w as f32 {
-            self.x = (screen_w as f32) - r;
-            self.vx *= -ELASTICITY;
-        }
-        if self.y - r < 0.0 {
-            self.y = r;
-            self.vy *= -ELASTICITY;
-        } else if self.y + r > screen_h as f32 {
-            self.y = (screen_h as f32) - r;
-            self.vy *= -ELASTICITY;
-        }
+    fn area(radius: f32) -> f32 {
+        std::f32::consts::PI * radius * radius
+    }
 
-        if enable_traces {
-            self.traces.push(Trace {
-                x: self.x,
-                y: self.y,
-            });
-            if self.traces.len() > TRACE_LIMIT {
-                self.traces.remove(0);
-            }
-        } else {
-            self.traces.clear();
-        }
+    /// Derives the mass from the radius and the given density.
+    pub fn with_density(mut self, density: f32) -> Self {
+        self.mass = Self::area(self.radius) * density;
+        self
     }
 
-    pub fn render<T: sdl2::render::RenderTarget>(
-        &self,
-        canvas: &mut sdl2::render::Canvas<T>,
-        enable_traces: bool,
-    ) {
-        let x_i16 = self.x as i16;
-        let y_i16 = self.y as i16;
-        let r_i16 = self.radius as i16;
+    pub fn with_mass(mut self, mass: f32) -> Self {
+        self.mass = mass;
+        self
+    }
 
-        let _ = canvas.filled_circle(x_i16, y_i16, r_i16, Color::RGBA(0, 255, 0, 255));
+    pub fn with_restitution(mut self, restitution: f32) -> Self {
+        self.restitution = restitution;
+        self
+    }
 
-        if enable_traces {
-            let size = self.traces.len();
-            for (i, trace) in self.traces.iter().enumerate() {
-                let scaling_factor = (i as f32 + 1.0) / size as f32;
-                let scaled_radius = (self.radius as f32 * scaling_factor * 0.7) as i16;
-                let tx = trace.x as i16;
-                let ty = trace.y as i16;
+    pub fn with_friction(mut self, friction: f32) -> Self {
+        self.friction = friction;
+        self
+    }
 
-                let _ = canvas.filled_circle(tx, ty, scaled_radius, Color::RGBA(0, 255, 0, 255));
-            }
-        }
+    pub fn with_charge(mut self, charge: f32) -> Self {
+        self.charge = charge;
+        self
     }
 
-    pub fn set_position(&mut self, nx: f32, ny: f32) {
-        self.x = nx;
-        self.y = ny;
+    pub fn with_color(mut self, color: (u8, u8, u8)) -> Self {
+        self.color = Some(color);
+        self
     }
 }
+
+/// Reflects the velocity component normal to a wall and applies friction to the tangential one.
+/// Returns the new `(normal, tangent)` pair.
+pub fn wall_bounce(normal: f32, tangent: f32, restitution: f32, friction: f32) -> (f32, f32) {
+    let new_normal = -normal * restitution;
+    let max_friction = friction * (new_normal - normal).abs();
+    let new_tangent = tangent - tangent.signum() * tangent.abs().min(max_friction);
+    (new_normal, new_tangent)
+}
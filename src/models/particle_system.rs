@@ -0,0 +1,518 @@
+use crate::models::boundary::{Boundaries, BoundaryMode};
+use crate::models::force_field::{FieldSample, ForceFields};
+use crate::models::particle::{wall_bounce, Particle, DEFAULT_DENSITY, TRACE_LIMIT};
+use crate::utils::sleep::SleepState;
+use rayon::prelude::*;
+use sdl2::gfx::primitives::DrawRenderer;
+use sdl2::pixels::Color;
+use sdl2::render::{Canvas, RenderTarget};
+
+/// Particles handed to one worker thread at a time by `par_integrate`.
+const CHUNK_SIZE: usize = 4096;
+
+/// Structure-of-arrays particle storage.
+///
+/// Each attribute lives in its own contiguous array indexed by particle, so the integration loop
+/// streams through memory and can be auto-vectorised. Trails are not stored per particle but as
+/// a shared ring of position snapshots.
+#[derive(Default)]
+pub struct ParticleSystem {
+    pub x: Vec<f32>,
+    pub y: Vec<f32>,
+    pub vx: Vec<f32>,
+    pub vy: Vec<f32>,
+    pub radius: Vec<f32>,
+    pub mass: Vec<f32>,
+    pub restitution: Vec<f32>,
+    pub friction: Vec<f32>,
+    pub charge: Vec<f32>,
+    pub color: Vec<Option<(u8, u8, u8)>>,
+    /// Identifier that stays with a particle while others are removed around it. Ids are
+    /// handed out in increasing order and removal keeps the order, so this is always sorted.
+    pub id: Vec<u64>,
+    next_id: u64,
+    /// Sleeping particles are left where they are by `integrate` and treated as immovable by
+    /// the contact solver
+    pub sleep: Vec<SleepState>,
+    /// What the screen edges do, reflective on both axes by default
+    pub boundaries: Boundaries,
+    trails: TrailBuffer,
+    /// Scratch keep-mask for `retain`, kept so that compaction does not allocate
+    mask: Vec<bool>,
+}
+
+/// Momentum the particles handed to each screen edge, summed over one or more steps.
+///
+/// Every bounce adds m |Δv| along the wall normal, so dividing by the elapsed time and the
+/// wall length gives the pressure on that wall.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct WallImpulses {
+    pub left: f32,
+    pub right: f32,
+    pub top: f32,
+    pub bottom: f32,
+}
+
+impl WallImpulses {
+    pub fn total(&self) -> f32 {
+        self.left + self.right + self.top + self.bottom
+    }
+}
+
+impl std::ops::Add for WallImpulses {
+    type Output = WallImpulses;
+
+    fn add(self, other: WallImpulses) -> WallImpulses {
+        WallImpulses {
+            left: self.left + other.left,
+            right: self.right + other.right,
+            top: self.top + other.top,
+            bottom: self.bottom + other.bottom,
+        }
+    }
+}
+
+impl std::ops::AddAssign for WallImpulses {
+    fn add_assign(&mut self, other: WallImpulses) {
+        *self = *self + other;
+    }
+}
+
+/// Borrowed view over a contiguous range of particles, used to integrate one chunk.
+struct ChunkMut<'a> {
+    x: &'a mut [f32],
+    y: &'a mut [f32],
+    vx: &'a mut [f32],
+    vy: &'a mut [f32],
+    radius: &'a [f32],
+    mass: &'a [f32],
+    restitution: &'a [f32],
+    friction: &'a [f32],
+    sleep: &'a [SleepState],
+    boundaries: Boundaries,
+}
+
+impl ChunkMut<'_> {
+    fn integrate(self, dt: f32, width: f32, height: f32, fields: &ForceFields) -> WallImpulses {
+        // Straight-line loops over plain slices, which the compiler turns into SIMD. Sleeping
+        // particles take a step of zero, computed from their state instead of branching on it.
+        let step = |s: &SleepState| dt * f32::from(u8::from(!s.is_asleep()));
+        let (gx, gy) = fields.uniform_acceleration();
+        for (vx, s) in self.vx.iter_mut().zip(self.sleep) {
+            *vx += gx * step(s);
+        }
+        for (vy, s) in self.vy.iter_mut().zip(self.sleep) {
+            *vy += gy * step(s);
+        }
+        if fields.has_local() {
+            for i in 0..self.x.len() {
+                if self.sleep[i].is_asleep() {
+                    continue;
+                }
+                let mass = self.mass[i];
+                let (ax, ay) = fields.local_acceleration(&FieldSample {
+                    x: self.x[i],
+                    y: self.y[i],
+                    vx: self.vx[i],
+                    vy: self.vy[i],
+                    radius: self.radius[i],
+                    inv_mass: if mass > 0.0 { 1.0 / mass } else { 0.0 },
+                });
+                self.vx[i] += ax * dt;
+                self.vy[i] += ay * dt;
+            }
+        }
+        for ((x, vx), s) in self.x.iter_mut().zip(self.vx.iter()).zip(self.sleep) {
+            *x += vx * step(s);
+        }
+        for ((y, vy), s) in self.y.iter_mut().zip(self.vy.iter()).zip(self.sleep) {
+            *y += vy * step(s);
+        }
+
+        let mut impulses = WallImpulses::default();
+        let Boundaries {
+            x: x_mode,
+            y: y_mode,
+        } = self.boundaries;
+        for i in 0..self.x.len() {
+            let (r, e, mu, m) = (
+                self.radius[i],
+                self.restitution[i],
+                self.friction[i],
+                self.mass[i],
+            );
+            let (vx, vy) = (self.vx[i], self.vy[i]);
+            match x_mode {
+                BoundaryMode::Reflective => {
+                    if self.x[i] - r < 0.0 {
+                        self.x[i] = r;
+                        (self.vx[i], self.vy[i]) = wall_bounce(self.vx[i], self.vy[i], e, mu);
+                        impulses.left += m * (self.vx[i] - vx).abs();
+                    } else if self.x[i] + r > width {
+                        self.x[i] = width - r;
+                        (self.vx[i], self.vy[i]) = wall_bounce(self.vx[i], self.vy[i], e, mu);
+                        impulses.right += m * (self.vx[i] - vx).abs();
+                    }
+                }
+                BoundaryMode::Periodic => self.x[i] = self.x[i].rem_euclid(width),
+                // Removed by `remove_escaped` once they are out
+                BoundaryMode::Open | BoundaryMode::Inflow { .. } => {}
+            }
+            match y_mode {
+                BoundaryMode::Reflective => {
+                    if self.y[i] - r < 0.0 {
+                        self.y[i] = r;
+                        (self.vy[i], self.vx[i]) = wall_bounce(self.vy[i], self.vx[i], e, mu);
+                        impulses.top += m * (self.vy[i] - vy).abs();
+                    } else if self.y[i] + r > height {
+                        self.y[i] = height - r;
+                        (self.vy[i], self.vx[i]) = wall_bounce(self.vy[i], self.vx[i], e, mu);
+                        impulses.bottom += m * (self.vy[i] - vy).abs();
+                    }
+                }
+                BoundaryMode::Periodic => self.y[i] = self.y[i].rem_euclid(height),
+                BoundaryMode::Open | BoundaryMode::Inflow { .. } => {}
+            }
+        }
+        impulses
+    }
+}
+
+impl ParticleSystem {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.x.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.x.is_empty()
+    }
+
+    pub fn push(&mut self, p: Particle) {
+        self.x.push(p.x);
+        self.y.push(p.y);
+        self.vx.push(p.vx);
+        self.vy.push(p.vy);
+        self.radius.push(p.radius);
+        self.mass.push(p.mass);
+        self.restitution.push(p.restitution);
+        self.friction.push(p.friction);
+        self.charge.push(p.charge);
+        self.color.push(p.color);
+        self.id.push(self.next_id);
+        self.next_id += 1;
+        self.sleep.push(SleepState::default());
+    }
+
+    pub fn clear(&mut self) {
+        self.x.clear();
+        self.y.clear();
+        self.vx.clear();
+        self.vy.clear();
+        self.radius.clear();
+        self.mass.clear();
+        self.restitution.clear();
+        self.friction.clear();
+        self.charge.clear();
+        self.color.clear();
+        self.id.clear();
+        self.sleep.clear();
+        self.trails.clear();
+    }
+
+    /// Keeps only the particles for which `keep(i)` is true, preserving their order. Trail
+    /// frames are compacted the same way.
+    pub fn retain<F: Fn(usize) -> bool>(&mut self, keep: F) {
+        let mut mask = std::mem::take(&mut self.mask);
+        mask.clear();
+        mask.extend((0..self.len()).map(keep));
+        self.mask = mask;
+        self.retain_mask();
+    }
+
+    /// Compacts every attribute down to the particles marked in `self.mask`.
+    fn retain_mask(&mut self) {
+        let mask = &self.mask;
+        for values in [
+            &mut self.x,
+            &mut self.y,
+            &mut self.vx,
+            &mut self.vy,
+            &mut self.radius,
+            &mut self.mass,
+            &mut self.restitution,
+            &mut self.friction,
+            &mut self.charge,
+        ] {
+            retain_masked(values, mask);
+        }
+        retain_masked(&mut self.color, mask);
+        retain_masked(&mut self.id, mask);
+        retain_masked(&mut self.sleep, mask);
+        self.trails.retain_masked(mask);
+    }
+
+    /// Current index of the particle with the given id, if it still exists.
+    pub fn index_of(&self, id: u64) -> Option<usize> {
+        self.id.binary_search(&id).ok()
+    }
+
+    /// Index of the particle under the point `(x, y)`, the closest one if several overlap.
+    pub fn at(&self, x: f32, y: f32) -> Option<usize> {
+        (0..self.len())
+            .map(|i| (i, (self.x[i] - x).powi(2) + (self.y[i] - y).powi(2)))
+            .filter(|&(i, d_sq)| d_sq <= self.radius[i] * self.radius[i])
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i)
+    }
+
+    /// Accelerates particles within `radius` of `(cx, cy)` away from it, by `accel` at the
+    /// centre falling off linearly to nothing at the edge.
+    pub fn push_away(&mut self, (cx, cy): (f32, f32), radius: f32, accel: f32, dt: f32) {
+        for i in 0..self.len() {
+            let (dx, dy) = (self.x[i] - cx, self.y[i] - cy);
+            let dist = (dx * dx + dy * dy).sqrt();
+            if dist >= radius || dist < 1e-6 {
+                continue;
+            }
+            let dv = accel * (1.0 - dist / radius) * dt;
+            self.vx[i] += dx / dist * dv;
+            self.vy[i] += dy / dist * dv;
+        }
+    }
+
+    pub fn inverse_mass(&self, i: usize) -> f32 {
+        if self.mass[i] > 0.0 {
+            1.0 / self.mass[i]
+        } else {
+            0.0
+        }
+    }
+
+    pub fn max_radius(&self) -> f32 {
+        self.radius.iter().fold(0.0f32, |m, &r| m.max(r))
+    }
+
+    pub fn positions(&self) -> impl ExactSizeIterator<Item = (f32, f32)> + '_ {
+        self.x.iter().copied().zip(self.y.iter().copied())
+    }
+
+    fn chunk(&mut self) -> ChunkMut<'_> {
+        ChunkMut {
+            x: &mut self.x,
+            y: &mut self.y,
+            vx: &mut self.vx,
+            vy: &mut self.vy,
+            radius: &self.radius,
+            mass: &self.mass,
+            restitution: &self.restitution,
+            friction: &self.friction,
+            sleep: &self.sleep,
+            boundaries: self.boundaries,
+        }
+    }
+
+    /// Applies the force fields, moves every particle and bounces it off the screen edges, on
+    /// the calling thread. Returns the momentum handed to each edge.
+    pub fn integrate(
+        &mut self,
+        fields: &ForceFields,
+        dt: f32,
+        screen_w: u32,
+        screen_h: u32,
+    ) -> WallImpulses {
+        self.chunk()
+            .integrate(dt, screen_w as f32, screen_h as f32, fields)
+    }
+
+    /// Same as `integrate`, split into chunks run on the current rayon pool.
+    pub fn par_integrate(
+        &mut self,
+        fields: &ForceFields,
+        dt: f32,
+        screen_w: u32,
+        screen_h: u32,
+    ) -> WallImpulses {
+        let (width, height) = (screen_w as f32, screen_h as f32);
+        let boundaries = self.boundaries;
+        (
+            self.x.par_chunks_mut(CHUNK_SIZE),
+            self.y.par_chunks_mut(CHUNK_SIZE),
+            self.vx.par_chunks_mut(CHUNK_SIZE),
+            self.vy.par_chunks_mut(CHUNK_SIZE),
+            self.radius.par_chunks(CHUNK_SIZE),
+            self.mass.par_chunks(CHUNK_SIZE),
+            self.restitution.par_chunks(CHUNK_SIZE),
+            self.friction.par_chunks(CHUNK_SIZE),
+            self.sleep.par_chunks(CHUNK_SIZE),
+        )
+            .into_par_iter()
+            .map(
+                |(x, y, vx, vy, radius, mass, restitution, friction, sleep)| {
+                    ChunkMut {
+                        x,
+                        y,
+                        vx,
+                        vy,
+                        radius,
+                        mass,
+                        restitution,
+                        friction,
+                        sleep,
+                        boundaries,
+                    }
+                    .integrate(dt, width, height, fields)
+                },
+            )
+            .reduce(WallImpulses::default, |a, b| a + b)
+    }
+
+    /// Deletes the particles that are entirely outside `0..width` by `0..height` along an
+    /// open or inflow axis, and returns how many went.
+    pub fn remove_escaped(&mut self, width: f32, height: f32) -> usize {
+        let Boundaries {
+            x: x_mode,
+            y: y_mode,
+        } = self.boundaries;
+        if !x_mode.absorbs() && !y_mode.absorbs() {
+            return 0;
+        }
+        let outside = |pos: f32, r: f32, length: f32| pos + r < 0.0 || pos - r > length;
+        let (x, y, radius) = (&self.x, &self.y, &self.radius);
+        let escaped = |i: usize| {
+            x_mode.absorbs() && outside(x[i], radius[i], width)
+                || y_mode.absorbs() && outside(y[i], radius[i], height)
+        };
+        let count = (0..self.len()).filter(|&i| escaped(i)).count();
+        if count == 0 {
+            return 0;
+        }
+        self.mask.clear();
+        self.mask.extend((0..self.len()).map(|i| !escaped(i)));
+        self.retain_mask();
+        count
+    }
+
+    /// Records the current positions as the newest trail frame, or drops the trails.
+    pub fn update_trails(&mut self, enable_traces: bool) {
+        if enable_traces {
+            self.trails.record(&self.x, &self.y);
+        } else {
+            self.trails.clear();
+        }
+    }
+
+    /// The particle's own colour if it has one, otherwise green for the default density,
+    /// shading to red for particles ten times denser. Sleeping particles are drawn darker.
+    fn color(&self, i: usize) -> Color {
+        let (r, g, b) = self.color[i].unwrap_or_else(|| {
+            let density = self.mass[i] / (std::f32::consts::PI * self.radius[i] * self.radius[i]);
+            let heaviness = ((density / DEFAULT_DENSITY - 1.0) / 9.0).clamp(0.0, 1.0);
+            (
+                (255.0 * heaviness) as u8,
+                (255.0 * (1.0 - heaviness)) as u8,
+                0,
+            )
+        });
+        if self.sleep[i].is_asleep() {
+            Color::RGBA(r / 2, g / 2, b / 2, 255)
+        } else {
+            Color::RGBA(r, g, b, 255)
+        }
+    }
+
+    pub fn render<T: RenderTarget>(&self, canvas: &mut Canvas<T>, enable_traces: bool) {
+        if enable_traces {
+            let size = self.trails.len();
+            for (k, (xs, ys)) in self.trails.frames().enumerate() {
+                let scaling_factor = (k as f32 + 1.0) / size as f32;
+                // Particles spawned after this frame was recorded have no entry in it
+                for i in 0..xs.len().min(self.len()) {
+                    let scaled_radius = (self.radius[i] * scaling_factor * 0.7) as i16;
+                    let _ = canvas.filled_circle(
+                        xs[i] as i16,
+                        ys[i] as i16,
+                        scaled_radius,
+                        self.color(i),
+                    );
+                }
+            }
+        }
+
+        for i in 0..self.len() {
+            let _ = canvas.filled_circle(
+                self.x[i] as i16,
+                self.y[i] as i16,
+                self.radius[i] as i16,
+                self.color(i),
+            );
+        }
+    }
+}
+
+/// Keeps the elements whose entry in `mask` is true. Elements past the end of the mask are
+/// dropped.
+fn retain_masked<T: Copy>(values: &mut Vec<T>, mask: &[bool]) {
+    let mut kept = 0;
+    for i in 0..values.len().min(mask.len()) {
+        if mask[i] {
+            values[kept] = values[i];
+            kept += 1;
+        }
+    }
+    values.truncate(kept);
+}
+
+/// Ring of the last `TRACE_LIMIT` position snapshots, shared by all particles. Slots keep their
+/// allocation when overwritten, so recording a frame is two memcpys.
+#[derive(Default)]
+struct TrailBuffer {
+    xs: Vec<Vec<f32>>,
+    ys: Vec<Vec<f32>>,
+    /// Slot the next frame will be written to
+    head: usize,
+    len: usize,
+}
+
+impl TrailBuffer {
+    fn record(&mut self, x: &[f32], y: &[f32]) {
+        if self.xs.len() < TRACE_LIMIT {
+            self.xs.resize_with(TRACE_LIMIT, Vec::new);
+            self.ys.resize_with(TRACE_LIMIT, Vec::new);
+        }
+        self.xs[self.head].clear();
+        self.xs[self.head].extend_from_slice(x);
+        self.ys[self.head].clear();
+        self.ys[self.head].extend_from_slice(y);
+        self.head = (self.head + 1) % TRACE_LIMIT;
+        self.len = (self.len + 1).min(TRACE_LIMIT);
+    }
+
+    /// Drops removed particles from every stored frame.
+    fn retain_masked(&mut self, mask: &[bool]) {
+        for frame in self.xs.iter_mut().chain(self.ys.iter_mut()) {
+            retain_masked(frame, mask);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.head = 0;
+        self.len = 0;
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Stored frames from oldest to newest.
+    fn frames(&self) -> impl Iterator<Item = (&[f32], &[f32])> {
+        let oldest = (self.head + TRACE_LIMIT - self.len) % TRACE_LIMIT;
+        (0..self.len).map(move |k| {
+            let slot = (oldest + k) % TRACE_LIMIT;
+            (self.xs[slot].as_slice(), self.ys[slot].as_slice())
+        })
+    }
+}
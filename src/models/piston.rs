@@ -0,0 +1,119 @@
+use crate::models::particle_system::ParticleSystem;
+use sdl2::gfx::primitives::DrawRenderer;
+use sdl2::render::{Canvas, RenderTarget};
+
+/// Width of the piston head as drawn.
+const HEAD_WIDTH: f32 = 12.0;
+/// Closest the piston may come to the left wall, enough for the largest particles.
+pub const MIN_GAP: f32 = 60.0;
+
+/// What moves the piston.
+#[derive(Clone, Copy, PartialEq)]
+pub enum PistonMotion {
+    /// Stays where it is unless dragged with the mouse
+    Manual,
+    /// Swings between `low` and `high` as a cosine with `period` seconds, starting at `high`
+    Oscillate { low: f32, high: f32, period: f32 },
+}
+
+/// Vertical wall that closes the container from the right and can be moved to compress or
+/// expand the gas. Particles bounce off it in its own frame, so a moving piston does work on
+/// the gas: pushing in heats it, pulling out cools it.
+pub struct Piston {
+    pub x: f32,
+    /// Average velocity over the last `advance`, used for the bounces
+    pub velocity: f32,
+    pub motion: PistonMotion,
+    /// Where the mouse wants the piston in manual mode
+    target: Option<f32>,
+    time: f32,
+}
+
+impl Piston {
+    pub fn new(x: f32) -> Self {
+        Piston {
+            x,
+            velocity: 0.0,
+            motion: PistonMotion::Manual,
+            target: None,
+            time: 0.0,
+        }
+    }
+
+    /// Asks for the piston at `x`, reached on the next `advance`. Ignored while oscillating.
+    pub fn drag_to(&mut self, x: f32) {
+        self.target = Some(x);
+    }
+
+    /// Whether a click at `x` lands on the piston head.
+    pub fn is_near(&self, x: f32) -> bool {
+        (self.x - HEAD_WIDTH..=self.x + 2.0 * HEAD_WIDTH).contains(&x)
+    }
+
+    /// Starts the scripted motion from the top of the cosine.
+    pub fn set_motion(&mut self, motion: PistonMotion) {
+        self.motion = motion;
+        self.target = None;
+        self.time = 0.0;
+    }
+
+    /// Moves the piston by `dt` and keeps it between `MIN_GAP` and `max_x`.
+    pub fn advance(&mut self, dt: f32, max_x: f32) {
+        let previous = self.x;
+        let wanted = match self.motion {
+            PistonMotion::Manual => self.target.take().unwrap_or(self.x),
+            PistonMotion::Oscillate { low, high, period } => {
+                self.time += dt;
+                let phase = 2.0 * std::f32::consts::PI * self.time / period;
+                low + (high - low) * 0.5 * (1.0 + phase.cos())
+            }
+        };
+        self.x = wanted.clamp(MIN_GAP, max_x.max(MIN_GAP));
+        self.velocity = if dt != 0.0 {
+            (self.x - previous) / dt
+        } else {
+            0.0
+        };
+    }
+
+    /// Puts every particle that reached the piston back against it and reflects its
+    /// velocity relative to the piston, using the particle's restitution. The head is
+    /// frictionless. Returns the momentum handed to the piston.
+    pub fn confine(&self, system: &mut ParticleSystem) -> f32 {
+        let mut impulse = 0.0;
+        for i in 0..system.len() {
+            let r = system.radius[i];
+            if system.x[i] + r <= self.x {
+                continue;
+            }
+            system.x[i] = self.x - r;
+            let relative = system.vx[i] - self.velocity;
+            if relative > 0.0 {
+                let vx = self.velocity - system.restitution[i] * relative;
+                impulse += system.mass[i] * (system.vx[i] - vx);
+                system.vx[i] = vx;
+            }
+        }
+        impulse
+    }
+
+    /// Draws the head and its rod out to the right edge of the screen.
+    pub fn render<T: RenderTarget>(&self, canvas: &mut Canvas<T>, screen_w: u32, screen_h: u32) {
+        let color = (150, 150, 160, 255);
+        let mid = (screen_h / 2) as i16;
+        let _ = canvas.box_(
+            (self.x + HEAD_WIDTH) as i16,
+            mid - 6,
+            screen_w as i16,
+            mid + 6,
+            color,
+        );
+        let _ = canvas.box_(
+            self.x as i16,
+            0,
+            (self.x + HEAD_WIDTH) as i16,
+            screen_h as i16,
+            color,
+        );
+    }
+}
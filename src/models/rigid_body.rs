@@ -0,0 +1,263 @@
+use crate::models::obstacle::convex_hull;
+use sdl2::gfx::primitives::DrawRenderer;
+use sdl2::render::{Canvas, RenderTarget};
+use std::f32::consts::{PI, TAU};
+
+pub type Vec2 = (f32, f32);
+
+pub fn add(a: Vec2, b: Vec2) -> Vec2 {
+    (a.0 + b.0, a.1 + b.1)
+}
+
+pub fn sub(a: Vec2, b: Vec2) -> Vec2 {
+    (a.0 - b.0, a.1 - b.1)
+}
+
+pub fn scale(a: Vec2, s: f32) -> Vec2 {
+    (a.0 * s, a.1 * s)
+}
+
+pub fn dot(a: Vec2, b: Vec2) -> f32 {
+    a.0 * b.0 + a.1 * b.1
+}
+
+/// z component of the 3D cross product of two vectors in the plane.
+pub fn cross(a: Vec2, b: Vec2) -> f32 {
+    a.0 * b.1 - a.1 * b.0
+}
+
+/// Cross product of an angular velocity `w` about z with the vector `r`: the velocity of a
+/// point at `r` from the centre of a body spinning at `w`.
+pub fn spin(w: f32, r: Vec2) -> Vec2 {
+    (-w * r.1, w * r.0)
+}
+
+pub fn rotate(a: Vec2, angle: f32) -> Vec2 {
+    let (sin, cos) = angle.sin_cos();
+    (a.0 * cos - a.1 * sin, a.0 * sin + a.1 * cos)
+}
+
+pub fn length(a: Vec2) -> f32 {
+    a.0.hypot(a.1)
+}
+
+#[derive(Clone, Debug)]
+pub enum BodyShape {
+    Circle {
+        radius: f32,
+    },
+    /// Convex polygon around the centre of mass, with counter-clockwise vertices (in y-up
+    /// terms) like `Obstacle::polygon`
+    Polygon {
+        points: Vec<Vec2>,
+    },
+}
+
+/// Solid body that moves and turns. Bodies with zero inverse mass are static: walls and
+/// ground that others rest on.
+#[derive(Clone, Debug)]
+pub struct RigidBody {
+    pub shape: BodyShape,
+    /// Centre of mass
+    pub position: Vec2,
+    /// Rotation in radians, turning x towards y
+    pub angle: f32,
+    pub velocity: Vec2,
+    pub angular_velocity: f32,
+    pub inverse_mass: f32,
+    /// Inverse of the moment of inertia about the centre of mass
+    pub inverse_inertia: f32,
+    pub restitution: f32,
+    pub friction: f32,
+    pub color: (u8, u8, u8),
+}
+
+impl RigidBody {
+    fn with_mass(shape: BodyShape, position: Vec2, mass: f32, inertia: f32) -> Self {
+        RigidBody {
+            shape,
+            position,
+            angle: 0.0,
+            velocity: (0.0, 0.0),
+            angular_velocity: 0.0,
+            inverse_mass: if mass > 0.0 { 1.0 / mass } else { 0.0 },
+            inverse_inertia: if inertia > 0.0 { 1.0 / inertia } else { 0.0 },
+            restitution: 0.2,
+            friction: 0.6,
+            color: (200, 160, 90),
+        }
+    }
+
+    /// Disc of uniform `density`, in mass per square pixel.
+    pub fn circle(position: Vec2, radius: f32, density: f32) -> Self {
+        let mass = density * PI * radius * radius;
+        Self::with_mass(
+            BodyShape::Circle { radius },
+            position,
+            mass,
+            0.5 * mass * radius * radius,
+        )
+    }
+
+    /// Convex hull of `points`, given relative to `position`, of uniform `density`. The body is
+    /// placed so that the hull stays where it was drawn. Returns `None` if the points do not
+    /// enclose an area.
+    pub fn polygon(position: Vec2, points: &[Vec2], density: f32) -> Option<Self> {
+        let hull = convex_hull(points)?;
+        // Area, centroid and inertia from the triangles fanning out from the first vertex
+        let origin = hull[0];
+        let (mut area, mut centroid) = (0.0, (0.0, 0.0));
+        for k in 1..hull.len() - 1 {
+            let (a, b) = (sub(hull[k], origin), sub(hull[k + 1], origin));
+            let triangle = 0.5 * cross(a, b);
+            area += triangle;
+            centroid = add(centroid, scale(add(a, b), triangle / 3.0));
+        }
+        if area <= f32::EPSILON {
+            return None;
+        }
+        let centroid = add(origin, scale(centroid, 1.0 / area));
+        let local: Vec<Vec2> = hull.iter().map(|&p| sub(p, centroid)).collect();
+        let mut inertia = 0.0;
+        for k in 0..local.len() {
+            let (a, b) = (local[k], local[(k + 1) % local.len()]);
+            inertia += cross(a, b) * (dot(a, a) + dot(a, b) + dot(b, b));
+        }
+        let mass = density * area;
+        Some(Self::with_mass(
+            BodyShape::Polygon { points: local },
+            add(position, centroid),
+            mass,
+            density * inertia / 12.0,
+        ))
+    }
+
+    pub fn rectangle(position: Vec2, width: f32, height: f32, density: f32) -> Self {
+        let (w, h) = (0.5 * width, 0.5 * height);
+        Self::polygon(position, &[(-w, -h), (w, -h), (w, h), (-w, h)], density)
+            .expect("a rectangle has an area")
+    }
+
+    /// Regular polygon with `sides` corners on a circle of `radius`.
+    pub fn regular(position: Vec2, radius: f32, sides: usize, density: f32) -> Self {
+        let points: Vec<Vec2> = (0..sides.max(3))
+            .map(|k| rotate((radius, 0.0), TAU * k as f32 / sides.max(3) as f32))
+            .collect();
+        Self::polygon(position, &points, density).expect("a regular polygon has an area")
+    }
+
+    /// Makes the body immovable.
+    pub fn fixed(mut self) -> Self {
+        self.inverse_mass = 0.0;
+        self.inverse_inertia = 0.0;
+        self.color = (120, 120, 130);
+        self
+    }
+
+    pub fn with_angle(mut self, angle: f32) -> Self {
+        self.angle = angle;
+        self
+    }
+
+    pub fn with_color(mut self, color: (u8, u8, u8)) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn is_static(&self) -> bool {
+        self.inverse_mass == 0.0
+    }
+
+    /// Corners of a polygon body in world coordinates, empty for a circle.
+    pub fn world_points(&self) -> Vec<Vec2> {
+        match &self.shape {
+            BodyShape::Circle { .. } => Vec::new(),
+            BodyShape::Polygon { points } => points
+                .iter()
+                .map(|&p| add(self.position, rotate(p, self.angle)))
+                .collect(),
+        }
+    }
+
+    /// Bounding box as `(min_x, min_y, max_x, max_y)`.
+    pub fn bounds(&self) -> (f32, f32, f32, f32) {
+        match &self.shape {
+            BodyShape::Circle { radius } => (
+                self.position.0 - radius,
+                self.position.1 - radius,
+                self.position.0 + radius,
+                self.position.1 + radius,
+            ),
+            BodyShape::Polygon { .. } => self.world_points().iter().fold(
+                (f32::MAX, f32::MAX, f32::MIN, f32::MIN),
+                |(x0, y0, x1, y1), &(x, y)| (x0.min(x), y0.min(y), x1.max(x), y1.max(y)),
+            ),
+        }
+    }
+
+    /// Velocity of the material point at `point`.
+    pub fn velocity_at(&self, point: Vec2) -> Vec2 {
+        add(
+            self.velocity,
+            spin(self.angular_velocity, sub(point, self.position)),
+        )
+    }
+
+    /// Applies `impulse` at the offset `r` from the centre of mass.
+    pub fn apply_impulse(&mut self, impulse: Vec2, r: Vec2) {
+        self.velocity = add(self.velocity, scale(impulse, self.inverse_mass));
+        self.angular_velocity += self.inverse_inertia * cross(r, impulse);
+    }
+
+    pub fn contains(&self, point: Vec2) -> bool {
+        match &self.shape {
+            BodyShape::Circle { radius } => length(sub(point, self.position)) <= *radius,
+            BodyShape::Polygon { .. } => {
+                let corners = self.world_points();
+                (0..corners.len()).all(|k| {
+                    let (a, b) = (corners[k], corners[(k + 1) % corners.len()]);
+                    cross(sub(b, a), sub(point, a)) >= 0.0
+                })
+            }
+        }
+    }
+
+    /// Kinetic energy, translational and rotational.
+    pub fn kinetic_energy(&self) -> f32 {
+        let linear = if self.inverse_mass > 0.0 {
+            0.5 * dot(self.velocity, self.velocity) / self.inverse_mass
+        } else {
+            0.0
+        };
+        let angular = if self.inverse_inertia > 0.0 {
+            0.5 * self.angular_velocity * self.angular_velocity / self.inverse_inertia
+        } else {
+            0.0
+        };
+        linear + angular
+    }
+
+    pub fn render<T: RenderTarget>(&self, canvas: &mut Canvas<T>) {
+        self.render_in(canvas, self.color);
+    }
+
+    /// Draws the body filled with `color` instead of its own.
+    pub fn render_in<T: RenderTarget>(&self, canvas: &mut Canvas<T>, (r, g, b): (u8, u8, u8)) {
+        let (x, y) = (self.position.0 as i16, self.position.1 as i16);
+        match &self.shape {
+            BodyShape::Circle { radius } => {
+                let _ = canvas.filled_circle(x, y, *radius as i16, (r, g, b, 255));
+                // Spoke showing the rotation
+                let rim = add(self.position, rotate((*radius, 0.0), self.angle));
+                let _ = canvas.line(x, y, rim.0 as i16, rim.1 as i16, (40, 40, 40, 255));
+            }
+            BodyShape::Polygon { .. } => {
+                let corners = self.world_points();
+                let xs: Vec<i16> = corners.iter().map(|p| p.0 as i16).collect();
+                let ys: Vec<i16> = corners.iter().map(|p| p.1 as i16).collect();
+                let _ = canvas.filled_polygon(&xs, &ys, (r, g, b, 255));
+                let _ = canvas.polygon(&xs, &ys, (40, 40, 40, 255));
+            }
+        }
+    }
+}
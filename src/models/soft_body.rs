@@ -0,0 +1,159 @@
+use crate::models::particle::Particle;
+use crate::models::particle_system::ParticleSystem;
+use crate::utils::xpbd::{self, Area, Distance};
+use sdl2::gfx::primitives::DrawRenderer;
+use sdl2::render::{Canvas, RenderTarget};
+use std::f32::consts::TAU;
+
+/// Constraint iterations per step.
+const ITERATIONS: usize = 10;
+/// Compliance of the skin between neighbouring ring particles.
+const SKIN_COMPLIANCE: f32 = 1e-4;
+/// Compliance of the enclosed area, per unit of rest area so that big and small blobs are
+/// equally squashy.
+const AREA_COMPLIANCE: f32 = 2e-3;
+
+/// Balloon made of a ring of ordinary particles, so it collides with everything else in the
+/// system. The skin keeps neighbours at their spacing and an area constraint plays the part
+/// of the gas inside: `pressure` scales the area the blob tries to enclose.
+pub struct SoftBody {
+    /// Ids of the ring particles, in order around the ring
+    ring: Vec<u64>,
+    rest_lengths: Vec<f32>,
+    rest_area: f32,
+    pub pressure: f32,
+    area: Area,
+    indices: Vec<usize>,
+    /// Inverse masses indexed like the system, but only filled in at the ring particles
+    weights: Vec<f32>,
+    lambdas: Vec<f32>,
+    before: Vec<(f32, f32)>,
+}
+
+impl SoftBody {
+    /// Adds a ring of particles of `particle_radius` around a circle of `radius` centred on
+    /// `centre`, spaced so that neighbours just do not touch. `make(x, y)` describes each one.
+    pub fn spawn<F>(
+        system: &mut ParticleSystem,
+        centre: (f32, f32),
+        radius: f32,
+        particle_radius: f32,
+        pressure: f32,
+        make: F,
+    ) -> Self
+    where
+        F: Fn(f32, f32) -> Particle,
+    {
+        let count = ((TAU * radius / (2.2 * particle_radius)) as usize).max(6);
+        let mut ring = Vec::with_capacity(count);
+        for k in 0..count {
+            let angle = TAU * k as f32 / count as f32;
+            system.push(make(
+                centre.0 + radius * angle.cos(),
+                centre.1 + radius * angle.sin(),
+            ));
+            ring.push(system.id[system.len() - 1]);
+        }
+        let indices: Vec<usize> = (system.len() - count..system.len()).collect();
+        let rest_lengths = (0..count)
+            .map(|k| {
+                let (a, b) = (indices[k], indices[(k + 1) % count]);
+                (system.x[b] - system.x[a]).hypot(system.y[b] - system.y[a])
+            })
+            .collect();
+        let rest_area = xpbd::area(system, &indices);
+        SoftBody {
+            ring,
+            rest_lengths,
+            rest_area,
+            pressure,
+            area: Area::new(
+                pressure * rest_area,
+                AREA_COMPLIANCE / rest_area.abs().max(1.0),
+            ),
+            indices,
+            weights: Vec::new(),
+            lambdas: Vec::new(),
+            before: Vec::new(),
+        }
+    }
+
+    /// Finds the ring particles. Returns false if any of them is gone, which bursts the blob.
+    fn resolve(&mut self, system: &ParticleSystem) -> bool {
+        self.indices.clear();
+        for &id in &self.ring {
+            match system.index_of(id) {
+                Some(i) => self.indices.push(i),
+                None => return false,
+            }
+        }
+        true
+    }
+
+    /// Moves the ring back onto its constraints after a step of `dt`, turning the corrections
+    /// into velocity changes. Returns false if the blob has burst and should be dropped.
+    pub fn project(&mut self, system: &mut ParticleSystem, dt: f32) -> bool {
+        if !self.resolve(system) {
+            return false;
+        }
+        if dt <= 0.0 {
+            return true;
+        }
+        self.weights.resize(system.len(), 0.0);
+        for &i in &self.indices {
+            self.weights[i] = system.inverse_mass(i);
+        }
+        self.before.clear();
+        self.before
+            .extend(self.indices.iter().map(|&i| (system.x[i], system.y[i])));
+
+        let n = self.indices.len();
+        self.area.target = self.pressure * self.rest_area;
+        self.lambdas.clear();
+        self.lambdas.resize(n + 1, 0.0);
+        for _ in 0..ITERATIONS {
+            for k in 0..n {
+                let skin = Distance {
+                    i: self.indices[k],
+                    j: self.indices[(k + 1) % n],
+                    rest_length: self.rest_lengths[k],
+                    compliance: SKIN_COMPLIANCE,
+                    tension_only: false,
+                };
+                skin.solve(system, &self.weights, dt, &mut self.lambdas[k]);
+            }
+            self.area.solve(
+                system,
+                &self.indices,
+                &self.weights,
+                dt,
+                &mut self.lambdas[n],
+            );
+        }
+
+        for (&i, &(x, y)) in self.indices.iter().zip(&self.before) {
+            system.vx[i] += (system.x[i] - x) / dt;
+            system.vy[i] += (system.y[i] - y) / dt;
+        }
+        true
+    }
+
+    /// Fills the blob with a translucent colour under its ring.
+    pub fn render<T: RenderTarget>(&self, canvas: &mut Canvas<T>, system: &ParticleSystem) {
+        let points: Option<Vec<(i16, i16)>> = self
+            .ring
+            .iter()
+            .map(|&id| {
+                system
+                    .index_of(id)
+                    .map(|i| (system.x[i] as i16, system.y[i] as i16))
+            })
+            .collect();
+        let Some(points) = points else {
+            return;
+        };
+        let (xs, ys): (Vec<i16>, Vec<i16>) = points.into_iter().unzip();
+        let _ = canvas.filled_polygon(&xs, &ys, (90, 200, 140, 90));
+        let _ = canvas.polygon(&xs, &ys, (140, 240, 180, 255));
+    }
+}
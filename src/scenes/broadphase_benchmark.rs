@@ -0,0 +1,206 @@
+use crate::engine::{GlobalContext, Scene};
+use crate::models::force_field::ForceFields;
+use crate::models::particle::Particle;
+use crate::models::particle_system::ParticleSystem;
+use crate::utils::benchmark::BenchmarkQueue;
+use crate::utils::broadphase::{BroadphaseKind, ParticlePairs};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use sdl2::{event::Event, keyboard::Keycode};
+use sdl2::{render::Canvas, video::Window};
+use std::time::Instant;
+
+const PARTICLE_COUNT: usize = 20_000;
+const FRAMES_PER_RUN: usize = 60;
+const BENCH_DT: f32 = 1.0 / 60.0;
+const SEED: u64 = 7;
+/// Large particles among the small ones in the mixed population
+const LARGE_COUNT: usize = 20;
+const CLUSTER_COUNT: usize = 8;
+const CLUSTER_SPREAD: f32 = 40.0;
+
+#[derive(Clone, Copy)]
+enum Population {
+    Uniform,
+    MixedSizes,
+    Clustered,
+}
+
+impl Population {
+    const ALL: [Population; 3] = [
+        Population::Uniform,
+        Population::MixedSizes,
+        Population::Clustered,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Population::Uniform => "uniform, radius 2-4",
+            Population::MixedSizes => "radius 2-4 and 20 of 40-80",
+            Population::Clustered => "8 clusters, radius 2",
+        }
+    }
+}
+
+struct BenchResult {
+    population: Population,
+    broadphase: BroadphaseKind,
+    candidates: usize,
+    ms_per_frame: f64,
+}
+
+/// Compares the broadphases on `PARTICLE_COUNT` moving particles in three populations: evenly
+/// spread particles of similar size, a few very large particles among small ones, and tight
+/// clusters. Only the pair search is timed, not the contacts. Each row also gives the mean
+/// number of candidate pairs, so a broadphase that is only fast because it misses pairs shows.
+pub struct BroadphaseBenchmark {
+    queue: BenchmarkQueue<(Population, BroadphaseKind), BenchResult>,
+    setup: Setup,
+    done: bool,
+}
+
+/// What every run starts from.
+struct Setup {
+    fields: ForceFields,
+    width: u32,
+    height: u32,
+}
+
+impl BroadphaseBenchmark {
+    pub fn new(ctx: &GlobalContext) -> Self {
+        let runs = Population::ALL.into_iter().flat_map(|population| {
+            BroadphaseKind::ALL
+                .into_iter()
+                .map(move |kind| (population, kind))
+        });
+        BroadphaseBenchmark {
+            queue: BenchmarkQueue::new(runs),
+            setup: Setup {
+                fields: ForceFields::none(),
+                width: ctx.screen_width,
+                height: ctx.screen_height,
+            },
+            done: false,
+        }
+    }
+}
+
+impl Setup {
+    /// Same random particles for every broadphase.
+    fn population(&self, population: Population) -> ParticleSystem {
+        let mut rng = StdRng::seed_from_u64(SEED);
+        let (w, h) = (self.width as f32, self.height as f32);
+        let centres: Vec<(f32, f32)> = (0..CLUSTER_COUNT)
+            .map(|_| {
+                (
+                    rng.gen_range(0.2 * w..0.8 * w),
+                    rng.gen_range(0.2 * h..0.8 * h),
+                )
+            })
+            .collect();
+        let mut system = ParticleSystem::new();
+        for k in 0..PARTICLE_COUNT {
+            let (x, y, radius) = match population {
+                Population::Uniform => (
+                    rng.gen_range(0.0..w),
+                    rng.gen_range(0.0..h),
+                    rng.gen_range(2.0..4.0),
+                ),
+                Population::MixedSizes => (
+                    rng.gen_range(0.0..w),
+                    rng.gen_range(0.0..h),
+                    if k < LARGE_COUNT {
+                        rng.gen_range(40.0..80.0)
+                    } else {
+                        rng.gen_range(2.0..4.0)
+                    },
+                ),
+                Population::Clustered => {
+                    // Sum of uniforms, roughly normal around the cluster centre
+                    let (cx, cy) = centres[k % CLUSTER_COUNT];
+                    let mut spread =
+                        || (0..3).map(|_| rng.gen_range(-1.0..1.0)).sum::<f32>() * CLUSTER_SPREAD;
+                    (cx + spread(), cy + spread(), 2.0)
+                }
+            };
+            system.push(Particle::new(
+                x,
+                y,
+                rng.gen_range(-60.0..60.0),
+                rng.gen_range(-60.0..60.0),
+                radius,
+            ));
+        }
+        system
+    }
+
+    /// Returns the mean candidate pairs and the total seconds spent finding them.
+    fn run(&self, population: Population, kind: BroadphaseKind) -> (usize, f64) {
+        let mut system = self.population(population);
+        let mut broadphase = kind.create();
+        let mut particle_pairs = ParticlePairs::new();
+        let mut pairs = Vec::new();
+        let (mut candidates, mut seconds) = (0, 0.0);
+        for _ in 0..FRAMES_PER_RUN {
+            system.integrate(&self.fields, BENCH_DT, self.width, self.height);
+            let start = Instant::now();
+            particle_pairs.find(
+                broadphase.as_mut(),
+                &system,
+                (self.width as f32, self.height as f32),
+                (false, false),
+                &mut pairs,
+            );
+            seconds += start.elapsed().as_secs_f64();
+            candidates += pairs.len();
+        }
+        (candidates / FRAMES_PER_RUN, seconds)
+    }
+}
+
+impl Scene for BroadphaseBenchmark {
+    fn handle_event(&mut self, _ctx: &mut GlobalContext, event: &Event) {
+        if let Event::KeyDown {
+            keycode: Some(k), ..
+        } = event
+        {
+            match k {
+                Keycode::Escape => self.done = true,
+                _ => self.queue.handle_key(*k),
+            }
+        }
+    }
+
+    fn update(&mut self, _ctx: &mut GlobalContext, _dt: f32) {
+        let setup = &self.setup;
+        self.queue.update(|(population, broadphase)| {
+            let (candidates, seconds) = setup.run(population, broadphase);
+            BenchResult {
+                population,
+                broadphase,
+                candidates,
+                ms_per_frame: seconds * 1000.0 / FRAMES_PER_RUN as f64,
+            }
+        });
+    }
+
+    fn render(&mut self, _ctx: &GlobalContext, canvas: &mut Canvas<Window>) {
+        let title = format!(
+            "Broadphase pair search, {} particles, {} frames per run",
+            PARTICLE_COUNT, FRAMES_PER_RUN
+        );
+        self.queue.render(canvas, &title, |result| {
+            format!(
+                "{:<28} {:<16} {:>9} candidate pairs {:>8.3} ms/frame",
+                result.population.label(),
+                result.broadphase.label(),
+                result.candidates,
+                result.ms_per_frame
+            )
+        });
+    }
+
+    fn is_done(&self) -> bool {
+        self.done
+    }
+}
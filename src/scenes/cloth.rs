@@ -0,0 +1,255 @@
+use crate::engine::{GlobalContext, Scene};
+use crate::models::cloth::{Cloth, Material};
+use crate::models::force_field::{
+    ForceField, ForceFields, LinearDrag, TurbulentWind, UniformGravity,
+};
+use sdl2::gfx::primitives::DrawRenderer;
+use sdl2::mouse::MouseButton;
+use sdl2::{event::Event, keyboard::Keycode};
+use sdl2::{render::Canvas, video::Window};
+
+const COLUMNS: usize = 60;
+const ROWS: usize = 40;
+const SPACING: f32 = 12.0;
+const NODE_MASS: f32 = 1.0;
+/// Distance from the top of the screen to the top edge of the cloth.
+const TOP: f32 = 80.0;
+/// How close a click must be to a node to grab or pin it.
+const PICK_DISTANCE: f32 = 20.0;
+/// Longest frame simulated in one go, so a stall does not blow the cloth apart.
+const MAX_FRAME: f32 = 1.0 / 30.0;
+const DEFAULT_SUBSTEPS: usize = 20;
+/// Fewer substeps leave the stiff materials badly converged and jittery.
+const MIN_SUBSTEPS: usize = 4;
+/// Air resistance, so the cloth settles once left alone.
+const AIR_DRAG: f32 = 0.1;
+const TEAR_STRAIN: f32 = 0.4;
+
+/// Compliances of the three edge kinds, from nearly inextensible to stretchy.
+const MATERIALS: [Material; 3] = [
+    Material {
+        name: "silk",
+        structural: 0.0,
+        shear: 1e-5,
+        bend: 1e-3,
+    },
+    Material {
+        name: "cotton",
+        structural: 1e-6,
+        shear: 1e-6,
+        bend: 1e-4,
+    },
+    Material {
+        name: "rubber",
+        structural: 5e-6,
+        shear: 5e-6,
+        bend: 1e-2,
+    },
+];
+
+/// Which nodes of the top row are pinned when the cloth is rebuilt.
+#[derive(Clone, Copy, PartialEq)]
+enum Hanging {
+    Corners,
+    TopEdge,
+}
+
+/// A sheet of cloth solved with XPBD: drag it with the mouse, cut it with the right button,
+/// tear it by pulling and blow on it with turbulent wind.
+pub struct ClothScene {
+    cloth: Cloth,
+    fields: ForceFields,
+    material: usize,
+    hanging: Hanging,
+    substeps: usize,
+    tearing: bool,
+    cutting: bool,
+    cursor: (f32, f32),
+    width: f32,
+    height: f32,
+    done: bool,
+}
+
+impl ClothScene {
+    pub fn new(ctx: &GlobalContext) -> Self {
+        let (width, height) = (ctx.screen_width as f32, ctx.screen_height as f32);
+        let mut cloth = Self::build((width, height), MATERIALS[1], Hanging::Corners);
+        cloth.tear_strain = Some(TEAR_STRAIN);
+        let mut fields = ForceFields::default();
+        fields.push(Box::new(LinearDrag {
+            coefficient: AIR_DRAG,
+        }));
+        ClothScene {
+            cloth,
+            fields,
+            material: 1,
+            hanging: Hanging::Corners,
+            substeps: DEFAULT_SUBSTEPS,
+            tearing: true,
+            cutting: false,
+            cursor: (0.0, 0.0),
+            width,
+            height,
+            done: false,
+        }
+    }
+
+    fn build((width, height): (f32, f32), material: Material, hanging: Hanging) -> Cloth {
+        let left = 0.5 * (width - (COLUMNS - 1) as f32 * SPACING);
+        let mut cloth = Cloth::new((left, TOP), (COLUMNS, ROWS), SPACING, NODE_MASS, material);
+        cloth.floor = Some(height - 1.0);
+        for column in 0..COLUMNS {
+            let corner = column == 0 || column == COLUMNS - 1;
+            if corner || hanging == Hanging::TopEdge {
+                cloth.toggle_pin(cloth.index(column, 0));
+            }
+        }
+        cloth
+    }
+
+    fn reset(&mut self) {
+        self.cloth = Self::build(
+            (self.width, self.height),
+            MATERIALS[self.material],
+            self.hanging,
+        );
+        self.cloth.tear_strain = self.tearing.then_some(TEAR_STRAIN);
+    }
+
+    fn toggle_field(&mut self, name: &str, make: impl FnOnce() -> Box<dyn ForceField>) {
+        if !self.fields.remove_named(name) {
+            self.fields.push(make());
+        }
+    }
+
+    fn on_off(on: bool) -> &'static str {
+        if on {
+            "on"
+        } else {
+            "off"
+        }
+    }
+}
+
+impl Scene for ClothScene {
+    fn update(&mut self, ctx: &mut GlobalContext, dt: f32) {
+        if ctx.paused {
+            return;
+        }
+        let dt = (dt * ctx.simulation_speed.max(0.0)).min(MAX_FRAME);
+        self.fields.advance(dt);
+        self.cloth.step(&self.fields, dt, self.substeps);
+    }
+
+    fn render(&mut self, _ctx: &GlobalContext, canvas: &mut Canvas<Window>) {
+        self.cloth.render(canvas);
+        if self.cutting {
+            let (x, y) = self.cursor;
+            let _ = canvas.circle(x as i16, y as i16, 4, (255, 120, 120, 255));
+        }
+
+        let white = (255, 255, 255, 255);
+        let lines = [
+            format!(
+                "{} x {} cloth, {} edges, {} torn or cut",
+                COLUMNS,
+                ROWS,
+                self.cloth.edge_count(),
+                self.cloth.torn
+            ),
+            format!(
+                "Material {} (M), {} substeps ([ / ]), tearing {} (T)",
+                MATERIALS[self.material].name,
+                self.substeps,
+                Self::on_off(self.tearing)
+            ),
+            format!(
+                "Gravity {} (G), wind {} (W). 1 hang by corners, 2 hang by top edge, R reset",
+                Self::on_off(self.fields.contains("gravity")),
+                Self::on_off(self.fields.contains("wind"))
+            ),
+            "Left drag: pull, right drag: cut, P: pin or unpin the node under the cursor"
+                .to_string(),
+        ];
+        for (k, line) in lines.iter().enumerate() {
+            let _ = canvas.string(10, 10 + 15 * k as i16, line, white);
+        }
+    }
+
+    fn handle_event(&mut self, ctx: &mut GlobalContext, event: &Event) {
+        match *event {
+            Event::KeyDown {
+                keycode: Some(k), ..
+            } => match k {
+                Keycode::Escape => self.done = true,
+                Keycode::Space => ctx.paused = !ctx.paused,
+                Keycode::Left => ctx.simulation_speed -= 0.1,
+                Keycode::Right => ctx.simulation_speed += 0.1,
+                Keycode::Num1 => {
+                    self.hanging = Hanging::Corners;
+                    self.reset();
+                }
+                Keycode::Num2 => {
+                    self.hanging = Hanging::TopEdge;
+                    self.reset();
+                }
+                Keycode::R => self.reset(),
+                Keycode::M => {
+                    self.material = (self.material + 1) % MATERIALS.len();
+                    self.cloth.set_material(MATERIALS[self.material]);
+                }
+                Keycode::T => {
+                    self.tearing = !self.tearing;
+                    self.cloth.tear_strain = self.tearing.then_some(TEAR_STRAIN);
+                }
+                Keycode::G => self.toggle_field("gravity", || Box::new(UniformGravity::default())),
+                Keycode::W => self.toggle_field("wind", || Box::new(TurbulentWind::default())),
+                Keycode::LeftBracket => self.substeps = (self.substeps - 1).max(MIN_SUBSTEPS),
+                Keycode::RightBracket => self.substeps += 1,
+                Keycode::P => {
+                    let (x, y) = self.cursor;
+                    if let Some(i) = self.cloth.node_near(x, y, PICK_DISTANCE) {
+                        self.cloth.toggle_pin(i);
+                    }
+                }
+                _ => {}
+            },
+            Event::MouseMotion { x, y, .. } => {
+                let point = (x as f32, y as f32);
+                if self.cutting {
+                    self.cloth.cut(self.cursor, point);
+                }
+                self.cloth.move_held(point.0, point.1);
+                self.cursor = point;
+            }
+            Event::MouseButtonDown {
+                mouse_btn: MouseButton::Left,
+                x,
+                y,
+                ..
+            } => {
+                let (x, y) = (x as f32, y as f32);
+                if let Some(i) = self.cloth.node_near(x, y, PICK_DISTANCE) {
+                    self.cloth.hold(i, x, y);
+                }
+            }
+            Event::MouseButtonUp {
+                mouse_btn: MouseButton::Left,
+                ..
+            } => self.cloth.release(),
+            Event::MouseButtonDown {
+                mouse_btn: MouseButton::Right,
+                ..
+            } => self.cutting = true,
+            Event::MouseButtonUp {
+                mouse_btn: MouseButton::Right,
+                ..
+            } => self.cutting = false,
+            _ => {}
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.done
+    }
+}
@@ -0,0 +1,385 @@
+use crate::engine::{GlobalContext, Scene};
+use crate::scenes::elastic_pendulum::draw_spring;
+use crate::scenes::pendulum::draw_pendulum;
+use crate::utils::linalg::{dot, symmetric_eigen};
+use crate::utils::RK4::rk4;
+use sdl2::gfx::primitives::DrawRenderer;
+use sdl2::mouse::MouseButton;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::{event::Event, keyboard::Keycode};
+use sdl2::{render::Canvas, video::Window};
+
+const MIN_OSCILLATORS: usize = 2;
+const MAX_OSCILLATORS: usize = 9;
+const AMPLITUDE: f32 = 0.25;
+
+#[derive(Clone, Copy)]
+enum Excitation {
+    Mode(usize),
+    Superposition,
+    Pluck,
+}
+
+struct Button {
+    rect: Rect,
+    label: String,
+    excitation: Excitation,
+}
+
+/// A row of identical pendulums whose bobs are joined by horizontal springs.
+/// State is `[theta_0..theta_{M-1}, omega_0..omega_{M-1}]`.
+pub struct CoupledOscillators {
+    count: usize,
+    length: f32,
+    gravity: f32,
+    mass: f32,
+    coupling: f32,
+    /// Angular frequencies of the normal modes, ascending
+    frequencies: Vec<f32>,
+    /// Unit mode shapes, `modes[k]` belongs to `frequencies[k]`
+    modes: Vec<Vec<f32>>,
+    excitation: Excitation,
+    buttons: Vec<Button>,
+    solutions: Option<(Vec<f32>, Vec<Vec<f32>>)>,
+    current_index: usize,
+    done: bool,
+}
+
+impl CoupledOscillators {
+    pub fn new() -> Self {
+        let mut oscillators = CoupledOscillators {
+            count: 5,
+            length: 2.0,
+            gravity: 9.8,
+            mass: 1.0,
+            coupling: 4.0,
+            frequencies: Vec::new(),
+            modes: Vec::new(),
+            excitation: Excitation::Mode(0),
+            buttons: Vec::new(),
+            solutions: None,
+            current_index: 0,
+            done: false,
+        };
+        oscillators.analyse();
+        oscillators.solve();
+        oscillators
+    }
+
+    /// Linearised stiffness matrix K in theta'' = -K theta.
+    fn stiffness_matrix(&self) -> Vec<Vec<f32>> {
+        let n = self.count;
+        let spring = self.coupling / self.mass;
+        let mut k = vec![vec![0.0; n]; n];
+        for i in 0..n {
+            k[i][i] = self.gravity / self.length;
+            if i > 0 {
+                k[i][i] += spring;
+                k[i][i - 1] = -spring;
+            }
+            if i + 1 < n {
+                k[i][i] += spring;
+                k[i][i + 1] = -spring;
+            }
+        }
+        k
+    }
+
+    fn analyse(&mut self) {
+        let (values, vectors) = symmetric_eigen(&self.stiffness_matrix());
+        self.frequencies = values.iter().map(|v| v.max(0.0).sqrt()).collect();
+        self.modes = vectors;
+        self.build_buttons();
+    }
+
+    fn build_buttons(&mut self) {
+        self.buttons.clear();
+        let mut x = 10;
+        let y = 60;
+        for k in 0..self.count {
+            self.buttons.push(Button {
+                rect: Rect::new(x, y, 90, 30),
+                label: format!("Mode {}", k + 1),
+                excitation: Excitation::Mode(k),
+            });
+            x += 100;
+        }
+        self.buttons.push(Button {
+            rect: Rect::new(x, y, 110, 30),
+            label: "Modes 1+2".to_string(),
+            excitation: Excitation::Superposition,
+        });
+        x += 120;
+        self.buttons.push(Button {
+            rect: Rect::new(x, y, 110, 30),
+            label: "Pluck first".to_string(),
+            excitation: Excitation::Pluck,
+        });
+    }
+
+    /// Scales a mode shape so that its largest displacement is `AMPLITUDE`.
+    fn scaled_mode(&self, k: usize) -> Vec<f32> {
+        let mode = &self.modes[k];
+        let peak = mode.iter().fold(0.0f32, |m, v| m.max(v.abs())).max(1e-6);
+        mode.iter().map(|v| v * AMPLITUDE / peak).collect()
+    }
+
+    fn initial_state(&self) -> Vec<f32> {
+        let n = self.count;
+        let thetas = match self.excitation {
+            Excitation::Mode(k) => self.scaled_mode(k.min(n - 1)),
+            Excitation::Superposition => self
+                .scaled_mode(0)
+                .iter()
+                .zip(self.scaled_mode(1))
+                .map(|(a, b)| 0.5 * (a + b))
+                .collect(),
+            Excitation::Pluck => (0..n)
+                .map(|i| if i == 0 { AMPLITUDE } else { 0.0 })
+                .collect(),
+        };
+        let mut state = thetas;
+        state.resize(2 * n, 0.0);
+        state
+    }
+
+    /// Full (non-linearised) equations of motion.
+    fn derivatives(&self, _t: f32, state: &[f32]) -> Vec<f32> {
+        let n = self.count;
+        let (theta, omega) = state.split_at(n);
+        let spring = self.coupling / self.mass;
+        let mut d = omega.to_vec();
+        for i in 0..n {
+            let mut stretch = 0.0;
+            if i > 0 {
+                stretch += theta[i - 1].sin() - theta[i].sin();
+            }
+            if i + 1 < n {
+                stretch += theta[i + 1].sin() - theta[i].sin();
+            }
+            d.push(
+                -(self.gravity / self.length) * theta[i].sin() + spring * theta[i].cos() * stretch,
+            );
+        }
+        d
+    }
+
+    pub fn solve(&mut self) {
+        let state0 = self.initial_state();
+        let (a, b) = (0.0, 50.0);
+        let n: u32 = 10000;
+        let solutions = rk4(a, b, state0, |t, state| self.derivatives(t, state), n);
+        self.solutions = Some(solutions);
+        self.current_index = 0;
+    }
+
+    fn excite(&mut self, excitation: Excitation) {
+        self.excitation = excitation;
+        self.solve();
+    }
+
+    fn set_count(&mut self, count: usize) {
+        self.count = count.clamp(MIN_OSCILLATORS, MAX_OSCILLATORS);
+        if let Excitation::Mode(k) = self.excitation {
+            self.excitation = Excitation::Mode(k.min(self.count - 1));
+        }
+        self.analyse();
+        self.solve();
+    }
+
+    /// Energy held in each normal mode, from projecting the state onto the mode shapes.
+    fn mode_energies(&self, state: &[f32]) -> Vec<f32> {
+        let (theta, omega) = state.split_at(self.count);
+        let inertia = self.mass * self.length * self.length;
+        self.modes
+            .iter()
+            .zip(&self.frequencies)
+            .map(|(mode, w)| {
+                let q = dot(mode, theta);
+                let q_dot = dot(mode, omega);
+                0.5 * inertia * (q_dot * q_dot + w * w * q * q)
+            })
+            .collect()
+    }
+
+    fn render_energy_chart(
+        &self,
+        canvas: &mut Canvas<Window>,
+        energies: &[f32],
+        width: u32,
+        height: u32,
+    ) {
+        let total: f32 = energies.iter().sum::<f32>().max(1e-9);
+        let chart_height = 200.0;
+        let bar_width = 60;
+        let base_y = height as i16 - 60;
+        let start_x = width as i16 - (energies.len() as i16) * (bar_width + 10) - 20;
+
+        let _ = canvas.string(
+            start_x,
+            base_y - chart_height as i16 - 30,
+            "Mode energy",
+            (255, 255, 255, 255),
+        );
+        for (k, energy) in energies.iter().enumerate() {
+            let x = start_x + k as i16 * (bar_width + 10);
+            let bar = (chart_height * energy / total) as i16;
+            let _ = canvas.box_(
+                x,
+                base_y - bar,
+                x + bar_width,
+                base_y,
+                Color::RGB(0, 200, 255),
+            );
+            let _ = canvas.rectangle(
+                x,
+                base_y - chart_height as i16,
+                x + bar_width,
+                base_y,
+                Color::RGB(80, 80, 80),
+            );
+            let _ = canvas.string(x, base_y + 8, &format!("{}", k + 1), (255, 255, 255, 255));
+            let _ = canvas.string(
+                x,
+                base_y + 22,
+                &format!("{:.2}", self.frequencies[k]),
+                (160, 160, 160, 255),
+            );
+        }
+    }
+}
+
+impl Scene for CoupledOscillators {
+    fn handle_event(&mut self, ctx: &mut GlobalContext, event: &Event) {
+        match event {
+            Event::MouseButtonDown {
+                mouse_btn: MouseButton::Left,
+                x,
+                y,
+                ..
+            } => {
+                let clicked = self
+                    .buttons
+                    .iter()
+                    .find(|b| b.rect.contains_point((*x, *y)))
+                    .map(|b| b.excitation);
+                if let Some(excitation) = clicked {
+                    self.excite(excitation);
+                }
+            }
+            Event::KeyDown {
+                keycode: Some(k), ..
+            } => match k {
+                Keycode::Escape => {
+                    self.done = true;
+                }
+                Keycode::R => {
+                    self.current_index = 0;
+                }
+                Keycode::Left => {
+                    ctx.simulation_speed -= 0.1;
+                }
+                Keycode::Right => {
+                    ctx.simulation_speed += 0.1;
+                }
+                Keycode::Up => self.set_count(self.count + 1),
+                Keycode::Down => self.set_count(self.count - 1),
+                Keycode::S => self.excite(Excitation::Superposition),
+                Keycode::P => self.excite(Excitation::Pluck),
+                _ => {
+                    let digit = (*k as i32) - (Keycode::Num1 as i32);
+                    if (0..self.count as i32).contains(&digit) {
+                        self.excite(Excitation::Mode(digit as usize));
+                    }
+                }
+            },
+            _ => {}
+        }
+    }
+
+    fn update(&mut self, ctx: &mut GlobalContext, _dt: f32) {
+        if self.solutions.is_none() {
+            self.solve();
+        }
+        if let Some((_, ref sol)) = self.solutions {
+            if (self.current_index + 1 + (ctx.simulation_speed * 10.) as usize) < sol.len() {
+                self.current_index += 1 + (ctx.simulation_speed * 10.) as usize;
+            } else {
+                self.current_index = sol.len() - 1
+            }
+        }
+    }
+
+    fn render(&mut self, _ctx: &GlobalContext, canvas: &mut Canvas<Window>) {
+        if let Some((_, ref solutions)) = self.solutions {
+            let (width, height) = canvas.output_size().unwrap_or((800, 600));
+            let state = &solutions[self.current_index];
+            let scale = 200.0;
+            let spacing = width as f32 / (self.count + 1) as f32;
+            let pivot_y = 200.0;
+
+            let bobs: Vec<(f32, f32)> = (0..self.count)
+                .map(|i| {
+                    let pivot_x = spacing * (i + 1) as f32;
+                    let theta = state[i];
+                    (
+                        pivot_x + scale * self.length * theta.sin(),
+                        pivot_y + scale * self.length * theta.cos(),
+                    )
+                })
+                .collect();
+
+            canvas.set_draw_color(Color::RGB(255, 255, 255));
+            let _ = canvas.draw_line((0, pivot_y as i32), (width as i32, pivot_y as i32));
+            for pair in bobs.windows(2) {
+                draw_spring(canvas, pair[0], pair[1], 8.0, Color::RGB(255, 200, 0));
+            }
+            for (i, bob) in bobs.iter().enumerate() {
+                let pivot = ((spacing * (i + 1) as f32) as i32, pivot_y as i32);
+                draw_pendulum(canvas, pivot, (bob.0 as i32, bob.1 as i32), 20);
+            }
+
+            for button in &self.buttons {
+                let r = button.rect;
+                let _ = canvas.rectangle(
+                    r.left() as i16,
+                    r.top() as i16,
+                    r.right() as i16,
+                    r.bottom() as i16,
+                    Color::RGB(200, 200, 200),
+                );
+                let _ = canvas.string(
+                    r.left() as i16 + 8,
+                    r.top() as i16 + 11,
+                    &button.label,
+                    (255, 255, 255, 255),
+                );
+            }
+
+            let excitation = match self.excitation {
+                Excitation::Mode(k) => {
+                    format!("mode {} (w = {:.3} rad/s)", k + 1, self.frequencies[k])
+                }
+                Excitation::Superposition => "modes 1 + 2".to_string(),
+                Excitation::Pluck => "first pendulum plucked".to_string(),
+            };
+            let _ = canvas.string(
+                10,
+                10,
+                &format!(
+                    "{} pendulums (Up/Down), k = {:.1} N/m, exciting {}",
+                    self.count, self.coupling, excitation
+                ),
+                (255, 255, 255, 255),
+            );
+
+            let energies = self.mode_energies(state);
+            self.render_energy_chart(canvas, &energies, width, height);
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.done
+    }
+}
@@ -0,0 +1,227 @@
+use crate::engine::{GlobalContext, Scene};
+use crate::utils::RK4::rk4;
+use sdl2::gfx::primitives::DrawRenderer;
+use sdl2::pixels::Color;
+use sdl2::{event::Event, keyboard::Keycode};
+use sdl2::{render::Canvas, video::Window};
+use std::f32::consts::PI;
+
+const SPRING_COILS: usize = 16;
+
+/// Spring pendulum: a mass on a stretchable rod, free to swing and bounce.
+/// State is `[r, r_dot, theta, theta_dot]` with theta measured from the downward vertical.
+pub struct ElasticPendulum {
+    solutions: Option<(Vec<f32>, Vec<Vec<f32>>)>,
+    current_index: usize,
+    rest_length: f32,
+    stiffness: f32,
+    mass: f32,
+    gravity: f32,
+    done: bool,
+}
+
+impl ElasticPendulum {
+    pub fn new() -> Self {
+        let gravity = 9.8;
+        let rest_length = 1.0;
+        let mass = 1.0;
+        let mut pendulum = ElasticPendulum {
+            solutions: None,
+            current_index: 0,
+            rest_length,
+            // k/m = 3g/L0 puts the spring at twice the swing frequency, where energy sloshes
+            // back and forth between the two motions.
+            stiffness: 3.0 * gravity * mass / rest_length,
+            mass,
+            gravity,
+            done: false,
+        };
+        pendulum.solve();
+        pendulum
+    }
+
+    fn derivatives(&self, _t: f32, state: &[f32]) -> Vec<f32> {
+        let (r, r_dot, theta, theta_dot) = (state[0], state[1], state[2], state[3]);
+        let r_ddot = r * theta_dot * theta_dot + self.gravity * theta.cos()
+            - (self.stiffness / self.mass) * (r - self.rest_length);
+        let theta_ddot = (-self.gravity * theta.sin() - 2.0 * r_dot * theta_dot) / r;
+        vec![r_dot, r_ddot, theta_dot, theta_ddot]
+    }
+
+    pub fn solve(&mut self) {
+        let equilibrium = self.equilibrium_length();
+        let state0 = vec![equilibrium + 0.02, 0.0, PI / 8.0, 0.0];
+        let (a, b) = (0.0, 50.0);
+        let n: u32 = 10000;
+        let solutions = rk4(a, b, state0, |t, state| self.derivatives(t, state), n);
+        self.solutions = Some(solutions);
+        self.current_index = 0;
+    }
+
+    fn equilibrium_length(&self) -> f32 {
+        self.rest_length + self.mass * self.gravity / self.stiffness
+    }
+
+    /// Splits the total energy into the swinging part and the bouncing (radial) part.
+    fn energies(&self, state: &[f32]) -> (f32, f32) {
+        let (r, r_dot, theta, theta_dot) = (state[0], state[1], state[2], state[3]);
+        let stretch = r - self.equilibrium_length();
+        let bounce = 0.5 * self.mass * r_dot * r_dot + 0.5 * self.stiffness * stretch * stretch;
+        let swing = 0.5 * self.mass * r * r * theta_dot * theta_dot
+            + self.mass * self.gravity * r * (1.0 - theta.cos());
+        (swing, bounce)
+    }
+}
+
+/// Draws a zig-zag spring between two screen points.
+pub fn draw_spring(
+    canvas: &mut Canvas<Window>,
+    from: (f32, f32),
+    to: (f32, f32),
+    width: f32,
+    color: Color,
+) {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let length = (dx * dx + dy * dy).sqrt().max(1e-3);
+    let (nx, ny) = (-dy / length, dx / length);
+    let mut last = from;
+    for i in 1..=SPRING_COILS {
+        let t = i as f32 / SPRING_COILS as f32;
+        let side = if i == SPRING_COILS {
+            0.0
+        } else if i % 2 == 0 {
+            width
+        } else {
+            -width
+        };
+        let point = (from.0 + dx * t + nx * side, from.1 + dy * t + ny * side);
+        let _ = canvas.line(
+            last.0 as i16,
+            last.1 as i16,
+            point.0 as i16,
+            point.1 as i16,
+            color,
+        );
+        last = point;
+    }
+}
+
+impl Scene for ElasticPendulum {
+    fn handle_event(&mut self, ctx: &mut GlobalContext, event: &Event) {
+        if let Event::KeyDown {
+            keycode: Some(k), ..
+        } = event
+        {
+            match k {
+                Keycode::Escape => {
+                    self.done = true;
+                }
+                Keycode::R => {
+                    self.current_index = 0;
+                }
+                Keycode::Left => {
+                    ctx.simulation_speed -= 0.1;
+                }
+                Keycode::Right => {
+                    ctx.simulation_speed += 0.1;
+                }
+                Keycode::Up => {
+                    self.stiffness *= 1.1;
+                    self.solve();
+                }
+                Keycode::Down => {
+                    self.stiffness /= 1.1;
+                    self.solve();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn update(&mut self, ctx: &mut GlobalContext, _dt: f32) {
+        if self.solutions.is_none() {
+            self.solve();
+        }
+        if let Some((_, ref sol)) = self.solutions {
+            if (self.current_index + 1 + (ctx.simulation_speed * 10.) as usize) < sol.len() {
+                self.current_index += 1 + (ctx.simulation_speed * 10.) as usize;
+            } else {
+                self.current_index = sol.len() - 1
+            }
+        }
+    }
+
+    fn render(&mut self, _ctx: &GlobalContext, canvas: &mut Canvas<Window>) {
+        if let Some((_, ref solutions)) = self.solutions {
+            let (width, height) = canvas.output_size().unwrap_or((800, 600));
+            let pivot = ((width / 2) as f32, (height / 3) as f32);
+            let scale = 300.0;
+
+            let state = &solutions[self.current_index];
+            let (r, theta) = (state[0], state[2]);
+            let bob = (
+                pivot.0 + scale * r * theta.sin(),
+                pivot.1 + scale * r * theta.cos(),
+            );
+
+            // Trail of recent bob positions
+            let trail_start = self.current_index.saturating_sub(400);
+            for (i, s) in solutions[trail_start..self.current_index]
+                .iter()
+                .enumerate()
+            {
+                let t = i as f32 / (self.current_index - trail_start).max(1) as f32;
+                let px = pivot.0 + scale * s[0] * s[2].sin();
+                let py = pivot.1 + scale * s[0] * s[2].cos();
+                let _ = canvas.pixel(
+                    px as i16,
+                    py as i16,
+                    Color::RGBA(255, 255, 0, (255.0 * t) as u8),
+                );
+            }
+
+            draw_spring(canvas, pivot, bob, 12.0, Color::RGB(255, 255, 255));
+            let _ = canvas.filled_circle(bob.0 as i16, bob.1 as i16, 25, Color::RGB(0, 255, 0));
+
+            let (swing, bounce) = self.energies(state);
+            let total = (swing + bounce).max(1e-6);
+            let bar_width = 300.0;
+            let _ = canvas.box_(
+                10,
+                30,
+                10 + (bar_width * swing / total) as i16,
+                45,
+                Color::RGB(0, 200, 255),
+            );
+            let _ = canvas.box_(
+                10,
+                55,
+                10 + (bar_width * bounce / total) as i16,
+                70,
+                Color::RGB(255, 120, 0),
+            );
+            let _ = canvas.string(
+                10,
+                10,
+                &format!("k = {:.2} N/m  (Up/Down)", self.stiffness),
+                (255, 255, 255, 255),
+            );
+            let _ = canvas.string(
+                320,
+                34,
+                &format!("swing  {:.3} J", swing),
+                (255, 255, 255, 255),
+            );
+            let _ = canvas.string(
+                320,
+                59,
+                &format!("bounce {:.3} J", bounce),
+                (255, 255, 255, 255),
+            );
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.done
+    }
+}
@@ -0,0 +1,506 @@
+use crate::engine::{GlobalContext, Scene};
+use crate::models::force_field::{ForceFields, LinearDrag};
+use crate::models::particle::Particle;
+use crate::models::particle_system::ParticleSystem;
+use crate::utils::contour::{marching_squares, Segment};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use sdl2::gfx::primitives::DrawRenderer;
+use sdl2::mouse::MouseButton;
+use sdl2::{event::Event, keyboard::Keycode};
+use sdl2::{render::Canvas, video::Window};
+
+/// Coulomb constant in px³·mass/(s²·charge²): two unit charges of unit mass 100 px apart
+/// accelerate at 1000 px/s².
+const COULOMB_K: f32 = 1.0e7;
+/// Plummer softening so close charges feel a finite force.
+const SOFTENING: f32 = 10.0;
+/// Spacing of the grid the potential and field are sampled on, in px.
+const GRID: f32 = 10.0;
+const SUBSTEPS: usize = 4;
+const FREE_RADIUS: f32 = 6.0;
+/// Field lines leaving each unit of positive charge.
+const LINES_PER_CHARGE: f32 = 8.0;
+const LINE_STEP: f32 = 4.0;
+const LINE_MAX_STEPS: usize = 800;
+/// Potential 100 px from a unit charge; equipotentials are drawn at powers of two of it.
+const POTENTIAL_UNIT: f32 = COULOMB_K / 100.0;
+/// How close a click must be to a fixed charge to pick it up.
+const PICK_DISTANCE: f32 = 15.0;
+
+/// A charge that stays where it is put.
+struct FixedCharge {
+    x: f32,
+    y: f32,
+    q: f32,
+}
+
+/// Field and potential at `(x, y)` from point charges `(x, y, q)`, leaving out `skip`.
+fn coulomb(sources: &[(f32, f32, f32)], x: f32, y: f32, skip: Option<usize>) -> (f32, f32, f32) {
+    let eps_sq = SOFTENING * SOFTENING;
+    let (mut ex, mut ey, mut v) = (0.0, 0.0, 0.0);
+    for (k, &(sx, sy, q)) in sources.iter().enumerate() {
+        if Some(k) == skip {
+            continue;
+        }
+        let (dx, dy) = (x - sx, y - sy);
+        let inv_r = 1.0 / (dx * dx + dy * dy + eps_sq).sqrt();
+        let kq = COULOMB_K * q;
+        ex += kq * dx * inv_r * inv_r * inv_r;
+        ey += kq * dy * inv_r * inv_r * inv_r;
+        v += kq * inv_r;
+    }
+    (ex, ey, v)
+}
+
+/// Point charges interacting through Coulomb's law. Fixed charges are placed with the mouse;
+/// free charges move and bounce off the screen edges. The potential and field are sampled on a
+/// grid every frame to draw equipotential contours and field-line streamlines.
+pub struct Electrostatics {
+    free: ParticleSystem,
+    fixed: Vec<FixedCharge>,
+    fields: ForceFields,
+    /// Every charge as `(x, y, q)`, fixed ones first
+    sources: Vec<(f32, f32, f32)>,
+    cols: usize,
+    rows: usize,
+    /// Potential and field at the grid samples, row by row
+    potential: Vec<f32>,
+    field: Vec<(f32, f32)>,
+    /// Equipotential segments in grid coordinates, with their potential
+    contours: Vec<(f32, Vec<Segment>)>,
+    field_lines: Vec<Vec<(f32, f32)>>,
+    show_equipotentials: bool,
+    show_field_lines: bool,
+    damping: bool,
+    /// Charge given to newly placed charges
+    next_charge: f32,
+    held: Option<usize>,
+    cursor: (f32, f32),
+    width: f32,
+    height: f32,
+    rng: StdRng,
+    done: bool,
+}
+
+impl Electrostatics {
+    pub fn new(ctx: &GlobalContext) -> Self {
+        let (width, height) = (ctx.screen_width as f32, ctx.screen_height as f32);
+        let cols = (width / GRID) as usize + 1;
+        let rows = (height / GRID) as usize + 1;
+        let mut scene = Electrostatics {
+            free: ParticleSystem::new(),
+            fixed: Vec::new(),
+            fields: ForceFields::none(),
+            sources: Vec::new(),
+            cols,
+            rows,
+            potential: vec![0.0; cols * rows],
+            field: vec![(0.0, 0.0); cols * rows],
+            contours: Vec::new(),
+            field_lines: Vec::new(),
+            show_equipotentials: true,
+            show_field_lines: true,
+            damping: false,
+            next_charge: 1.0,
+            held: None,
+            cursor: (0.0, 0.0),
+            width,
+            height,
+            rng: StdRng::seed_from_u64(42),
+            done: false,
+        };
+        scene.preset(1);
+        scene
+    }
+
+    fn preset(&mut self, number: u8) {
+        self.free.clear();
+        self.fixed.clear();
+        self.held = None;
+        let (cx, cy) = (self.width * 0.5, self.height * 0.5);
+        let mut fixed = |x: f32, y: f32, q: f32| self.fixed.push(FixedCharge { x, y, q });
+        match number {
+            1 => {
+                fixed(cx - 150.0, cy, 2.0);
+                fixed(cx + 150.0, cy, -2.0);
+            }
+            2 => {
+                for (k, (dx, dy)) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+                    .into_iter()
+                    .enumerate()
+                {
+                    let q = if k % 2 == 0 { 1.5 } else { -1.5 };
+                    fixed(cx + dx * 150.0, cy + dy * 150.0, q);
+                }
+            }
+            3 => {
+                // Parallel plates: two rows of opposite charge
+                for k in 0..15 {
+                    let x = cx - 280.0 + 40.0 * k as f32;
+                    fixed(x, cy - 120.0, 0.5);
+                    fixed(x, cy + 120.0, -0.5);
+                }
+            }
+            _ => {
+                // Free charges of both signs around a fixed positive core
+                fixed(cx, cy, 6.0);
+                for k in 0..40 {
+                    let q = if k % 4 == 0 { 1.0 } else { -1.0 };
+                    let x = self.rng.gen_range(cx - 400.0..cx + 400.0);
+                    let y = self.rng.gen_range(cy - 300.0..cy + 300.0);
+                    self.free.push(
+                        Particle::new(x, y, 0.0, 0.0, FREE_RADIUS)
+                            .with_mass(1.0)
+                            .with_charge(q),
+                    );
+                }
+            }
+        }
+        self.refresh_field();
+    }
+
+    fn collect_sources(&mut self) {
+        self.sources.clear();
+        self.sources
+            .extend(self.fixed.iter().map(|c| (c.x, c.y, c.q)));
+        let f = &self.free;
+        self.sources
+            .extend((0..f.len()).map(|i| (f.x[i], f.y[i], f.charge[i])));
+    }
+
+    /// Moves the free charges by one substep: Coulomb kick, then drift, walls and drag.
+    fn step(&mut self, h: f32, ctx: &GlobalContext) {
+        self.collect_sources();
+        let offset = self.fixed.len();
+        let sources = &self.sources;
+        let f = &self.free;
+        let accelerations: Vec<(f32, f32)> = (0..f.len())
+            .into_par_iter()
+            .map(|i| {
+                let (ex, ey, _) = coulomb(sources, f.x[i], f.y[i], Some(offset + i));
+                let qm = f.charge[i] * f.inverse_mass(i);
+                (qm * ex, qm * ey)
+            })
+            .collect();
+        for (i, (ax, ay)) in accelerations.into_iter().enumerate() {
+            self.free.vx[i] += ax * h;
+            self.free.vy[i] += ay * h;
+        }
+        self.free
+            .integrate(&self.fields, h, ctx.screen_width, ctx.screen_height);
+    }
+
+    /// Samples the potential and field on the grid and rebuilds contours and field lines.
+    fn refresh_field(&mut self) {
+        self.collect_sources();
+        let (cols, sources) = (self.cols, &self.sources);
+        self.potential
+            .par_iter_mut()
+            .zip(self.field.par_iter_mut())
+            .enumerate()
+            .for_each(|(k, (v, e))| {
+                let (x, y) = ((k % cols) as f32 * GRID, (k / cols) as f32 * GRID);
+                let (ex, ey, potential) = coulomb(sources, x, y, None);
+                *v = potential;
+                *e = (ex, ey);
+            });
+
+        self.contours.clear();
+        if self.show_equipotentials {
+            for n in -3..=3 {
+                for sign in [1.0, -1.0] {
+                    let level = sign * POTENTIAL_UNIT * 2f32.powi(n);
+                    let mut segments = Vec::new();
+                    marching_squares(&self.potential, self.cols, self.rows, level, &mut segments);
+                    self.contours.push((level, segments));
+                }
+            }
+        }
+
+        self.field_lines.clear();
+        if self.show_field_lines {
+            self.trace_field_lines();
+        }
+    }
+
+    /// Field at a point, interpolated bilinearly from the grid.
+    fn sample_field(&self, x: f32, y: f32) -> (f32, f32) {
+        let (gx, gy) = (
+            (x / GRID).clamp(0.0, (self.cols - 1) as f32 - 1e-3),
+            (y / GRID).clamp(0.0, (self.rows - 1) as f32 - 1e-3),
+        );
+        let (i, j) = (gx as usize, gy as usize);
+        let (tx, ty) = (gx - i as f32, gy - j as f32);
+        let at = |i: usize, j: usize| self.field[j * self.cols + i];
+        let lerp =
+            |a: (f32, f32), b: (f32, f32), t: f32| (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t);
+        lerp(
+            lerp(at(i, j), at(i + 1, j), tx),
+            lerp(at(i, j + 1), at(i + 1, j + 1), tx),
+            ty,
+        )
+    }
+
+    /// Lines start on positive charges and follow the field. If negative charge outweighs
+    /// positive, the excess gets lines traced backwards from the negative charges.
+    fn trace_field_lines(&mut self) {
+        let positive: f32 = self.sources.iter().map(|s| s.2.max(0.0)).sum();
+        let negative: f32 = self.sources.iter().map(|s| (-s.2).max(0.0)).sum();
+        let excess = if negative > positive {
+            (negative - positive) / negative
+        } else {
+            0.0
+        };
+
+        let mut lines = Vec::new();
+        for (k, &(sx, sy, q)) in self.sources.iter().enumerate() {
+            let (count, direction) = if q > 0.0 {
+                (LINES_PER_CHARGE * q, 1.0)
+            } else {
+                (LINES_PER_CHARGE * -q * excess, -1.0)
+            };
+            let count = count.round() as usize;
+            for n in 0..count {
+                let angle = (n as f32 + 0.5) / count as f32 * std::f32::consts::TAU;
+                let start = (sx + SOFTENING * angle.cos(), sy + SOFTENING * angle.sin());
+                lines.push(self.trace_line(start, direction, k));
+            }
+        }
+        self.field_lines = lines;
+    }
+
+    /// Follows the field (or against it for `direction` -1) with midpoint steps until the line
+    /// leaves the screen or reaches a charge other than `origin`.
+    fn trace_line(&self, start: (f32, f32), direction: f32, origin: usize) -> Vec<(f32, f32)> {
+        let unit = |(ex, ey): (f32, f32)| {
+            let len = ex.hypot(ey);
+            (len > 1e-9).then(|| (direction * ex / len, direction * ey / len))
+        };
+        let mut line = vec![start];
+        let mut p = start;
+        for _ in 0..LINE_MAX_STEPS {
+            let Some((dx, dy)) = unit(self.sample_field(p.0, p.1)) else {
+                break;
+            };
+            let mid = (p.0 + dx * LINE_STEP * 0.5, p.1 + dy * LINE_STEP * 0.5);
+            let Some((dx, dy)) = unit(self.sample_field(mid.0, mid.1)) else {
+                break;
+            };
+            p = (p.0 + dx * LINE_STEP, p.1 + dy * LINE_STEP);
+            line.push(p);
+
+            let outside = p.0 < 0.0 || p.1 < 0.0 || p.0 > self.width || p.1 > self.height;
+            let arrived = self
+                .sources
+                .iter()
+                .enumerate()
+                .any(|(k, &(sx, sy, _))| k != origin && (p.0 - sx).hypot(p.1 - sy) < SOFTENING);
+            if outside || arrived {
+                break;
+            }
+        }
+        line
+    }
+
+    fn fixed_near(&self, x: f32, y: f32) -> Option<usize> {
+        self.fixed
+            .iter()
+            .position(|c| (c.x - x).hypot(c.y - y) < PICK_DISTANCE)
+    }
+
+    /// Removes the charge, fixed or free, closest to the cursor.
+    fn remove_nearest(&mut self) {
+        let (x, y) = self.cursor;
+        let distance = |cx: f32, cy: f32| (cx - x).hypot(cy - y);
+        let fixed = self
+            .fixed
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (i, distance(c.x, c.y)))
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        let free = (0..self.free.len())
+            .map(|i| (i, distance(self.free.x[i], self.free.y[i])))
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        self.held = None;
+        match (fixed, free) {
+            (Some((i, df)), Some((_, dm))) if df <= dm => {
+                self.fixed.remove(i);
+            }
+            (Some((i, _)), None) => {
+                self.fixed.remove(i);
+            }
+            (_, Some((j, _))) => self.free.retain(|i| i != j),
+            (None, None) => {}
+        }
+    }
+
+    fn charge_color(q: f32) -> (u8, u8, u8, u8) {
+        if q >= 0.0 {
+            (255, 80, 70, 255)
+        } else {
+            (80, 130, 255, 255)
+        }
+    }
+}
+
+impl Scene for Electrostatics {
+    fn update(&mut self, ctx: &mut GlobalContext, dt: f32) {
+        if !ctx.paused && !self.free.is_empty() {
+            let h = dt * ctx.simulation_speed / SUBSTEPS as f32;
+            for _ in 0..SUBSTEPS {
+                self.step(h, ctx);
+            }
+        }
+        self.refresh_field();
+    }
+
+    fn render(&mut self, _ctx: &GlobalContext, canvas: &mut Canvas<Window>) {
+        for (level, segments) in &self.contours {
+            let color = if *level > 0.0 {
+                (120, 50, 50, 255)
+            } else {
+                (50, 70, 130, 255)
+            };
+            for &((x0, y0), (x1, y1)) in segments {
+                let _ = canvas.line(
+                    (x0 * GRID) as i16,
+                    (y0 * GRID) as i16,
+                    (x1 * GRID) as i16,
+                    (y1 * GRID) as i16,
+                    color,
+                );
+            }
+        }
+        for line in &self.field_lines {
+            for pair in line.windows(2) {
+                let _ = canvas.line(
+                    pair[0].0 as i16,
+                    pair[0].1 as i16,
+                    pair[1].0 as i16,
+                    pair[1].1 as i16,
+                    (200, 200, 200, 255),
+                );
+            }
+        }
+
+        for c in &self.fixed {
+            let r = (8.0 * c.q.abs().sqrt()) as i16 + 2;
+            let (x, y) = (c.x as i16, c.y as i16);
+            let color = Self::charge_color(c.q);
+            let _ = canvas.box_(x - r, y - r, x + r, y + r, color);
+            let _ = canvas.rectangle(x - r, y - r, x + r, y + r, (255, 255, 255, 255));
+        }
+        let f = &self.free;
+        for i in 0..f.len() {
+            let color = Self::charge_color(f.charge[i]);
+            let _ = canvas.filled_circle(f.x[i] as i16, f.y[i] as i16, f.radius[i] as i16, color);
+        }
+
+        let white = (255, 255, 255, 255);
+        let sign = if self.next_charge > 0.0 { "+" } else { "-" };
+        let lines = [
+            format!(
+                "{} fixed, {} free charges. Presets: 1 dipole, 2 quadrupole, 3 plates, 4 free charges",
+                self.fixed.len(),
+                self.free.len()
+            ),
+            format!(
+                "Left click: place fixed {}{:.1} (Q sign, wheel size) or drag one, right click: free charge, X remove, C clear",
+                sign,
+                self.next_charge.abs()
+            ),
+            format!(
+                "Equipotentials {} (V), field lines {} (L), damping {} (D)",
+                if self.show_equipotentials { "on" } else { "off" },
+                if self.show_field_lines { "on" } else { "off" },
+                if self.damping { "on" } else { "off" }
+            ),
+        ];
+        for (k, line) in lines.iter().enumerate() {
+            let _ = canvas.string(10, 10 + 15 * k as i16, line, white);
+        }
+    }
+
+    fn handle_event(&mut self, ctx: &mut GlobalContext, event: &Event) {
+        match *event {
+            Event::KeyDown {
+                keycode: Some(k), ..
+            } => match k {
+                Keycode::Escape => self.done = true,
+                Keycode::Space => ctx.paused = !ctx.paused,
+                Keycode::Left => ctx.simulation_speed -= 0.1,
+                Keycode::Right => ctx.simulation_speed += 0.1,
+                Keycode::Num1 => self.preset(1),
+                Keycode::Num2 => self.preset(2),
+                Keycode::Num3 => self.preset(3),
+                Keycode::Num4 => self.preset(4),
+                Keycode::Q => self.next_charge = -self.next_charge,
+                Keycode::V => self.show_equipotentials = !self.show_equipotentials,
+                Keycode::L => self.show_field_lines = !self.show_field_lines,
+                Keycode::D => {
+                    self.damping = !self.damping;
+                    self.fields = ForceFields::none();
+                    if self.damping {
+                        self.fields.push(Box::new(LinearDrag { coefficient: 0.3 }));
+                    }
+                }
+                Keycode::X => self.remove_nearest(),
+                Keycode::C => {
+                    self.fixed.clear();
+                    self.free.clear();
+                    self.held = None;
+                }
+                _ => {}
+            },
+            Event::MouseWheel { y, .. } => {
+                let size = (self.next_charge.abs() + 0.5 * y as f32).clamp(0.5, 5.0);
+                self.next_charge = size.copysign(self.next_charge);
+            }
+            Event::MouseMotion { x, y, .. } => {
+                self.cursor = (x as f32, y as f32);
+                if let Some(i) = self.held {
+                    (self.fixed[i].x, self.fixed[i].y) = self.cursor;
+                }
+            }
+            Event::MouseButtonDown {
+                mouse_btn: MouseButton::Left,
+                x,
+                y,
+                ..
+            } => {
+                let (x, y) = (x as f32, y as f32);
+                self.held = self.fixed_near(x, y);
+                if self.held.is_none() {
+                    self.fixed.push(FixedCharge {
+                        x,
+                        y,
+                        q: self.next_charge,
+                    });
+                }
+            }
+            Event::MouseButtonUp {
+                mouse_btn: MouseButton::Left,
+                ..
+            } => self.held = None,
+            Event::MouseButtonDown {
+                mouse_btn: MouseButton::Right,
+                x,
+                y,
+                ..
+            } => {
+                self.free.push(
+                    Particle::new(x as f32, y as f32, 0.0, 0.0, FREE_RADIUS)
+                        .with_mass(1.0)
+                        .with_charge(self.next_charge),
+                );
+            }
+            _ => {}
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.done
+    }
+}
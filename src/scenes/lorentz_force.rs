@@ -0,0 +1,480 @@
+use crate::engine::{GlobalContext, Scene};
+use crate::utils::boris::{boris_half_step, boris_step, cross, Vec3};
+use crate::utils::RK4::rk4_step;
+use sdl2::gfx::primitives::DrawRenderer;
+use sdl2::mouse::MouseButton;
+use sdl2::{event::Event, keyboard::Keycode};
+use sdl2::{render::Canvas, video::Window};
+use std::collections::VecDeque;
+
+/// Integration step in seconds. Cyclotron periods are a few seconds, so this is well resolved
+/// for Boris; RK4 still loses energy slowly at this step.
+const STEP: f32 = 1.0 / 1000.0;
+const MAX_STEPS_PER_FRAME: usize = 100;
+const TRAIL_LENGTH: usize = 3000;
+/// Charge-to-mass ratio of spawned particles, up to sign.
+const Q_OVER_M: f32 = 1.0;
+/// Spawn velocity per pixel of mouse drag, in 1/s.
+const FLING_SCALE: f32 = 2.0;
+/// Distance over which `Bz` doubles in the gradient field, in px.
+const GRADIENT_LENGTH: f32 = 400.0;
+/// Distance from the mirror centre at which the axial field has doubled, in px.
+const MIRROR_LENGTH: f32 = 350.0;
+/// How far off screen a particle may go before it is dropped.
+const ESCAPE_MARGIN: f32 = 200.0;
+const E_STEP: f32 = 25.0;
+const B_STEP: f32 = 0.25;
+
+#[derive(Clone, Copy, PartialEq)]
+enum FieldShape {
+    /// `B` along z, into the screen since y points down, with the same strength everywhere
+    Uniform,
+    /// `B` along z growing linearly with x
+    Gradient,
+    /// `B` along x, strongest at both ends: a magnetic bottle
+    Mirror,
+}
+
+impl FieldShape {
+    fn next(self) -> Self {
+        match self {
+            FieldShape::Uniform => FieldShape::Gradient,
+            FieldShape::Gradient => FieldShape::Mirror,
+            FieldShape::Mirror => FieldShape::Uniform,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            FieldShape::Uniform => "uniform Bz",
+            FieldShape::Gradient => "gradient Bz",
+            FieldShape::Mirror => "magnetic mirror",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Integrator {
+    Boris,
+    Rk4,
+}
+
+/// The applied fields, positioned relative to the screen centre.
+#[derive(Clone, Copy)]
+struct EmField {
+    shape: FieldShape,
+    e: Vec3,
+    b0: f32,
+    center: (f32, f32),
+}
+
+impl EmField {
+    fn at(&self, p: Vec3) -> (Vec3, Vec3) {
+        let (x, y, z) = (p[0] - self.center.0, p[1] - self.center.1, p[2]);
+        let b = match self.shape {
+            FieldShape::Uniform => [0.0, 0.0, self.b0],
+            FieldShape::Gradient => [0.0, 0.0, self.b0 * (1.0 + x / GRADIENT_LENGTH).max(0.1)],
+            FieldShape::Mirror => {
+                // Bx = b0 (1 + s²); the radial part keeps div B = 0 near the axis
+                let s = x / MIRROR_LENGTH;
+                let radial = -self.b0 * s / MIRROR_LENGTH;
+                [self.b0 * (1.0 + s * s), radial * y, radial * z]
+            }
+        };
+        (self.e, b)
+    }
+
+    /// Kinetic plus electric potential energy per unit mass, conserved by the exact motion.
+    fn energy(&self, c: &Charge) -> f32 {
+        let v_sq: f32 = c.velocity.iter().map(|v| v * v).sum();
+        let r = [
+            c.position[0] - self.center.0,
+            c.position[1] - self.center.1,
+            c.position[2],
+        ];
+        let e_dot_r: f32 = self.e.iter().zip(r).map(|(e, r)| e * r).sum();
+        0.5 * v_sq - c.q_over_m * e_dot_r
+    }
+}
+
+struct Charge {
+    position: Vec3,
+    /// Half a step behind the position under Boris, at the same time under RK4
+    velocity: Vec3,
+    q_over_m: f32,
+    initial_energy: f32,
+    trail: VecDeque<(f32, f32)>,
+}
+
+/// Charged particles moving in applied electric and magnetic fields, shown in projection onto
+/// the screen plane. Presets demonstrate cyclotron orbits, E×B and grad-B drifts and a
+/// magnetic mirror. Particles can be pushed with Boris or RK4 to compare energy conservation.
+pub struct LorentzForce {
+    charges: Vec<Charge>,
+    field: EmField,
+    integrator: Integrator,
+    next_sign: f32,
+    /// Where a drag to spawn a particle started
+    drag_start: Option<(f32, f32)>,
+    cursor: (f32, f32),
+    pending: f32,
+    width: f32,
+    height: f32,
+    done: bool,
+}
+
+impl LorentzForce {
+    pub fn new(ctx: &GlobalContext) -> Self {
+        let (width, height) = (ctx.screen_width as f32, ctx.screen_height as f32);
+        let mut scene = LorentzForce {
+            charges: Vec::new(),
+            field: EmField {
+                shape: FieldShape::Uniform,
+                e: [0.0; 3],
+                b0: 2.0,
+                center: (width * 0.5, height * 0.5),
+            },
+            integrator: Integrator::Boris,
+            next_sign: 1.0,
+            drag_start: None,
+            cursor: (0.0, 0.0),
+            pending: 0.0,
+            width,
+            height,
+            done: false,
+        };
+        scene.preset(1);
+        scene
+    }
+
+    fn preset(&mut self, number: u8) {
+        self.charges.clear();
+        let (cx, cy) = self.field.center;
+        match number {
+            1 => {
+                // Cyclotron orbits: radius v / (q/m B), opposite senses for opposite signs
+                self.field.shape = FieldShape::Uniform;
+                self.field.e = [0.0; 3];
+                self.field.b0 = 2.0;
+                self.spawn([cx - 250.0, cy, 0.0], [0.0, -200.0, 0.0], Q_OVER_M);
+                self.spawn([cx + 250.0, cy, 0.0], [0.0, -200.0, 0.0], -Q_OVER_M);
+                self.spawn([cx, cy + 200.0, 0.0], [100.0, 0.0, 0.0], 2.0 * Q_OVER_M);
+            }
+            2 => {
+                // E×B drift at |E| / |B| = 50 px/s to the right, whatever the charge
+                self.field.shape = FieldShape::Uniform;
+                self.field.e = [0.0, 100.0, 0.0];
+                self.field.b0 = 2.0;
+                let x = cx - 700.0;
+                self.spawn([x, cy - 200.0, 0.0], [0.0, 0.0, 0.0], Q_OVER_M);
+                self.spawn([x, cy, 0.0], [0.0, 150.0, 0.0], Q_OVER_M);
+                self.spawn([x, cy + 200.0, 0.0], [0.0, 0.0, 0.0], -Q_OVER_M);
+            }
+            3 => {
+                // Grad-B drift along y, in opposite directions for opposite signs
+                self.field.shape = FieldShape::Gradient;
+                self.field.e = [0.0; 3];
+                self.field.b0 = 2.0;
+                self.spawn([cx - 100.0, cy, 0.0], [0.0, 200.0, 0.0], Q_OVER_M);
+                self.spawn([cx + 100.0, cy, 0.0], [0.0, 200.0, 0.0], -Q_OVER_M);
+            }
+            _ => {
+                // Magnetic bottle: small pitch angles escape through the loss cone
+                self.field.shape = FieldShape::Mirror;
+                self.field.e = [0.0; 3];
+                self.field.b0 = 2.0;
+                for pitch in [15f32, 45.0, 70.0] {
+                    let pitch = pitch.to_radians();
+                    let velocity = [200.0 * pitch.cos(), 200.0 * pitch.sin(), 0.0];
+                    self.spawn([cx, cy, 0.0], velocity, Q_OVER_M);
+                }
+            }
+        }
+    }
+
+    fn spawn(&mut self, position: Vec3, velocity: Vec3, q_over_m: f32) {
+        let field = self.field;
+        let velocity = match self.integrator {
+            Integrator::Boris => {
+                boris_half_step(position, velocity, q_over_m, -STEP, |p| field.at(p))
+            }
+            Integrator::Rk4 => velocity,
+        };
+        let mut charge = Charge {
+            position,
+            velocity,
+            q_over_m,
+            initial_energy: 0.0,
+            trail: VecDeque::with_capacity(TRAIL_LENGTH),
+        };
+        charge.initial_energy = self.field.energy(&charge);
+        self.charges.push(charge);
+    }
+
+    /// Starts every energy measurement again, after the field has been changed.
+    fn reset_energies(&mut self) {
+        let field = self.field;
+        for c in &mut self.charges {
+            c.initial_energy = field.energy(c);
+        }
+    }
+
+    fn step(&mut self) {
+        let field = self.field;
+        for c in &mut self.charges {
+            match self.integrator {
+                Integrator::Boris => {
+                    boris_step(&mut c.position, &mut c.velocity, c.q_over_m, STEP, |p| {
+                        field.at(p)
+                    });
+                }
+                Integrator::Rk4 => {
+                    let state = [c.position, c.velocity].concat();
+                    let q_over_m = c.q_over_m;
+                    let next = rk4_step(0.0, STEP, &state, |_, w| {
+                        let (p, v) = ([w[0], w[1], w[2]], [w[3], w[4], w[5]]);
+                        let (e, b) = field.at(p);
+                        let f = cross(v, b);
+                        vec![
+                            v[0],
+                            v[1],
+                            v[2],
+                            q_over_m * (e[0] + f[0]),
+                            q_over_m * (e[1] + f[1]),
+                            q_over_m * (e[2] + f[2]),
+                        ]
+                    });
+                    c.position = [next[0], next[1], next[2]];
+                    c.velocity = [next[3], next[4], next[5]];
+                }
+            }
+            if c.trail.len() == TRAIL_LENGTH {
+                c.trail.pop_front();
+            }
+            c.trail.push_back((c.position[0], c.position[1]));
+        }
+        let (w, h) = (self.width, self.height);
+        self.charges.retain(|c| {
+            let (x, y) = (c.position[0], c.position[1]);
+            x > -ESCAPE_MARGIN
+                && y > -ESCAPE_MARGIN
+                && x < w + ESCAPE_MARGIN
+                && y < h + ESCAPE_MARGIN
+        });
+    }
+
+    /// Largest relative energy error over all particles.
+    fn energy_drift(&self) -> f32 {
+        self.charges
+            .iter()
+            .map(|c| {
+                let scale = c.initial_energy.abs().max(1.0);
+                (self.field.energy(c) - c.initial_energy).abs() / scale
+            })
+            .fold(0.0, f32::max)
+    }
+
+    fn render_field(&self, canvas: &mut Canvas<Window>) {
+        let field = &self.field;
+        match field.shape {
+            FieldShape::Uniform | FieldShape::Gradient => {
+                // Crosses for B into the screen, dots for B out of it, sized by strength
+                let color = (60, 60, 90, 255);
+                for j in 0..(self.height / 80.0) as i32 + 1 {
+                    for i in 0..(self.width / 80.0) as i32 + 1 {
+                        let (x, y) = (i as f32 * 80.0 + 40.0, j as f32 * 80.0 + 40.0);
+                        let bz = field.at([x, y, 0.0]).1[2];
+                        let size = (2.0 * bz.abs()).clamp(1.0, 10.0) as i16;
+                        let (x, y) = (x as i16, y as i16);
+                        if bz >= 0.0 {
+                            let _ = canvas.line(x - size, y - size, x + size, y + size, color);
+                            let _ = canvas.line(x - size, y + size, x + size, y - size, color);
+                        } else {
+                            let _ = canvas.filled_circle(x, y, size, color);
+                        }
+                    }
+                }
+            }
+            FieldShape::Mirror => {
+                // Field lines conserve flux, so their radius goes as 1 / sqrt(Bx)
+                let (cx, cy) = field.center;
+                for r0 in [60.0, 120.0, 180.0, 240.0] {
+                    for sign in [1.0, -1.0] {
+                        let mut previous = None;
+                        for k in 0..=(self.width / 8.0) as i32 {
+                            let x = k as f32 * 8.0;
+                            let s = (x - cx) / MIRROR_LENGTH;
+                            let y = cy + sign * r0 / (1.0 + s * s).sqrt();
+                            if let Some((px, py)) = previous {
+                                let _ = canvas.line(px, py, x as i16, y as i16, (60, 60, 90, 255));
+                            }
+                            previous = Some((x as i16, y as i16));
+                        }
+                    }
+                }
+            }
+        }
+
+        // Electric field arrow in the corner
+        let (ex, ey) = (field.e[0], field.e[1]);
+        let length = ex.hypot(ey);
+        if length > 0.0 {
+            let (ox, oy) = (self.width - 120.0, 120.0);
+            let scale = 80.0 / length.max(100.0);
+            let (tx, ty) = (ox + ex * scale, oy + ey * scale);
+            let color = (230, 200, 80, 255);
+            let _ = canvas.thick_line(ox as i16, oy as i16, tx as i16, ty as i16, 2, color);
+            let _ = canvas.filled_circle(tx as i16, ty as i16, 4, color);
+            let _ = canvas.string(ox as i16 - 10, oy as i16 + 90, "E", color);
+        }
+    }
+}
+
+impl Scene for LorentzForce {
+    fn update(&mut self, ctx: &mut GlobalContext, dt: f32) {
+        if ctx.paused {
+            return;
+        }
+        self.pending += dt * ctx.simulation_speed.max(0.0);
+        let mut steps = 0;
+        while self.pending >= STEP && steps < MAX_STEPS_PER_FRAME {
+            self.step();
+            self.pending -= STEP;
+            steps += 1;
+        }
+        self.pending = self.pending.min(STEP);
+    }
+
+    fn render(&mut self, _ctx: &GlobalContext, canvas: &mut Canvas<Window>) {
+        self.render_field(canvas);
+
+        for c in &self.charges {
+            let color = if c.q_over_m > 0.0 {
+                (255, 110, 90, 255)
+            } else {
+                (100, 160, 255, 255)
+            };
+            let faded = (color.0 / 2, color.1 / 2, color.2 / 2, 255);
+            let points: Vec<(i16, i16)> =
+                c.trail.iter().map(|&(x, y)| (x as i16, y as i16)).collect();
+            for pair in points.windows(2) {
+                let _ = canvas.line(pair[0].0, pair[0].1, pair[1].0, pair[1].1, faded);
+            }
+            let _ = canvas.filled_circle(c.position[0] as i16, c.position[1] as i16, 5, color);
+        }
+
+        if let Some((sx, sy)) = self.drag_start {
+            let (x, y) = self.cursor;
+            let _ = canvas.line(
+                sx as i16,
+                sy as i16,
+                x as i16,
+                y as i16,
+                (200, 200, 200, 255),
+            );
+        }
+
+        let white = (255, 255, 255, 255);
+        let f = &self.field;
+        let integrator = match self.integrator {
+            Integrator::Boris => "Boris",
+            Integrator::Rk4 => "RK4",
+        };
+        let lines = [
+            format!(
+                "Presets: 1 cyclotron, 2 ExB drift, 3 grad-B drift, 4 magnetic mirror. Field: {} (G)",
+                f.shape.name()
+            ),
+            format!(
+                "B0: {:.2} (Up/Down), E: ({:.0}, {:.0}) (A/D, W/S)",
+                f.b0, f.e[0], f.e[1]
+            ),
+            format!(
+                "Integrator: {} (I), max energy drift: {:.2e}, {} particles",
+                integrator,
+                self.energy_drift(),
+                self.charges.len()
+            ),
+            format!(
+                "Drag to launch a {} charge (Q flips sign), C clear, Space pause",
+                if self.next_sign > 0.0 { "positive" } else { "negative" }
+            ),
+        ];
+        for (k, line) in lines.iter().enumerate() {
+            let _ = canvas.string(10, 10 + 15 * k as i16, line, white);
+        }
+    }
+
+    fn handle_event(&mut self, ctx: &mut GlobalContext, event: &Event) {
+        match *event {
+            Event::KeyDown {
+                keycode: Some(k), ..
+            } => {
+                match k {
+                    Keycode::Escape => self.done = true,
+                    Keycode::Space => ctx.paused = !ctx.paused,
+                    Keycode::Left => ctx.simulation_speed -= 0.1,
+                    Keycode::Right => ctx.simulation_speed += 0.1,
+                    Keycode::Num1 => self.preset(1),
+                    Keycode::Num2 => self.preset(2),
+                    Keycode::Num3 => self.preset(3),
+                    Keycode::Num4 => self.preset(4),
+                    Keycode::Q => self.next_sign = -self.next_sign,
+                    Keycode::C => self.charges.clear(),
+                    Keycode::I => {
+                        // Move the velocities to where the other integrator keeps them
+                        let (integrator, dt) = match self.integrator {
+                            Integrator::Boris => (Integrator::Rk4, STEP),
+                            Integrator::Rk4 => (Integrator::Boris, -STEP),
+                        };
+                        let field = self.field;
+                        for c in &mut self.charges {
+                            c.velocity =
+                                boris_half_step(c.position, c.velocity, c.q_over_m, dt, |p| {
+                                    field.at(p)
+                                });
+                        }
+                        self.integrator = integrator;
+                    }
+                    Keycode::G => self.field.shape = self.field.shape.next(),
+                    Keycode::Up => self.field.b0 += B_STEP,
+                    Keycode::Down => self.field.b0 -= B_STEP,
+                    Keycode::A => self.field.e[0] -= E_STEP,
+                    Keycode::D => self.field.e[0] += E_STEP,
+                    Keycode::W => self.field.e[1] -= E_STEP,
+                    Keycode::S => self.field.e[1] += E_STEP,
+                    _ => return,
+                }
+                // Any change to the field changes what counts as conserved
+                self.reset_energies();
+            }
+            Event::MouseMotion { x, y, .. } => self.cursor = (x as f32, y as f32),
+            Event::MouseButtonDown {
+                mouse_btn: MouseButton::Left,
+                x,
+                y,
+                ..
+            } => self.drag_start = Some((x as f32, y as f32)),
+            Event::MouseButtonUp {
+                mouse_btn: MouseButton::Left,
+                x,
+                y,
+                ..
+            } => {
+                if let Some((sx, sy)) = self.drag_start.take() {
+                    let velocity = [
+                        (x as f32 - sx) * FLING_SCALE,
+                        (y as f32 - sy) * FLING_SCALE,
+                        0.0,
+                    ];
+                    self.spawn([sx, sy, 0.0], velocity, self.next_sign * Q_OVER_M);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.done
+    }
+}
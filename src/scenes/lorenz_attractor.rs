@@ -1,4 +1,5 @@
 use crate::engine::{GlobalContext, Scene};
+use crate::utils::camera::Camera;
 use crate::utils::RK4::rk4;
 use sdl2::{event::Event, keyboard::Keycode};
 use sdl2::{render::Canvas, video::Window};
@@ -10,9 +11,7 @@ pub struct LorenzAttractor {
     solutions: Option<(Vec<f32>, Vec<Vec<f32>>)>,
     current_index: usize,
     done: bool,
-    camera_rotation: (f32, f32),
-    is_mouse_down: bool,
-    zoom: f32,
+    camera: Camera,
 }
 
 impl LorenzAttractor {
@@ -24,9 +23,7 @@ impl LorenzAttractor {
             solutions: None,
             current_index: 0,
             done: false,
-            camera_rotation: (0.0, 0.0),
-            is_mouse_down: false,
-            zoom: 1.0,
+            camera: Camera::new(50.0, 200.0),
         };
         lorenz_attractor.solve();
         lorenz_attractor
@@ -50,35 +47,6 @@ impl LorenzAttractor {
         }
         self.solutions = Some(solutions);
     }
-
-    fn rotate_3d(&self, point: (f32, f32, f32)) -> (f32, f32, f32) {
-        let (x, y, z) = point;
-        let (theta_x, theta_y) = self.camera_rotation;
-
-        let (x1, y1, z1) = (
-            x,
-            y * theta_x.cos() - z * theta_x.sin(),
-            y * theta_x.sin() + z * theta_x.cos(),
-        );
-
-        let (x2, y2, z2) = (
-            x1 * theta_y.cos() + z1 * theta_y.sin(),
-            y1,
-            -x1 * theta_y.sin() + z1 * theta_y.cos(),
-        );
-
-        (x2, y2, z2)
-    }
-
-    fn project(&self, point: (f32, f32, f32), width: u32, height: u32) -> (i32, i32) {
-        let (x, y, z) = point;
-
-        let d = 50.0;
-        let x_screen = x / (z + d) * 200.0 * self.zoom + width as f32 / 2.0;
-        let y_screen = y / (z + d) * 200.0 * self.zoom + height as f32 / 2.0;
-
-        (x_screen as i32, y_screen as i32)
-    }
 }
 
 impl Scene for LorenzAttractor {
@@ -108,8 +76,7 @@ impl Scene for LorenzAttractor {
             for i in 1..self.current_index {
                 let point = (solutions[i][0], solutions[i][1], solutions[i][2]);
 
-                let rotated = self.rotate_3d(point);
-                let projected = self.project(rotated, width, height);
+                let projected = self.camera.to_screen(point, width, height);
                 let t = i as f32 / total_points as f32;
                 let r = (255.0 * (1.0 - t)) as u8;
                 let g = (255.0 * t) as u8;
@@ -124,23 +91,10 @@ impl Scene for LorenzAttractor {
     }
 
     fn handle_event(&mut self, ctx: &mut GlobalContext, event: &Event) {
+        if self.camera.handle_event(event) {
+            return;
+        }
         match event {
-            Event::MouseButtonDown { mouse_btn, .. } => {
-                if *mouse_btn == sdl2::mouse::MouseButton::Left {
-                    self.is_mouse_down = true;
-                }
-            }
-            Event::MouseButtonUp { mouse_btn, .. } => {
-                if *mouse_btn == sdl2::mouse::MouseButton::Left {
-                    self.is_mouse_down = false;
-                }
-            }
-            Event::MouseMotion { xrel, yrel, .. } => {
-                if self.is_mouse_down {
-                    self.camera_rotation.0 += *yrel as f32 * 0.01;
-                    self.camera_rotation.1 += *xrel as f32 * 0.01;
-                }
-            }
             Event::KeyDown {
                 keycode: Some(k),
                 ..
@@ -161,13 +115,6 @@ impl Scene for LorenzAttractor {
                     _ => {}
                 }
             }
-            Event::MouseWheel { y, .. } => {
-                if *y > 0 {
-                    self.zoom *= 1.1; 
-                } else if *y < 0 {
-                    self.zoom *= 0.9; 
-                }
-            }
             _ => {}
         }
     }
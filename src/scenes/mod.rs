@@ -1,3 +1,15 @@
 pub mod particle_collisions;
 pub mod lorenz_attractor;
-pub mod pendulum;
\ No newline at end of file
+pub mod pendulum;
+pub mod elastic_pendulum;
+pub mod spherical_pendulum;
+pub mod coupled_oscillators;
+pub mod storage_benchmark;
+pub mod n_body;
+pub mod electrostatics;
+pub mod lorentz_force;
+pub mod sph_fluid;
+pub mod molecular_dynamics;
+pub mod cloth;
+pub mod rigid_bodies;
+pub mod broadphase_benchmark;
@@ -0,0 +1,364 @@
+use crate::engine::{GlobalContext, Scene};
+use crate::models::particle::Particle;
+use crate::models::particle_system::ParticleSystem;
+use crate::utils::molecular_dynamics::{temperature, LennardJones, Thermostat, CUTOFF};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use sdl2::gfx::primitives::DrawRenderer;
+use sdl2::{event::Event, keyboard::Keycode};
+use sdl2::{render::Canvas, video::Window};
+
+/// Velocity-Verlet step in reduced time units.
+const STEP: f32 = 0.004;
+/// Reduced time units per real second at speed 1.
+const TIME_SCALE: f32 = 2.0;
+const MAX_STEPS_PER_FRAME: usize = 25;
+const PARTICLE_COUNTS: [usize; 3] = [400, 900, 1600];
+/// Thermostat time constant in reduced units, the same for both thermostats.
+const THERMOSTAT_TAU: f32 = 0.5;
+const RDF_BINS: usize = 50;
+/// Weight kept by the running g(r) average each frame.
+const RDF_MEMORY: f32 = 0.97;
+const SEED: u64 = 42;
+
+/// Starting density and temperature for each phase, in reduced units.
+#[derive(Clone, Copy, PartialEq)]
+enum Phase {
+    Gas,
+    Liquid,
+    Solid,
+    /// Below the critical point at intermediate density: droplets in their vapour
+    Coexistence,
+}
+
+impl Phase {
+    fn state(self) -> (f32, f32) {
+        match self {
+            Phase::Gas => (0.05, 1.0),
+            Phase::Liquid => (0.7, 0.5),
+            Phase::Solid => (0.85, 0.2),
+            Phase::Coexistence => (0.3, 0.4),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Phase::Gas => "gas",
+            Phase::Liquid => "liquid",
+            Phase::Solid => "solid",
+            Phase::Coexistence => "liquid-vapour coexistence",
+        }
+    }
+}
+
+/// Lennard-Jones particles in a box with a thermostat, showing temperature, virial pressure
+/// and the radial distribution function g(r) as the system moves between phases.
+pub struct MolecularDynamics {
+    particles: ParticleSystem,
+    md: LennardJones,
+    phase: Phase,
+    particle_count: usize,
+    thermostat: Thermostat,
+    target_temperature: f32,
+    /// Running pair counts per g(r) bin and the matching weighted frame count
+    rdf_counts: Vec<f32>,
+    rdf_frames: f32,
+    rdf_norm: f32,
+    /// Pixels per σ
+    scale: f32,
+    pending: f32,
+    screen: (f32, f32),
+    rng: StdRng,
+    done: bool,
+}
+
+impl MolecularDynamics {
+    pub fn new(ctx: &GlobalContext) -> Self {
+        let mut scene = MolecularDynamics {
+            particles: ParticleSystem::new(),
+            md: LennardJones::new(1.0, 1.0),
+            phase: Phase::Liquid,
+            particle_count: PARTICLE_COUNTS[1],
+            thermostat: Thermostat::NoseHoover {
+                tau: THERMOSTAT_TAU,
+            },
+            target_temperature: 0.0,
+            rdf_counts: vec![0.0; RDF_BINS],
+            rdf_frames: 0.0,
+            rdf_norm: 1.0,
+            scale: 1.0,
+            pending: 0.0,
+            screen: (ctx.screen_width as f32, ctx.screen_height as f32),
+            rng: StdRng::seed_from_u64(SEED),
+            done: false,
+        };
+        scene.reset();
+        scene
+    }
+
+    /// Places the particles on a triangular lattice at the phase's density, in a box with the
+    /// screen's aspect ratio, and draws velocities at its temperature.
+    fn reset(&mut self) {
+        let (density, temperature) = self.phase.state();
+        self.target_temperature = temperature;
+        let n = self.particle_count;
+        let aspect = self.screen.0 / self.screen.1;
+        let area = n as f32 / density;
+        let width = (area * aspect).sqrt();
+        let height = area / width;
+        self.md.resize(width, height);
+        self.scale = self.screen.0 / width;
+
+        // Rows and columns of the ideal lattice, stretched slightly so they fill the box
+        let spacing = (2.0 / (3f32.sqrt() * density)).sqrt();
+        let per_row = ((width / spacing).ceil() as usize).max(1);
+        let rows = n.div_ceil(per_row);
+        let (dx, dy) = (width / per_row as f32, height / rows as f32);
+        self.particles.clear();
+        for k in 0..n {
+            let (row, col) = (k / per_row, k % per_row);
+            let offset = if row % 2 == 1 { 0.25 * dx } else { -0.25 * dx };
+            let x = (col as f32 + 0.5) * dx + offset;
+            let y = (row as f32 + 0.5) * dy;
+            self.particles
+                .push(Particle::new(x, y, 0.0, 0.0, 0.5).with_mass(1.0));
+        }
+        self.randomise_velocities(temperature);
+        self.md.prepare(&self.particles);
+        self.rdf_counts.fill(0.0);
+        self.rdf_frames = 0.0;
+        self.pending = 0.0;
+    }
+
+    /// Gaussian velocities with the net momentum removed, rescaled to exactly `t`.
+    fn randomise_velocities(&mut self, t: f32) {
+        let p = &mut self.particles;
+        let n = p.len();
+        if n == 0 {
+            return;
+        }
+        for i in 0..n {
+            // Box-Muller
+            let (u1, u2): (f32, f32) = (self.rng.gen_range(1e-6..1.0), self.rng.gen());
+            let radius = (-2.0 * u1.ln()).sqrt();
+            let angle = std::f32::consts::TAU * u2;
+            p.vx[i] = radius * angle.cos();
+            p.vy[i] = radius * angle.sin();
+        }
+        let mean_vx = p.vx.iter().sum::<f32>() / n as f32;
+        let mean_vy = p.vy.iter().sum::<f32>() / n as f32;
+        for i in 0..n {
+            p.vx[i] -= mean_vx;
+            p.vy[i] -= mean_vy;
+        }
+        let current = temperature(p);
+        if current > 0.0 {
+            let scale = (t / current).sqrt();
+            for i in 0..n {
+                p.vx[i] *= scale;
+                p.vy[i] *= scale;
+            }
+        }
+    }
+
+    fn cycle_thermostat(&mut self) {
+        self.thermostat = match self.thermostat {
+            Thermostat::None => Thermostat::Berendsen {
+                tau: THERMOSTAT_TAU,
+            },
+            Thermostat::Berendsen { .. } => Thermostat::NoseHoover {
+                tau: THERMOSTAT_TAU,
+            },
+            Thermostat::NoseHoover { .. } => Thermostat::None,
+        };
+        self.md.reset_thermostat();
+    }
+
+    fn sample_rdf(&mut self) {
+        for count in self.rdf_counts.iter_mut() {
+            *count *= RDF_MEMORY;
+        }
+        self.rdf_frames = self.rdf_frames * RDF_MEMORY + 1.0;
+        self.rdf_norm = self
+            .md
+            .pair_histogram(&self.particles, CUTOFF, &mut self.rdf_counts);
+    }
+
+    fn rdf(&self) -> impl Iterator<Item = f32> + '_ {
+        let frames = self.rdf_frames.max(1e-6);
+        self.rdf_counts
+            .iter()
+            .enumerate()
+            .map(move |(k, &c)| c / (frames * self.rdf_norm * (k as f32 + 0.5)))
+    }
+
+    fn render_rdf(&self, canvas: &mut Canvas<Window>) {
+        let (w, h) = (380.0, 220.0);
+        let (x0, y0) = (self.screen.0 - w - 20.0, self.screen.1 - h - 20.0);
+        let values: Vec<f32> = self.rdf().collect();
+        let peak = values.iter().copied().fold(0.0, f32::max);
+        let top = peak.max(3.0);
+        let _ = canvas.box_(
+            x0 as i16,
+            y0 as i16,
+            (x0 + w) as i16,
+            (y0 + h) as i16,
+            (0, 0, 0, 200),
+        );
+        let _ = canvas.rectangle(
+            x0 as i16,
+            y0 as i16,
+            (x0 + w) as i16,
+            (y0 + h) as i16,
+            (120, 120, 120, 255),
+        );
+        let to_screen = |r: f32, g: f32| {
+            (
+                (x0 + r / CUTOFF * w) as i16,
+                (y0 + h - g / top * (h - 20.0)) as i16,
+            )
+        };
+        // Ideal gas reference at g = 1
+        let (ax, ay) = to_screen(0.0, 1.0);
+        let (bx, _) = to_screen(CUTOFF, 1.0);
+        let _ = canvas.hline(ax, bx, ay, (90, 90, 90, 255));
+        let bin = CUTOFF / RDF_BINS as f32;
+        let points: Vec<(i16, i16)> = values
+            .iter()
+            .enumerate()
+            .map(|(k, &g)| to_screen((k as f32 + 0.5) * bin, g))
+            .collect();
+        for pair in points.windows(2) {
+            let _ = canvas.line(
+                pair[0].0,
+                pair[0].1,
+                pair[1].0,
+                pair[1].1,
+                (120, 220, 140, 255),
+            );
+        }
+        let label = format!("g(r), r up to {:.1} sigma, peak {:.2}", CUTOFF, peak);
+        let _ = canvas.string(x0 as i16 + 8, y0 as i16 + 6, &label, (255, 255, 255, 255));
+    }
+}
+
+impl Scene for MolecularDynamics {
+    fn update(&mut self, ctx: &mut GlobalContext, dt: f32) {
+        if ctx.paused {
+            return;
+        }
+        self.pending += dt * ctx.simulation_speed.max(0.0) * TIME_SCALE;
+        let mut steps = 0;
+        while self.pending >= STEP && steps < MAX_STEPS_PER_FRAME {
+            self.md.step(
+                &mut self.particles,
+                STEP,
+                self.thermostat,
+                self.target_temperature,
+            );
+            self.pending -= STEP;
+            steps += 1;
+        }
+        self.pending = self.pending.min(STEP);
+        self.sample_rdf();
+    }
+
+    fn render(&mut self, _ctx: &GlobalContext, canvas: &mut Canvas<Window>) {
+        let p = &self.particles;
+        let t = temperature(p).max(1e-6);
+        let radius = ((0.5 * self.scale) as i16).max(1);
+        for i in 0..p.len() {
+            // Speed relative to the thermal speed, blue for slow and red for fast
+            let s = (p.vx[i].hypot(p.vy[i]) / (2.0 * t).sqrt() * 0.5).clamp(0.0, 1.0);
+            let color = (
+                (60.0 + 195.0 * s) as u8,
+                (120.0 + 40.0 * (1.0 - s)) as u8,
+                (255.0 * (1.0 - s)) as u8,
+                255,
+            );
+            let (x, y) = (p.x[i] * self.scale, p.y[i] * self.scale);
+            let _ = canvas.filled_circle(x as i16, y as i16, radius, color);
+        }
+        self.render_rdf(canvas);
+
+        let n = p.len().max(1) as f32;
+        let kinetic = t * n;
+        let white = (255, 255, 255, 255);
+        let (density, _) = self.phase.state();
+        let lines = [
+            format!(
+                "Phase preset: {} (1 gas, 2 liquid, 3 solid, 4 coexistence), {} particles (N), density {:.2}",
+                self.phase.name(),
+                p.len(),
+                density
+            ),
+            format!(
+                "T = {:.3} (target {:.2}, Up/Down), P = {:.3}, E/N = {:.3}",
+                t,
+                self.target_temperature,
+                self.md.pressure(p),
+                (kinetic + self.md.potential_energy) / n
+            ),
+            format!(
+                "Thermostat: {} (H), walls {} (B), R reset, Space pause",
+                self.thermostat.name(),
+                if self.md.is_periodic() {
+                    "periodic"
+                } else {
+                    "reflecting"
+                }
+            ),
+        ];
+        for (k, line) in lines.iter().enumerate() {
+            let _ = canvas.string(10, 10 + 15 * k as i16, line, white);
+        }
+    }
+
+    fn handle_event(&mut self, ctx: &mut GlobalContext, event: &Event) {
+        if let Event::KeyDown {
+            keycode: Some(k), ..
+        } = *event
+        {
+            match k {
+                Keycode::Escape => self.done = true,
+                Keycode::Space => ctx.paused = !ctx.paused,
+                Keycode::Left => ctx.simulation_speed -= 0.1,
+                Keycode::Right => ctx.simulation_speed += 0.1,
+                Keycode::Num1 | Keycode::Num2 | Keycode::Num3 | Keycode::Num4 => {
+                    self.phase = match k {
+                        Keycode::Num1 => Phase::Gas,
+                        Keycode::Num2 => Phase::Liquid,
+                        Keycode::Num3 => Phase::Solid,
+                        _ => Phase::Coexistence,
+                    };
+                    self.reset();
+                }
+                Keycode::R => self.reset(),
+                Keycode::N => {
+                    let next = PARTICLE_COUNTS
+                        .iter()
+                        .position(|&c| c == self.particle_count)
+                        .map_or(0, |k| (k + 1) % PARTICLE_COUNTS.len());
+                    self.particle_count = PARTICLE_COUNTS[next];
+                    self.reset();
+                }
+                Keycode::H => self.cycle_thermostat(),
+                Keycode::B => {
+                    self.md.set_periodic(!self.md.is_periodic());
+                    self.md.prepare(&self.particles);
+                    self.rdf_counts.fill(0.0);
+                    self.rdf_frames = 0.0;
+                }
+                Keycode::Up => self.target_temperature += 0.05,
+                Keycode::Down => {
+                    self.target_temperature = (self.target_temperature - 0.05).max(0.05)
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.done
+    }
+}
@@ -0,0 +1,566 @@
+use crate::engine::{GlobalContext, Scene};
+use crate::models::particle::Particle;
+use crate::models::particle_system::ParticleSystem;
+use crate::utils::barnes_hut::{direct_field, QuadTree};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use sdl2::gfx::primitives::DrawRenderer;
+use sdl2::mouse::MouseButton;
+use sdl2::{event::Event, keyboard::Keycode};
+use sdl2::{render::Canvas, video::Window};
+use std::f32::consts::PI;
+use std::time::Instant;
+
+/// Simulation units: G = 1 and both initial conditions have total mass 1, so one time unit is
+/// roughly a crossing time.
+const G: f32 = 1.0;
+const SOFTENING: f32 = 0.05;
+/// Leapfrog step in simulation time units.
+const STEP: f32 = 0.0025;
+/// Simulation time units per real second at speed 1.
+const TIME_SCALE: f32 = 0.5;
+/// Cap on steps per frame so a slow frame cannot snowball.
+const MAX_STEPS_PER_FRAME: usize = 20;
+/// Below this many bodies the automatic mode sums every pair directly.
+const DIRECT_LIMIT: usize = 256;
+/// Body radius (for merging and drawing) per square root of mass, so area follows mass.
+const RADIUS_PER_SQRT_MASS: f32 = 0.45;
+const BODY_COUNTS: [usize; 4] = [200, 1000, 4000, 10000];
+const SEED: u64 = 42;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Setup {
+    Plummer,
+    GalaxyCollision,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ForceMethod {
+    /// Direct summation up to `DIRECT_LIMIT` bodies, the tree above
+    Auto,
+    Direct,
+    Tree,
+}
+
+impl ForceMethod {
+    fn next(self) -> Self {
+        match self {
+            ForceMethod::Auto => ForceMethod::Direct,
+            ForceMethod::Direct => ForceMethod::Tree,
+            ForceMethod::Tree => ForceMethod::Auto,
+        }
+    }
+}
+
+fn radius_for(mass: f32) -> f32 {
+    RADIUS_PER_SQRT_MASS * mass.sqrt()
+}
+
+/// Self-gravitating bodies integrated with kick-drift-kick leapfrog. Forces come from a
+/// Barnes–Hut quadtree or, for small N, direct summation; total energy and momentum are shown
+/// so the integrator can be checked.
+pub struct NBodyScene {
+    bodies: ParticleSystem,
+    /// Which initial group each body came from, for colouring
+    group: Vec<u8>,
+    /// Acceleration and potential per unit mass from the last force evaluation
+    field: Vec<(f32, f32, f32)>,
+    tree: QuadTree,
+    setup: Setup,
+    body_count: usize,
+    method: ForceMethod,
+    theta: f32,
+    merging: bool,
+    merges: usize,
+    time: f32,
+    /// Simulation time not yet covered by whole steps
+    pending: f32,
+    initial_energy: f32,
+    force_ms: f32,
+    /// World point at the screen centre and pixels per world unit
+    center: (f32, f32),
+    zoom: f32,
+    drag_from: Option<(i32, i32)>,
+    done: bool,
+}
+
+impl NBodyScene {
+    pub fn new() -> Self {
+        let mut scene = NBodyScene {
+            bodies: ParticleSystem::new(),
+            group: Vec::new(),
+            field: Vec::new(),
+            tree: QuadTree::new(),
+            setup: Setup::GalaxyCollision,
+            body_count: BODY_COUNTS[2],
+            method: ForceMethod::Auto,
+            theta: 0.5,
+            merging: false,
+            merges: 0,
+            time: 0.0,
+            pending: 0.0,
+            initial_energy: 0.0,
+            force_ms: 0.0,
+            center: (0.0, 0.0),
+            zoom: 120.0,
+            drag_from: None,
+            done: false,
+        };
+        scene.reset();
+        scene
+    }
+
+    fn reset(&mut self) {
+        self.bodies.clear();
+        self.group.clear();
+        let mut rng = StdRng::seed_from_u64(SEED);
+        match self.setup {
+            Setup::Plummer => self.plummer(&mut rng),
+            Setup::GalaxyCollision => self.galaxy_collision(&mut rng),
+        }
+        self.merges = 0;
+        self.time = 0.0;
+        self.pending = 0.0;
+        self.center = (0.0, 0.0);
+        self.compute_forces();
+        self.initial_energy = self.energy();
+    }
+
+    fn add_body(&mut self, (x, y): (f32, f32), (vx, vy): (f32, f32), mass: f32, group: u8) {
+        self.bodies
+            .push(Particle::new(x, y, vx, vy, radius_for(mass)).with_mass(mass));
+        self.group.push(group);
+    }
+
+    /// Plummer sphere with unit mass and scale radius (Aarseth, Hénon & Wielen sampling),
+    /// projected onto the plane. Projection upsets equilibrium, so velocities are rescaled to
+    /// satisfy the virial theorem under the planar dynamics.
+    fn plummer(&mut self, rng: &mut StdRng) {
+        let n = self.body_count;
+        let mass = 1.0 / n as f32;
+        for _ in 0..n {
+            let r = loop {
+                let x: f32 = rng.gen_range(1e-6..1.0);
+                let r = 1.0 / (x.powf(-2.0 / 3.0) - 1.0).sqrt();
+                // Drop the handful of bodies far out in the tail
+                if r < 10.0 {
+                    break r;
+                }
+            };
+            let (x, y, _) = random_direction(rng, r);
+
+            // Speed as a fraction of escape speed, from g(q) = q²(1 - q²)^3.5
+            let q = loop {
+                let q: f32 = rng.gen_range(0.0..1.0);
+                if rng.gen_range(0.0..0.1) < q * q * (1.0 - q * q).powf(3.5) {
+                    break q;
+                }
+            };
+            let speed = q * 2f32.sqrt() * (1.0 + r * r).powf(-0.25);
+            let (vx, vy, _) = random_direction(rng, speed);
+            self.add_body((x, y), (vx, vy), mass, 0);
+        }
+        self.remove_bulk_motion();
+
+        self.compute_forces();
+        let kinetic = self.kinetic_energy();
+        let potential = self.potential_energy();
+        if kinetic > 0.0 {
+            let scale = (-0.5 * potential / kinetic).sqrt();
+            for v in self.bodies.vx.iter_mut().chain(self.bodies.vy.iter_mut()) {
+                *v *= scale;
+            }
+        }
+    }
+
+    /// Two rotating discs with heavy cores on a parabolic, off-centre approach. The second
+    /// disc turns the other way.
+    fn galaxy_collision(&mut self, rng: &mut StdRng) {
+        let first = self.body_count / 2;
+        let separation: (f32, f32) = (6.0, 1.5);
+        let distance = separation.0.hypot(separation.1);
+        // Parabolic relative speed for the total mass of 1
+        let approach = (2.0 * G / distance).sqrt();
+
+        for (k, sign, bodies) in [
+            (0u8, -1.0f32, first),
+            (1u8, 1.0f32, self.body_count - first),
+        ] {
+            let center = (sign * separation.0 * 0.5, sign * separation.1 * 0.5);
+            let velocity = (-sign * approach * 0.5, 0.0);
+            self.disc(rng, center, velocity, bodies, k, sign);
+        }
+        self.remove_bulk_motion();
+    }
+
+    /// A galaxy of mass 0.5 made of `bodies` bodies: half the mass in a core, half in stars on
+    /// circular orbits.
+    fn disc(
+        &mut self,
+        rng: &mut StdRng,
+        center: (f32, f32),
+        velocity: (f32, f32),
+        bodies: usize,
+        group: u8,
+        spin: f32,
+    ) {
+        if bodies == 0 {
+            return;
+        }
+        let (core_mass, disc_mass) = (0.25, 0.25);
+        self.add_body(center, velocity, core_mass, group);
+        let stars = bodies - 1;
+        if stars == 0 {
+            return;
+        }
+        let star_mass = disc_mass / stars as f32;
+
+        // Uniform surface density between the two radii, sorted to get the enclosed mass
+        let (inner, outer) = (0.2f32, 1.5f32);
+        let mut radii: Vec<f32> = (0..stars)
+            .map(|_| rng.gen_range(inner * inner..outer * outer).sqrt())
+            .collect();
+        radii.sort_by(f32::total_cmp);
+        for (k, r) in radii.into_iter().enumerate() {
+            let enclosed = core_mass + star_mass * k as f32;
+            // Circular speed under the softened force
+            let speed = (G * enclosed * r * r / (r * r + SOFTENING * SOFTENING).powf(1.5)).sqrt();
+            let angle = rng.gen_range(0.0..2.0 * PI);
+            let (c, s) = (angle.cos(), angle.sin());
+            self.add_body(
+                (center.0 + r * c, center.1 + r * s),
+                (velocity.0 - spin * speed * s, velocity.1 + spin * speed * c),
+                star_mass,
+                group,
+            );
+        }
+    }
+
+    /// Moves to the centre-of-mass frame.
+    fn remove_bulk_motion(&mut self) {
+        let b = &mut self.bodies;
+        let total: f32 = b.mass.iter().sum();
+        let weighted =
+            |values: &[f32]| values.iter().zip(&b.mass).map(|(v, m)| v * m).sum::<f32>() / total;
+        let (x, y, vx, vy) = (
+            weighted(&b.x),
+            weighted(&b.y),
+            weighted(&b.vx),
+            weighted(&b.vy),
+        );
+        for (values, mean) in [
+            (&mut b.x, x),
+            (&mut b.y, y),
+            (&mut b.vx, vx),
+            (&mut b.vy, vy),
+        ] {
+            for value in values.iter_mut() {
+                *value -= mean;
+            }
+        }
+    }
+
+    fn uses_tree(&self) -> bool {
+        match self.method {
+            ForceMethod::Auto => self.bodies.len() > DIRECT_LIMIT,
+            ForceMethod::Direct => false,
+            ForceMethod::Tree => true,
+        }
+    }
+
+    fn compute_forces(&mut self) {
+        let start = Instant::now();
+        let b = &self.bodies;
+        if self.uses_tree() {
+            self.tree.build(&b.x, &b.y, &b.mass);
+            self.tree.fields(self.theta, G, SOFTENING, &mut self.field);
+        } else {
+            (0..b.len())
+                .into_par_iter()
+                .map(|i| direct_field(i, &b.x, &b.y, &b.mass, G, SOFTENING))
+                .collect_into_vec(&mut self.field);
+        }
+        self.force_ms = start.elapsed().as_secs_f32() * 1000.0;
+    }
+
+    fn kick(&mut self, h: f32) {
+        let b = &mut self.bodies;
+        for (i, &(ax, ay, _)) in self.field.iter().enumerate() {
+            b.vx[i] += ax * h;
+            b.vy[i] += ay * h;
+        }
+    }
+
+    fn step(&mut self) {
+        self.kick(0.5 * STEP);
+        let b = &mut self.bodies;
+        for (x, vx) in b.x.iter_mut().zip(&b.vx) {
+            *x += vx * STEP;
+        }
+        for (y, vy) in b.y.iter_mut().zip(&b.vy) {
+            *y += vy * STEP;
+        }
+        if self.merging {
+            self.merge_overlapping();
+        }
+        self.compute_forces();
+        self.kick(0.5 * STEP);
+        self.time += STEP;
+    }
+
+    /// Merges touching bodies, conserving mass and momentum. The merged body keeps the
+    /// combined area, so it may reach further bodies; passes repeat until nothing touches.
+    fn merge_overlapping(&mut self) {
+        while self.merge_pass() {}
+    }
+
+    /// Merges each body with everything touching it, against a tree of the current positions.
+    /// A body that has merged is left alone for the rest of the pass, since it no longer is
+    /// where the tree has it. Returns whether anything merged.
+    fn merge_pass(&mut self) -> bool {
+        self.tree
+            .build(&self.bodies.x, &self.bodies.y, &self.bodies.mass);
+        let max_radius = self.bodies.max_radius();
+        let mut alive = vec![true; self.bodies.len()];
+        let mut merged = vec![false; self.bodies.len()];
+        let mut touching = Vec::new();
+        for i in 0..self.bodies.len() {
+            if !alive[i] || merged[i] {
+                continue;
+            }
+            let b = &self.bodies;
+            touching.clear();
+            self.tree
+                .for_each_near(b.x[i], b.y[i], b.radius[i] + max_radius, |j| {
+                    let (dx, dy) = (b.x[j] - b.x[i], b.y[j] - b.y[i]);
+                    let reach = b.radius[i] + b.radius[j];
+                    if j != i && alive[j] && !merged[j] && dx * dx + dy * dy < reach * reach {
+                        touching.push(j);
+                    }
+                });
+            for &j in &touching {
+                let b = &mut self.bodies;
+                let (mi, mj) = (b.mass[i], b.mass[j]);
+                let m = mi + mj;
+                b.x[i] = (mi * b.x[i] + mj * b.x[j]) / m;
+                b.y[i] = (mi * b.y[i] + mj * b.y[j]) / m;
+                b.vx[i] = (mi * b.vx[i] + mj * b.vx[j]) / m;
+                b.vy[i] = (mi * b.vy[i] + mj * b.vy[j]) / m;
+                b.radius[i] = (b.radius[i].powi(2) + b.radius[j].powi(2)).sqrt();
+                b.mass[i] = m;
+                if mj > mi {
+                    self.group[i] = self.group[j];
+                }
+                alive[j] = false;
+                merged[i] = true;
+                self.merges += 1;
+            }
+        }
+        if alive.iter().all(|&a| a) {
+            return false;
+        }
+        self.bodies.retain(|i| alive[i]);
+        let mut k = 0;
+        self.group.retain(|_| {
+            k += 1;
+            alive[k - 1]
+        });
+        true
+    }
+
+    fn kinetic_energy(&self) -> f32 {
+        let b = &self.bodies;
+        (0..b.len())
+            .map(|i| 0.5 * b.mass[i] * (b.vx[i] * b.vx[i] + b.vy[i] * b.vy[i]))
+            .sum()
+    }
+
+    /// Pair potential energy from the last force evaluation; each pair is counted from both
+    /// ends, hence the half.
+    fn potential_energy(&self) -> f32 {
+        0.5 * self
+            .field
+            .iter()
+            .zip(&self.bodies.mass)
+            .map(|(&(_, _, phi), m)| m * phi)
+            .sum::<f32>()
+    }
+
+    fn energy(&self) -> f32 {
+        self.kinetic_energy() + self.potential_energy()
+    }
+
+    /// Linear and angular momentum about the origin.
+    fn momentum(&self) -> ((f32, f32), f32) {
+        let b = &self.bodies;
+        (0..b.len()).fold(((0.0, 0.0), 0.0), |((px, py), l), i| {
+            let m = b.mass[i];
+            (
+                (px + m * b.vx[i], py + m * b.vy[i]),
+                l + m * (b.x[i] * b.vy[i] - b.y[i] * b.vx[i]),
+            )
+        })
+    }
+
+    fn to_screen(&self, ctx: &GlobalContext, x: f32, y: f32) -> (i16, i16) {
+        (
+            (ctx.screen_width as f32 * 0.5 + (x - self.center.0) * self.zoom) as i16,
+            (ctx.screen_height as f32 * 0.5 + (y - self.center.1) * self.zoom) as i16,
+        )
+    }
+}
+
+/// Uniformly random 3D vector of the given length.
+fn random_direction(rng: &mut StdRng, length: f32) -> (f32, f32, f32) {
+    let z: f32 = rng.gen_range(-1.0..1.0);
+    let phi = rng.gen_range(0.0..2.0 * PI);
+    let s = (1.0 - z * z).sqrt();
+    (length * s * phi.cos(), length * s * phi.sin(), length * z)
+}
+
+impl Scene for NBodyScene {
+    fn update(&mut self, ctx: &mut GlobalContext, dt: f32) {
+        if ctx.paused {
+            return;
+        }
+        // Leapfrog is time-reversible, but only forward time is offered here
+        self.pending += dt * ctx.simulation_speed.max(0.0) * TIME_SCALE;
+        let mut steps = 0;
+        while self.pending >= STEP && steps < MAX_STEPS_PER_FRAME {
+            self.step();
+            self.pending -= STEP;
+            steps += 1;
+        }
+        self.pending = self.pending.min(STEP);
+    }
+
+    fn render(&mut self, ctx: &GlobalContext, canvas: &mut Canvas<Window>) {
+        let b = &self.bodies;
+        for i in 0..b.len() {
+            let (sx, sy) = self.to_screen(ctx, b.x[i], b.y[i]);
+            let color = match self.group[i] {
+                0 => (170, 200, 255, 255),
+                _ => (255, 200, 140, 255),
+            };
+            let r = (b.radius[i] * self.zoom) as i16;
+            if r <= 1 {
+                let _ = canvas.pixel(sx, sy, color);
+            } else {
+                let _ = canvas.filled_circle(sx, sy, r, color);
+            }
+        }
+
+        let white = (255, 255, 255, 255);
+        let energy = self.energy();
+        let drift = (energy - self.initial_energy) / self.initial_energy.abs().max(1e-12);
+        let ((px, py), angular) = self.momentum();
+        let method = if self.uses_tree() {
+            format!("Barnes-Hut, opening angle {:.1} ([ / ])", self.theta)
+        } else {
+            "direct summation".to_string()
+        };
+        let mode = match self.method {
+            ForceMethod::Auto => "auto",
+            ForceMethod::Direct => "direct",
+            ForceMethod::Tree => "tree",
+        };
+        let lines = [
+            format!(
+                "{} bodies (N), t = {:.2}, 1 Plummer / 2 galaxy collision, R restart",
+                b.len(),
+                self.time
+            ),
+            format!(
+                "Forces: {} ({} mode, B), {:.2} ms per evaluation",
+                method, mode, self.force_ms
+            ),
+            format!(
+                "Energy {:.5} (drift {:+.2e}), momentum ({:+.1e}, {:+.1e}), angular momentum {:.5}",
+                energy, drift, px, py, angular
+            ),
+            format!(
+                "Merging {} (M), {} merges so far{}",
+                if self.merging { "on" } else { "off" },
+                self.merges,
+                if self.merging {
+                    ", merging is inelastic"
+                } else {
+                    ""
+                }
+            ),
+            "Wheel zoom, drag to pan, C recentre".to_string(),
+        ];
+        for (k, line) in lines.iter().enumerate() {
+            let _ = canvas.string(10, 10 + 15 * k as i16, line, white);
+        }
+    }
+
+    fn handle_event(&mut self, ctx: &mut GlobalContext, event: &Event) {
+        match *event {
+            Event::KeyDown {
+                keycode: Some(k), ..
+            } => match k {
+                Keycode::Escape => self.done = true,
+                Keycode::Space => ctx.paused = !ctx.paused,
+                Keycode::Left => ctx.simulation_speed -= 0.1,
+                Keycode::Right => ctx.simulation_speed += 0.1,
+                Keycode::Num1 => {
+                    self.setup = Setup::Plummer;
+                    self.reset();
+                }
+                Keycode::Num2 => {
+                    self.setup = Setup::GalaxyCollision;
+                    self.reset();
+                }
+                Keycode::R => self.reset(),
+                Keycode::N => {
+                    let next = BODY_COUNTS
+                        .iter()
+                        .position(|&n| n == self.body_count)
+                        .map_or(0, |k| (k + 1) % BODY_COUNTS.len());
+                    self.body_count = BODY_COUNTS[next];
+                    self.reset();
+                }
+                Keycode::LeftBracket => self.theta = (self.theta - 0.1).max(0.0),
+                Keycode::RightBracket => self.theta = (self.theta + 0.1).min(1.5),
+                Keycode::B => self.method = self.method.next(),
+                Keycode::M => self.merging = !self.merging,
+                Keycode::C => {
+                    let b = &self.bodies;
+                    let total: f32 = b.mass.iter().sum();
+                    let weighted = |values: &[f32]| {
+                        values.iter().zip(&b.mass).map(|(v, m)| v * m).sum::<f32>() / total
+                    };
+                    self.center = (weighted(&b.x), weighted(&b.y));
+                }
+                _ => {}
+            },
+            Event::MouseWheel { y, .. } => {
+                self.zoom = (self.zoom * 1.2f32.powi(y)).clamp(5.0, 5000.0);
+            }
+            Event::MouseButtonDown {
+                mouse_btn: MouseButton::Left,
+                x,
+                y,
+                ..
+            } => self.drag_from = Some((x, y)),
+            Event::MouseButtonUp {
+                mouse_btn: MouseButton::Left,
+                ..
+            } => self.drag_from = None,
+            Event::MouseMotion { x, y, .. } => {
+                if let Some((fx, fy)) = self.drag_from {
+                    self.center.0 -= (x - fx) as f32 / self.zoom;
+                    self.center.1 -= (y - fy) as f32 / self.zoom;
+                    self.drag_from = Some((x, y));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.done
+    }
+}
@@ -0,0 +1,87 @@
+use crate::models::particle::Particle;
+use crate::models::particle_system::ParticleSystem;
+use crate::models::soft_body::SoftBody;
+use sdl2::keyboard::Keycode;
+use sdl2::{render::Canvas, video::Window};
+
+/// Radius of the pressure blobs placed with A, and the pressures cycled with I.
+const BLOB_RADIUS: f32 = 70.0;
+const BLOB_PRESSURES: [f32; 3] = [1.0, 1.4, 0.7];
+
+/// Pressure blobs, balloons of ordinary particles placed at the cursor with A.
+pub struct BlobControls {
+    blobs: Vec<SoftBody>,
+    /// Index into `BLOB_PRESSURES` of the pressure given to the blobs
+    pressure: usize,
+}
+
+impl BlobControls {
+    pub fn new() -> Self {
+        BlobControls {
+            blobs: Vec::new(),
+            pressure: 0,
+        }
+    }
+
+    /// A places a blob at `cursor` whose skin particles are copies of `skin`, I cycles the
+    /// pressure of all blobs.
+    pub fn handle_key(
+        &mut self,
+        key: Keycode,
+        particles: &mut ParticleSystem,
+        cursor: (f32, f32),
+        skin: &Particle,
+    ) {
+        match key {
+            Keycode::A => {
+                let blob = SoftBody::spawn(
+                    particles,
+                    cursor,
+                    BLOB_RADIUS,
+                    skin.radius,
+                    BLOB_PRESSURES[self.pressure],
+                    |x, y| {
+                        Particle {
+                            x,
+                            y,
+                            ..skin.clone()
+                        }
+                        .with_color((140, 240, 180))
+                    },
+                );
+                self.blobs.push(blob);
+            }
+            Keycode::I => {
+                self.pressure = (self.pressure + 1) % BLOB_PRESSURES.len();
+                for blob in &mut self.blobs {
+                    blob.pressure = BLOB_PRESSURES[self.pressure];
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Moves the blobs back onto their constraints after a step of `dt` and drops the burst
+    /// ones.
+    pub fn project(&mut self, particles: &mut ParticleSystem, dt: f32) {
+        self.blobs.retain_mut(|blob| blob.project(particles, dt));
+    }
+
+    pub fn clear(&mut self) {
+        self.blobs.clear();
+    }
+
+    pub fn status(&self) -> String {
+        format!(
+            "Blobs: {} (A at cursor), pressure {:.1} (I)",
+            self.blobs.len(),
+            BLOB_PRESSURES[self.pressure]
+        )
+    }
+
+    pub fn render(&self, canvas: &mut Canvas<Window>, particles: &ParticleSystem) {
+        for blob in &self.blobs {
+            blob.render(canvas, particles);
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use crate::models::obstacle::Obstacle;
+use crate::models::particle_system::{ParticleSystem, WallImpulses};
+use crate::utils::ccd::Ccd;
+use sdl2::keyboard::Keycode;
+
+/// Continuous collision detection for fast particles, toggled with C, with its speed
+/// threshold set with , and .
+pub struct CcdControls {
+    ccd: Ccd,
+}
+
+impl CcdControls {
+    pub fn new() -> Self {
+        CcdControls { ccd: Ccd::new() }
+    }
+
+    pub fn handle_key(&mut self, key: Keycode) {
+        match key {
+            Keycode::C => self.ccd.enabled = !self.ccd.enabled,
+            Keycode::Comma => {
+                self.ccd.speed_threshold = (self.ccd.speed_threshold - 50.0).max(0.0);
+            }
+            Keycode::Period => self.ccd.speed_threshold += 50.0,
+            _ => {}
+        }
+    }
+
+    /// Moves the particles over `dt` with `integrate`, in substeps that stop at the impacts of
+    /// fast particles when CCD is on. Returns the momentum handed to the screen edges.
+    pub fn integrate<F>(
+        &mut self,
+        particles: &mut ParticleSystem,
+        obstacles: &[Obstacle],
+        dt: f32,
+        screen: (f32, f32),
+        mut integrate: F,
+    ) -> WallImpulses
+    where
+        F: FnMut(&mut ParticleSystem, f32) -> WallImpulses,
+    {
+        if !self.ccd.enabled {
+            return integrate(particles, dt);
+        }
+        let mut walls = WallImpulses::default();
+        self.ccd
+            .step(particles, obstacles, dt, screen, |system, t| {
+                walls += integrate(system, t)
+            });
+        walls + self.ccd.last_wall_impulses
+    }
+
+    /// Pairs of particles that hit each other in the last `integrate`.
+    pub fn impact_pairs(&self) -> &[(usize, usize)] {
+        if self.ccd.enabled {
+            &self.ccd.last_impact_pairs
+        } else {
+            &[]
+        }
+    }
+
+    /// Collisions between particles resolved by the sweep in the last `integrate`, which the
+    /// contact solver does not count.
+    pub fn swept_collisions(&self) -> usize {
+        if self.ccd.enabled {
+            self.ccd.last_pair_impacts
+        } else {
+            0
+        }
+    }
+
+    pub fn status(&self) -> String {
+        if self.ccd.enabled {
+            format!(
+                "CCD on (C): sweeping particles above {:.0} px/s (, / .), {} substeps",
+                self.ccd.speed_threshold, self.ccd.last_substeps
+            )
+        } else {
+            "CCD off (C)".to_string()
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use super::{FLING_SCALE, MIN_DRAG};
+use crate::models::emitter::{Distribution, Emitter, Region, Sink};
+use crate::models::particle_system::ParticleSystem;
+use rand::rngs::StdRng;
+use sdl2::keyboard::Keycode;
+use sdl2::{render::Canvas, video::Window};
+
+/// Kind of emitter placed with the emitter tool, cycled with U. The drag sets the direction
+/// and the typical speed.
+#[derive(Clone, Copy, PartialEq)]
+enum EmitterPreset {
+    /// Narrow stream of identical particles
+    Jet,
+    /// Wider cone with Gaussian speeds and sizes in rainbow colours
+    Spray,
+    /// Source in every direction with Maxwellian speeds and a spread of masses
+    Thermal,
+}
+
+impl EmitterPreset {
+    fn next(self) -> Self {
+        match self {
+            EmitterPreset::Jet => EmitterPreset::Spray,
+            EmitterPreset::Spray => EmitterPreset::Thermal,
+            EmitterPreset::Thermal => EmitterPreset::Jet,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            EmitterPreset::Jet => "jet",
+            EmitterPreset::Spray => "spray",
+            EmitterPreset::Thermal => "thermal",
+        }
+    }
+
+    fn create(self, (x, y): (f32, f32), direction: f32, speed: f32, radius: f32) -> Emitter {
+        let mut emitter = Emitter::new(x, y, direction);
+        match self {
+            EmitterPreset::Jet => {
+                emitter.spread = 5.0;
+                emitter.speed = Distribution::Constant(speed);
+                emitter.radius = Distribution::Constant(radius);
+            }
+            EmitterPreset::Spray => {
+                emitter.spread = 60.0;
+                emitter.speed = Distribution::Gaussian {
+                    mean: speed,
+                    std_dev: 0.2 * speed,
+                };
+                emitter.radius = Distribution::Gaussian {
+                    mean: radius,
+                    std_dev: 0.25 * radius,
+                };
+                emitter.hue = Some(Distribution::Uniform {
+                    min: 0.0,
+                    max: 360.0,
+                });
+            }
+            EmitterPreset::Thermal => {
+                emitter.region = Region::Disc {
+                    radius: 2.0 * radius,
+                };
+                emitter.spread = 360.0;
+                // The mean of the Maxwellian is scale √(π/2)
+                emitter.speed = Distribution::Maxwellian {
+                    scale: speed / (0.5 * std::f32::consts::PI).sqrt(),
+                };
+                emitter.radius = Distribution::Uniform {
+                    min: 0.6 * radius,
+                    max: 1.4 * radius,
+                };
+                emitter.mass = Some(Distribution::Uniform { min: 0.5, max: 5.0 });
+                emitter.hue = Some(Distribution::Gaussian {
+                    mean: 200.0,
+                    std_dev: 30.0,
+                });
+            }
+        }
+        emitter
+    }
+}
+
+/// Emitters and sinks placed with tools 7 and 8, and the clock that drives the emitter
+/// scripts.
+pub struct EmitterControls {
+    pub placed: Vec<Emitter>,
+    pub sinks: Vec<Sink>,
+    preset: EmitterPreset,
+    /// Simulated time since the last reset, which drives the emitter scripts
+    time: f32,
+}
+
+impl EmitterControls {
+    pub fn new() -> Self {
+        EmitterControls {
+            placed: Vec::new(),
+            sinks: Vec::new(),
+            preset: EmitterPreset::Jet,
+            time: 0.0,
+        }
+    }
+
+    pub fn handle_key(&mut self, key: Keycode) {
+        if key == Keycode::U {
+            self.preset = self.preset.next();
+        }
+    }
+
+    /// Places an emitter of the current preset at `start`, aimed along the drag to `end`.
+    pub fn place_emitter(&mut self, start: (f32, f32), end: (f32, f32), radius: f32) {
+        let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+        let direction = dy.atan2(dx).to_degrees();
+        let speed = (dx.hypot(dy) * FLING_SCALE).max(50.0);
+        self.placed
+            .push(self.preset.create(start, direction, speed, radius));
+    }
+
+    /// Places a sink: a click makes a round one, a drag a box.
+    pub fn place_sink(&mut self, start: (f32, f32), end: (f32, f32)) {
+        let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+        let sink = if dx.hypot(dy) < MIN_DRAG {
+            Sink::new(start.0, start.1, Region::Disc { radius: 30.0 })
+        } else {
+            let region = Region::Box {
+                width: dx.abs(),
+                height: dy.abs(),
+            };
+            Sink::new(0.5 * (start.0 + end.0), 0.5 * (start.1 + end.1), region)
+        };
+        self.sinks.push(sink);
+    }
+
+    /// Advances the clock by `dt` and runs the emitter scripts and sinks over that step.
+    /// Returns whether any particle was absorbed, which shifts the indices.
+    pub fn step(&mut self, particles: &mut ParticleSystem, dt: f32, rng: &mut StdRng) -> bool {
+        self.time += dt;
+        for emitter in &mut self.placed {
+            emitter.emit(particles, self.time, dt, rng);
+        }
+        let mut absorbed = 0;
+        for sink in &mut self.sinks {
+            absorbed += sink.absorb(particles);
+        }
+        absorbed > 0
+    }
+
+    /// Restarts the emitter scripts.
+    pub fn restart(&mut self) {
+        self.time = 0.0;
+    }
+
+    /// Restarts the scripts and the sink counters.
+    pub fn reset(&mut self) {
+        self.restart();
+        for sink in &mut self.sinks {
+            sink.absorbed = 0;
+            sink.absorbed_mass = 0.0;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.placed.clear();
+        self.sinks.clear();
+    }
+
+    /// Particles taken by all the sinks since the last reset.
+    pub fn absorbed(&self) -> usize {
+        self.sinks.iter().map(|sink| sink.absorbed).sum()
+    }
+
+    pub fn tool_label(&self) -> String {
+        format!(
+            "place {} emitter (U), drag sets direction and speed",
+            self.preset.label()
+        )
+    }
+
+    pub fn render(&self, canvas: &mut Canvas<Window>) {
+        for sink in &self.sinks {
+            sink.render(canvas);
+        }
+        for emitter in &self.placed {
+            emitter.render(canvas, self.time);
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use crate::models::force_field::{
+    ForceField, ForceFields, LinearDrag, PointAttractor, QuadraticDrag, TurbulentWind,
+    UniformGravity, Vortex,
+};
+use sdl2::keyboard::Keycode;
+use sdl2::{render::Canvas, video::Window};
+
+/// How close a click must be to a placed force field to pick it up.
+const FIELD_PICK_DISTANCE: f32 = 20.0;
+
+/// Force field placed with the field tool.
+#[derive(Clone, Copy, PartialEq)]
+enum FieldKind {
+    Attractor,
+    Repulsor,
+    Vortex,
+}
+
+impl FieldKind {
+    fn next(self) -> Self {
+        match self {
+            FieldKind::Attractor => FieldKind::Repulsor,
+            FieldKind::Repulsor => FieldKind::Vortex,
+            FieldKind::Vortex => FieldKind::Attractor,
+        }
+    }
+
+    fn create(self, x: f32, y: f32) -> Box<dyn ForceField> {
+        match self {
+            FieldKind::Attractor => Box::new(PointAttractor::attractor(x, y)),
+            FieldKind::Repulsor => Box::new(PointAttractor::repulsor(x, y)),
+            FieldKind::Vortex => Box::new(Vortex::new(x, y)),
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            FieldKind::Attractor => "attractor",
+            FieldKind::Repulsor => "repulsor",
+            FieldKind::Vortex => "vortex",
+        }
+    }
+}
+
+/// Air drag applied to the whole scene, cycled with D.
+#[derive(Clone, Copy, PartialEq)]
+enum DragMode {
+    None,
+    Linear,
+    Quadratic,
+}
+
+/// Scene-wide fields switched with G, W and D, and the fields placed with the field tool.
+pub struct FieldControls {
+    pub forces: ForceFields,
+    kind: FieldKind,
+    drag_mode: DragMode,
+    /// Placed field being moved with the field tool
+    held: Option<usize>,
+}
+
+impl FieldControls {
+    pub fn new() -> Self {
+        FieldControls {
+            forces: ForceFields::default(),
+            kind: FieldKind::Attractor,
+            drag_mode: DragMode::None,
+            held: None,
+        }
+    }
+
+    /// Returns whether the fields changed.
+    pub fn handle_key(&mut self, key: Keycode, cursor: (f32, f32)) -> bool {
+        match key {
+            Keycode::G => self.toggle("gravity", || Box::new(UniformGravity::default())),
+            Keycode::W => self.toggle("wind", || Box::new(TurbulentWind::default())),
+            Keycode::D => self.cycle_drag(),
+            Keycode::K => {
+                self.kind = self.kind.next();
+                return false;
+            }
+            Keycode::X => {
+                let Some(i) = self.forces.placed_near(cursor.0, cursor.1, f32::MAX) else {
+                    return false;
+                };
+                self.held = None;
+                self.forces.remove(i);
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Picks up the placed field under `point`, or places a new one there. Returns whether a
+    /// field was placed.
+    pub fn press(&mut self, point: (f32, f32)) -> bool {
+        self.held = self
+            .forces
+            .placed_near(point.0, point.1, FIELD_PICK_DISTANCE);
+        if self.held.is_some() {
+            return false;
+        }
+        self.forces.push(self.kind.create(point.0, point.1));
+        true
+    }
+
+    /// Moves the held field onto the cursor. Returns whether one moved.
+    pub fn drag_to(&mut self, (x, y): (f32, f32)) -> bool {
+        let Some(i) = self.held else {
+            return false;
+        };
+        self.forces.modify(i, |f| f.set_position(x, y));
+        true
+    }
+
+    pub fn release(&mut self) {
+        self.held = None;
+    }
+
+    /// Scales the placed field nearest to the cursor by one wheel notch per `steps`. Returns
+    /// whether there was one.
+    pub fn scale_nearest(&mut self, cursor: (f32, f32), steps: i32) -> bool {
+        let Some(i) = self.forces.placed_near(cursor.0, cursor.1, f32::MAX) else {
+            return false;
+        };
+        let factor = 1.25f32.powi(steps);
+        self.forces
+            .modify(i, |f| f.set_strength(f.strength() * factor));
+        true
+    }
+
+    /// Turns a scene-wide field on or off.
+    fn toggle(&mut self, name: &str, make: impl FnOnce() -> Box<dyn ForceField>) {
+        self.held = None;
+        if !self.forces.remove_named(name) {
+            self.forces.push(make());
+        }
+    }
+
+    fn cycle_drag(&mut self) {
+        self.held = None;
+        self.forces.remove_named("linear drag");
+        self.forces.remove_named("quadratic drag");
+        self.drag_mode = match self.drag_mode {
+            DragMode::None => {
+                self.forces.push(Box::new(LinearDrag { coefficient: 0.05 }));
+                DragMode::Linear
+            }
+            DragMode::Linear => {
+                self.forces.push(Box::new(QuadraticDrag {
+                    coefficient: 0.0005,
+                }));
+                DragMode::Quadratic
+            }
+            DragMode::Quadratic => DragMode::None,
+        };
+    }
+
+    pub fn tool_label(&self) -> String {
+        format!(
+            "place {} (K), drag to move, wheel scales nearest, X removes",
+            self.kind.label()
+        )
+    }
+
+    pub fn status(&self) -> String {
+        let on_off = |name| {
+            if self.forces.contains(name) {
+                "on"
+            } else {
+                "off"
+            }
+        };
+        let drag = match self.drag_mode {
+            DragMode::None => "off",
+            DragMode::Linear => "linear",
+            DragMode::Quadratic => "quadratic",
+        };
+        let placed = self
+            .forces
+            .iter()
+            .filter(|f| f.position().is_some())
+            .count();
+        format!(
+            "Fields: gravity {} (G), wind {} (W), drag {} (D), {} placed (tool 6)",
+            on_off("gravity"),
+            on_off("wind"),
+            drag,
+            placed
+        )
+    }
+
+    pub fn render(&self, canvas: &mut Canvas<Window>) {
+        self.forces.render(canvas);
+    }
+}
@@ -0,0 +1,1052 @@
+mod blobs;
+mod ccd;
+mod emitters;
+mod fields;
+mod piston;
+mod sleep;
+
+use crate::engine::{GlobalContext, Scene};
+use crate::models::boundary::{BoundaryMode, Inflow};
+use crate::models::constraint::{Constraints, LinkKind};
+use crate::models::layout::{load_layout, save_layout, Layout};
+use crate::models::obstacle::Obstacle;
+use crate::models::particle::{Particle, DEFAULT_DENSITY};
+use crate::models::particle_system::ParticleSystem;
+use crate::utils::broadphase::{Broadphase, BroadphaseKind, ParticlePairs};
+use crate::utils::collision::CollisionSolver;
+use crate::utils::sleep::Sleep;
+use crate::utils::spatial_hash::{cell_size_for_radius, SpatialHash, MIN_CELL_SIZE};
+use crate::utils::statistics::{GasStatistics, SPEED_BINS};
+use sdl2::gfx::primitives::DrawRenderer;
+use sdl2::keyboard::Mod;
+use sdl2::mouse::MouseButton;
+use sdl2::{event::Event, keyboard::Keycode, render::Canvas, video::Window};
+use std::path::Path;
+use std::time::Instant;
+
+use blobs::BlobControls;
+use ccd::CcdControls;
+use emitters::EmitterControls;
+use fields::FieldControls;
+use piston::PistonControls;
+use sleep::SleepControls;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Spawning is seeded so that a run can be replayed exactly (R resets to this seed).
+const DEFAULT_SEED: u64 = 42;
+/// Obstacles, emitters and sinks written by Ctrl+S and read by Ctrl+L, relative to the
+/// working directory.
+const LAYOUT_FILE: &str = "layout.txt";
+/// Drags shorter than this are treated as clicks and draw nothing.
+const MIN_DRAG: f32 = 3.0;
+/// Launch velocity per pixel of drag with the fling tool.
+const FLING_SCALE: f32 = 4.0;
+/// Reach and strength of the right-click repulsion.
+const REPEL_RADIUS: f32 = 120.0;
+const REPEL_ACCEL: f32 = 4000.0;
+/// Stream fed in through inflow edges: speed, number density per square pixel and radius.
+const INFLOW_SPEED: f32 = 150.0;
+const INFLOW_DENSITY: f32 = 1.0 / 2500.0;
+const INFLOW_RADIUS: f32 = 5.0;
+/// Springs made with the link tool are sized to the pair they join: two free particles
+/// oscillate at this angular frequency, in rad/s, with this fraction of critical damping.
+const SPRING_FREQUENCY: f32 = 40.0;
+const SPRING_DAMPING_RATIO: f32 = 0.1;
+/// Spacing of the particles in a chain drawn with the link tool, in particle radii, leaving a
+/// small gap so that neighbours do not collide at rest.
+const CHAIN_SPACING: f32 = 2.2;
+/// What the left mouse button does.
+#[derive(Clone, Copy, PartialEq)]
+enum Tool {
+    Spawn,
+    Fling,
+    Grab,
+    Select,
+    Draw,
+    Field,
+    Emitter,
+    Sink,
+    Link,
+}
+
+impl Tool {
+    fn from_key(key: Keycode) -> Option<Self> {
+        match key {
+            Keycode::Num1 => Some(Tool::Spawn),
+            Keycode::Num2 => Some(Tool::Fling),
+            Keycode::Num3 => Some(Tool::Grab),
+            Keycode::Num4 => Some(Tool::Select),
+            Keycode::Num5 => Some(Tool::Draw),
+            Keycode::Num6 => Some(Tool::Field),
+            Keycode::Num7 => Some(Tool::Emitter),
+            Keycode::Num8 => Some(Tool::Sink),
+            Keycode::Num9 => Some(Tool::Link),
+            _ => None,
+        }
+    }
+}
+
+/// Obstacle drawn with the left mouse button.
+#[derive(Clone, Copy, PartialEq)]
+enum DrawShape {
+    /// Drag from one end to the other
+    Wall,
+    /// Drag from the centre outwards
+    Circle,
+    /// Click the corners, right click to close
+    Polygon,
+}
+
+impl DrawShape {
+    fn next(self) -> Self {
+        match self {
+            DrawShape::Wall => DrawShape::Circle,
+            DrawShape::Circle => DrawShape::Polygon,
+            DrawShape::Polygon => DrawShape::Wall,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            DrawShape::Wall => "wall (drag)",
+            DrawShape::Circle => "circle (drag from centre)",
+            DrawShape::Polygon => "polygon (click corners, right click to close)",
+        }
+    }
+}
+
+pub struct ParticleCollisionScene {
+    pub done: bool,
+    pub particles: ParticleSystem,
+    pub enable_traces: bool,
+    /// Restitution and friction given to newly spawned particles
+    spawn_restitution: f32,
+    spawn_friction: f32,
+    spatial_hash: SpatialHash,
+    solver: CollisionSolver,
+    ccd: CcdControls,
+    rng: StdRng,
+    obstacles: Vec<Obstacle>,
+    /// Set when obstacles change so they are registered in the grid again
+    obstacles_dirty: bool,
+    tool: Tool,
+    /// Radius of particles placed with the spawn and fling tools
+    tool_radius: f32,
+    draw_shape: DrawShape,
+    /// Where the current left-button drag started
+    drag_start: Option<(f32, f32)>,
+    polygon_points: Vec<(f32, f32)>,
+    /// Particle held by the grab tool
+    grabbed: Option<usize>,
+    repelling: bool,
+    cursor: (f32, f32),
+    /// Cursor position at the previous update, to give grabbed particles its velocity
+    last_cursor: (f32, f32),
+    /// Outcome of the last save or load
+    status: String,
+    fields: FieldControls,
+    stats: GasStatistics,
+    show_stats: bool,
+    piston: PistonControls,
+    inflow: Inflow,
+    emitters: EmitterControls,
+    constraints: Constraints,
+    /// Whether the link tool makes springs rather than rods, toggled with Y
+    link_springs: bool,
+    /// Particle under the cursor when the current link drag started
+    link_from: Option<u64>,
+    blobs: BlobControls,
+    sleep: SleepControls,
+    /// Broadphase picked with Q
+    broadphase_kind: BroadphaseKind,
+    /// `None` for the grid, which is `spatial_hash` walked in parallel row bands by the solver
+    broadphase: Option<Box<dyn Broadphase>>,
+    particle_pairs: ParticlePairs,
+    pairs: Vec<(u32, u32)>,
+    /// Time spent finding and resolving particle contacts, averaged over recent frames
+    collision_ms: f32,
+}
+
+impl ParticleCollisionScene {
+    pub fn new(ctx: &GlobalContext) -> Self {
+        ParticleCollisionScene {
+            done: false,
+            particles: ParticleSystem::new(),
+            enable_traces: true,
+            spawn_restitution: 1.0,
+            spawn_friction: 0.0,
+            spatial_hash: SpatialHash::new(MIN_CELL_SIZE, ctx.screen_width, ctx.screen_height),
+            solver: CollisionSolver::new(Self::default_threads()),
+            ccd: CcdControls::new(),
+            rng: StdRng::seed_from_u64(DEFAULT_SEED),
+            obstacles: Vec::new(),
+            obstacles_dirty: false,
+            tool: Tool::Spawn,
+            tool_radius: 10.0,
+            draw_shape: DrawShape::Wall,
+            drag_start: None,
+            polygon_points: Vec::new(),
+            grabbed: None,
+            repelling: false,
+            cursor: (0.0, 0.0),
+            last_cursor: (0.0, 0.0),
+            status: String::new(),
+            fields: FieldControls::new(),
+            stats: GasStatistics::new(),
+            show_stats: true,
+            piston: PistonControls::new(),
+            inflow: Inflow::new(INFLOW_DENSITY),
+            emitters: EmitterControls::new(),
+            constraints: Constraints::new(),
+            link_springs: false,
+            link_from: None,
+            blobs: BlobControls::new(),
+            sleep: SleepControls::new(),
+            broadphase_kind: BroadphaseKind::Grid,
+            broadphase: None,
+            particle_pairs: ParticlePairs::new(),
+            pairs: Vec::new(),
+            collision_ms: 0.0,
+        }
+    }
+
+    fn default_threads() -> usize {
+        std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+    }
+
+    fn required_cell_size(&self) -> f32 {
+        cell_size_for_radius(self.particles.max_radius())
+    }
+
+    /// Sizes the grid and registers the obstacles in it. Particles are only bucketed when the
+    /// grid is the broadphase.
+    fn update_grid(&mut self, screen_w: u32, screen_h: u32) {
+        let cell_size = self.required_cell_size();
+        if cell_size != self.spatial_hash.cell_size() {
+            self.spatial_hash.resize(cell_size, screen_w, screen_h);
+            self.obstacles_dirty = true;
+        }
+        let boundaries = self.particles.boundaries;
+        if self.spatial_hash.set_periodic(
+            boundaries.x == BoundaryMode::Periodic,
+            boundaries.y == BoundaryMode::Periodic,
+        ) {
+            self.obstacles_dirty = true;
+        }
+        if self.obstacles_dirty {
+            self.spatial_hash.build_static(&self.obstacles);
+            self.obstacles_dirty = false;
+            // A pile resting on a removed obstacle must fall
+            self.wake_all();
+        }
+    }
+
+    fn add_obstacle(&mut self, obstacle: Obstacle) {
+        self.obstacles.push(obstacle);
+        self.obstacles_dirty = true;
+    }
+
+    /// Drops the last corner of the polygon being drawn, or else the last obstacle.
+    fn undo_drawing(&mut self) {
+        if self.polygon_points.pop().is_none() && self.obstacles.pop().is_some() {
+            self.obstacles_dirty = true;
+        }
+    }
+
+    fn layout_summary(&self) -> String {
+        format!(
+            "{} obstacles, {} emitters and {} sinks",
+            self.obstacles.len(),
+            self.emitters.placed.len(),
+            self.emitters.sinks.len()
+        )
+    }
+
+    fn save_layout(&mut self) {
+        let layout = Layout {
+            obstacles: self.obstacles.clone(),
+            emitters: self.emitters.placed.clone(),
+            sinks: self.emitters.sinks.clone(),
+        };
+        self.status = match save_layout(Path::new(LAYOUT_FILE), &layout) {
+            Ok(()) => format!("Saved {} to {}", self.layout_summary(), LAYOUT_FILE),
+            Err(e) => format!("Could not save {}: {}", LAYOUT_FILE, e),
+        };
+    }
+
+    fn load_layout(&mut self) {
+        self.status = match load_layout(Path::new(LAYOUT_FILE)) {
+            Ok(layout) => {
+                self.obstacles = layout.obstacles;
+                self.emitters.placed = layout.emitters;
+                self.emitters.sinks = layout.sinks;
+                self.obstacles_dirty = true;
+                // Scripts run from the moment the layout is loaded
+                self.emitters.restart();
+                format!("Loaded {} from {}", self.layout_summary(), LAYOUT_FILE)
+            }
+            Err(e) => format!("Could not load {}", e),
+        };
+    }
+
+    /// Particle of the tool radius and the spawn material.
+    fn new_particle(&self, (x, y): (f32, f32), (vx, vy): (f32, f32)) -> Particle {
+        Particle::new(x, y, vx, vy, self.tool_radius)
+            .with_restitution(self.spawn_restitution)
+            .with_friction(self.spawn_friction)
+    }
+
+    fn spawn_at(&mut self, position: (f32, f32), velocity: (f32, f32)) {
+        self.particles.push(self.new_particle(position, velocity));
+    }
+
+    /// Handles mouse input for the active tool. Right click repels particles, except that it
+    /// closes the polygon being drawn.
+    fn handle_mouse_event(&mut self, event: &Event) {
+        match *event {
+            Event::MouseMotion { x, y, .. } => {
+                self.cursor = (x as f32, y as f32);
+                self.piston.drag_to(x as f32);
+                if self.fields.drag_to(self.cursor) {
+                    self.wake_all();
+                }
+            }
+            Event::MouseWheel { y, .. } if self.tool == Tool::Field => {
+                let scaled = self.fields.scale_nearest(self.cursor, y);
+                if scaled {
+                    self.wake_all();
+                }
+            }
+            Event::MouseWheel { y, .. } => {
+                self.tool_radius = (self.tool_radius + y as f32).clamp(2.0, 40.0);
+            }
+            Event::MouseButtonDown {
+                mouse_btn: MouseButton::Left,
+                x,
+                ..
+            } if self.piston.is_at(x as f32) => self.piston.grab(),
+            Event::MouseButtonDown {
+                mouse_btn: MouseButton::Left,
+                x,
+                y,
+                ..
+            } => {
+                let point = (x as f32, y as f32);
+                match self.tool {
+                    Tool::Spawn => self.spawn_at(point, (0.0, 0.0)),
+                    Tool::Grab => self.grabbed = self.particles.at(point.0, point.1),
+                    Tool::Draw if self.draw_shape == DrawShape::Polygon => {
+                        self.polygon_points.push(point);
+                    }
+                    Tool::Fling | Tool::Select | Tool::Draw | Tool::Emitter | Tool::Sink => {
+                        self.drag_start = Some(point)
+                    }
+                    Tool::Link => {
+                        self.drag_start = Some(point);
+                        self.link_from = self
+                            .particles
+                            .at(point.0, point.1)
+                            .map(|i| self.particles.id[i]);
+                    }
+                    Tool::Field => {
+                        if self.fields.press(point) {
+                            self.wake_all();
+                        }
+                    }
+                }
+            }
+            Event::MouseButtonUp {
+                mouse_btn: MouseButton::Left,
+                x,
+                y,
+                ..
+            } => {
+                self.grabbed = None;
+                self.fields.release();
+                self.piston.release();
+                let Some(start) = self.drag_start.take() else {
+                    return;
+                };
+                let end = (x as f32, y as f32);
+                let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+                let length = (dx * dx + dy * dy).sqrt();
+                let (e, mu) = (self.spawn_restitution, self.spawn_friction);
+                match self.tool {
+                    Tool::Fling => self.spawn_at(start, (dx * FLING_SCALE, dy * FLING_SCALE)),
+                    Tool::Select => {
+                        let (x0, x1) = (start.0.min(end.0), start.0.max(end.0));
+                        let (y0, y1) = (start.1.min(end.1), start.1.max(end.1));
+                        let particles = &self.particles;
+                        let inside = |i: usize| {
+                            (x0..=x1).contains(&particles.x[i])
+                                && (y0..=y1).contains(&particles.y[i])
+                        };
+                        let mask: Vec<bool> = (0..particles.len()).map(|i| !inside(i)).collect();
+                        self.particles.retain(|i| mask[i]);
+                    }
+                    Tool::Emitter => self.emitters.place_emitter(start, end, self.tool_radius),
+                    Tool::Sink => self.emitters.place_sink(start, end),
+                    Tool::Link => self.finish_link(start, end, length),
+                    Tool::Draw if length >= MIN_DRAG => match self.draw_shape {
+                        DrawShape::Wall => self.add_obstacle(Obstacle::segment(start, end, e, mu)),
+                        DrawShape::Circle => {
+                            self.add_obstacle(Obstacle::circle(start, length, e, mu))
+                        }
+                        DrawShape::Polygon => {}
+                    },
+                    _ => {}
+                }
+            }
+            Event::MouseButtonDown {
+                mouse_btn: MouseButton::Right,
+                ..
+            } => {
+                if self.tool == Tool::Draw && !self.polygon_points.is_empty() {
+                    self.close_polygon();
+                } else if self.tool == Tool::Link {
+                    let (cx, cy) = self.cursor;
+                    if let Some(i) = self.particles.at(cx, cy) {
+                        self.constraints.toggle_pin(&self.particles, i);
+                    }
+                } else {
+                    self.repelling = true;
+                }
+            }
+            Event::MouseButtonUp {
+                mouse_btn: MouseButton::Right,
+                ..
+            } => self.repelling = false,
+            _ => {}
+        }
+    }
+
+    /// Ends a link drag at `end`: links the particles under both ends, or else lays a chain of
+    /// new particles along the drag, attached to whichever ends landed on a particle.
+    fn finish_link(&mut self, start: (f32, f32), end: (f32, f32), length: f32) {
+        let from = self
+            .link_from
+            .take()
+            .and_then(|id| self.particles.index_of(id));
+        let to = self.particles.at(end.0, end.1);
+        if let (Some(i), Some(j)) = (from, to) {
+            if i != j && !self.link(i, j) {
+                self.status = "Those particles are already linked".to_string();
+            }
+            return;
+        }
+        if length < MIN_DRAG {
+            return;
+        }
+
+        let (x0, y0) = match from {
+            Some(i) => (self.particles.x[i], self.particles.y[i]),
+            None => start,
+        };
+        let (x1, y1) = match to {
+            Some(j) => (self.particles.x[j], self.particles.y[j]),
+            None => end,
+        };
+        let span = (x1 - x0).hypot(y1 - y0);
+        let segments = ((span / (CHAIN_SPACING * self.tool_radius)).round() as usize).max(1);
+        let mut previous = match from {
+            Some(i) => self.particles.id[i],
+            None => {
+                self.spawn_at((x0, y0), (0.0, 0.0));
+                self.particles.id[self.particles.len() - 1]
+            }
+        };
+        for k in 1..=segments {
+            let current = match to {
+                Some(j) if k == segments => self.particles.id[j],
+                _ => {
+                    let t = k as f32 / segments as f32;
+                    self.spawn_at((x0 + (x1 - x0) * t, y0 + (y1 - y0) * t), (0.0, 0.0));
+                    self.particles.id[self.particles.len() - 1]
+                }
+            };
+            if let (Some(i), Some(j)) = (
+                self.particles.index_of(previous),
+                self.particles.index_of(current),
+            ) {
+                self.link(i, j);
+            }
+            previous = current;
+        }
+    }
+
+    fn close_polygon(&mut self) {
+        let points = std::mem::take(&mut self.polygon_points);
+        match Obstacle::polygon(&points, self.spawn_restitution, self.spawn_friction) {
+            Some(polygon) => self.add_obstacle(polygon),
+            None => self.status = "A polygon needs three corners that are not in line".to_string(),
+        }
+    }
+
+    /// Steps the edge mode of one axis: reflective, periodic, open, inflow. Opening the x
+    /// edges removes the piston.
+    fn cycle_boundary(&mut self, y_axis: bool) {
+        let boundaries = &mut self.particles.boundaries;
+        let mode = if y_axis {
+            &mut boundaries.y
+        } else {
+            &mut boundaries.x
+        };
+        *mode = match *mode {
+            BoundaryMode::Reflective => BoundaryMode::Periodic,
+            BoundaryMode::Periodic => BoundaryMode::Open,
+            BoundaryMode::Open => BoundaryMode::Inflow {
+                speed: INFLOW_SPEED,
+            },
+            BoundaryMode::Inflow { .. } => BoundaryMode::Reflective,
+        };
+        self.piston.edges_changed(self.particles.boundaries);
+        self.wake_all();
+    }
+
+    /// Moves the grabbed particle onto the cursor, moving with it so it is thrown on release.
+    fn drag_grabbed(&mut self, dt: f32) {
+        let cursor = self.cursor;
+        let (last_x, last_y) = std::mem::replace(&mut self.last_cursor, cursor);
+        let Some(i) = self.grabbed else {
+            return;
+        };
+        self.particles.x[i] = cursor.0;
+        self.particles.y[i] = cursor.1;
+        if dt > 0.0 {
+            self.particles.vx[i] = (cursor.0 - last_x) / dt;
+            self.particles.vy[i] = (cursor.1 - last_y) / dt;
+        }
+    }
+
+    fn tool_label(&self) -> String {
+        match self.tool {
+            Tool::Spawn => format!("spawn, radius {:.0} (wheel)", self.tool_radius),
+            Tool::Fling => format!("fling (drag), radius {:.0} (wheel)", self.tool_radius),
+            Tool::Grab => "grab and move".to_string(),
+            Tool::Select => "box select and delete".to_string(),
+            Tool::Draw => format!("draw {} (O)", self.draw_shape.label()),
+            Tool::Emitter => self.emitters.tool_label(),
+            Tool::Sink => "place sink, click for a disc or drag a box".to_string(),
+            Tool::Field => self.fields.tool_label(),
+            Tool::Link => format!(
+                "link with {} (Y), drag between particles or lay a chain, right click pins",
+                self.link_label()
+            ),
+        }
+    }
+
+    fn link_label(&self) -> &'static str {
+        if self.link_springs {
+            "springs"
+        } else {
+            "rods"
+        }
+    }
+
+    /// Links particles `i` and `j` with a rod or a spring. Returns false if they were already
+    /// linked.
+    fn link(&mut self, i: usize, j: usize) -> bool {
+        let kind = if self.link_springs {
+            let reduced_mass =
+                1.0 / (self.particles.inverse_mass(i) + self.particles.inverse_mass(j)).max(1e-6);
+            LinkKind::Spring {
+                stiffness: reduced_mass * SPRING_FREQUENCY * SPRING_FREQUENCY,
+                damping: 2.0 * SPRING_DAMPING_RATIO * reduced_mass * SPRING_FREQUENCY,
+            }
+        } else {
+            LinkKind::Rod
+        };
+        self.constraints.link(&self.particles, i, j, kind)
+    }
+
+    /// Finds candidate pairs with the selected broadphase and resolves them.
+    fn solve_contacts(&mut self, screen_w: u32, screen_h: u32) {
+        let start = Instant::now();
+        match &mut self.broadphase {
+            None => {
+                self.spatial_hash.build(self.particles.positions());
+                self.solver.solve(&self.spatial_hash, &mut self.particles);
+            }
+            Some(broadphase) => {
+                let boundaries = self.particles.boundaries;
+                self.particle_pairs.find(
+                    broadphase.as_mut(),
+                    &self.particles,
+                    (screen_w as f32, screen_h as f32),
+                    (
+                        boundaries.x == BoundaryMode::Periodic,
+                        boundaries.y == BoundaryMode::Periodic,
+                    ),
+                    &mut self.pairs,
+                );
+                self.solver
+                    .solve_pairs(&self.spatial_hash, &mut self.particles, &self.pairs);
+            }
+        }
+        let ms = start.elapsed().as_secs_f32() * 1000.0;
+        self.collision_ms += 0.1 * (ms - self.collision_ms);
+    }
+
+    fn cycle_broadphase(&mut self) {
+        self.broadphase_kind = self.broadphase_kind.next();
+        self.broadphase = match self.broadphase_kind {
+            BroadphaseKind::Grid => None,
+            kind => Some(kind.create()),
+        };
+    }
+
+    /// Wakes every particle, for changes that sleeping particles would not notice on their
+    /// own, like fields, boundaries and obstacles coming and going.
+    fn wake_all(&mut self) {
+        Sleep::wake_all(&mut self.particles.sleep);
+    }
+
+    /// Speed histogram against the Maxwell–Boltzmann fit, with the kinetic-theory numbers
+    /// underneath, in the bottom right corner.
+    fn render_statistics(&self, ctx: &GlobalContext, canvas: &mut Canvas<Window>) {
+        let stats = &self.stats;
+        let (w, h) = (400.0, 200.0);
+        let x0 = ctx.screen_width as f32 - w - 20.0;
+        let y0 = ctx.screen_height as f32 - h - 90.0;
+        let _ = canvas.box_(
+            x0 as i16,
+            y0 as i16,
+            (x0 + w) as i16,
+            (y0 + h + 70.0) as i16,
+            (0, 0, 0, 200),
+        );
+
+        let top = stats
+            .histogram
+            .iter()
+            .chain(stats.fitted.iter())
+            .copied()
+            .fold(1e-9, f32::max);
+        let bar_width = w / SPEED_BINS as f32;
+        let height_of = |density: f32| density / top * (h - 20.0);
+        for (k, &density) in stats.histogram.iter().enumerate() {
+            let left = x0 + k as f32 * bar_width;
+            let _ = canvas.box_(
+                left as i16,
+                (y0 + h - height_of(density)) as i16,
+                (left + bar_width - 1.0) as i16,
+                (y0 + h) as i16,
+                (90, 140, 220, 255),
+            );
+        }
+        let curve: Vec<(i16, i16)> = stats
+            .fitted
+            .iter()
+            .enumerate()
+            .map(|(k, &density)| {
+                (
+                    (x0 + (k as f32 + 0.5) * bar_width) as i16,
+                    (y0 + h - height_of(density)) as i16,
+                )
+            })
+            .collect();
+        for pair in curve.windows(2) {
+            let _ = canvas.thick_line(
+                pair[0].0,
+                pair[0].1,
+                pair[1].0,
+                pair[1].1,
+                2,
+                (255, 180, 80, 255),
+            );
+        }
+
+        let white = (255, 255, 255, 255);
+        let range = stats.bin_width * SPEED_BINS as f32;
+        let path = |length: f32| {
+            if length.is_finite() {
+                format!("{:.1} px", length)
+            } else {
+                "-".to_string()
+            }
+        };
+        let lines = [
+            format!(
+                "Thermal speeds 0-{:.0} px/s vs 2D Maxwell-Boltzmann (H hides)",
+                range
+            ),
+            format!(
+                "Kinetic energy {:.3e}, kT {:.3e}, mean speed {:.0} px/s",
+                stats.kinetic_energy, stats.temperature, stats.mean_speed
+            ),
+            format!("Collisions: {:.0} per second", stats.collision_rate),
+            format!(
+                "Mean free path {} (hard-disc theory {})",
+                path(stats.mean_free_path),
+                path(stats.predicted_mean_free_path)
+            ),
+        ];
+        for (k, line) in lines.iter().enumerate() {
+            let _ = canvas.string(
+                x0 as i16 + 6,
+                (y0 + h + 6.0 + 15.0 * k as f32) as i16,
+                line,
+                white,
+            );
+        }
+    }
+
+    /// Outline of whatever the active tool is about to do.
+    fn render_preview(&self, canvas: &mut Canvas<Window>) {
+        let color = (160, 160, 170, 255);
+        let (cx, cy) = (self.cursor.0 as i16, self.cursor.1 as i16);
+        if let Some((sx, sy)) = self.drag_start {
+            let (sx, sy) = (sx as i16, sy as i16);
+            match (self.tool, self.draw_shape) {
+                (Tool::Fling, _) => {
+                    let _ = canvas.circle(sx, sy, self.tool_radius as i16, color);
+                    let _ = canvas.line(sx, sy, cx, cy, color);
+                }
+                (Tool::Emitter, _) => {
+                    let _ = canvas.line(sx, sy, cx, cy, color);
+                    let _ = canvas.filled_circle(cx, cy, 3, color);
+                }
+                (Tool::Select, _) | (Tool::Sink, _) => {
+                    let _ = canvas.rectangle(sx.min(cx), sy.min(cy), sx.max(cx), sy.max(cy), color);
+                }
+                (Tool::Draw, DrawShape::Wall) | (Tool::Link, _) => {
+                    let _ = canvas.line(sx, sy, cx, cy, color);
+                }
+                (Tool::Draw, DrawShape::Circle) => {
+                    let radius = ((cx - sx) as f32).hypot((cy - sy) as f32);
+                    let _ = canvas.circle(sx, sy, radius as i16, color);
+                }
+                _ => {}
+            }
+        } else if self.tool == Tool::Spawn {
+            let _ = canvas.circle(cx, cy, self.tool_radius as i16, color);
+        }
+        if let Some(i) = self.grabbed {
+            let (x, y) = (self.particles.x[i] as i16, self.particles.y[i] as i16);
+            let _ = canvas.circle(x, y, self.particles.radius[i] as i16 + 3, color);
+        }
+        if self.repelling {
+            let _ = canvas.circle(cx, cy, REPEL_RADIUS as i16, color);
+        }
+        for (k, &(px, py)) in self.polygon_points.iter().enumerate() {
+            let (px, py) = (px as i16, py as i16);
+            let _ = canvas.filled_circle(px, py, 3, color);
+            let (nx, ny) = self
+                .polygon_points
+                .get(k + 1)
+                .map(|&(x, y)| (x as i16, y as i16))
+                .unwrap_or((cx, cy));
+            let _ = canvas.line(px, py, nx, ny, color);
+        }
+    }
+}
+
+impl Scene for ParticleCollisionScene {
+    fn update(&mut self, ctx: &mut GlobalContext, dt: f32) {
+        self.drag_grabbed(dt);
+        if !ctx.paused {
+            let real_dt = dt * ctx.simulation_speed;
+            self.fields.forces.advance(real_dt);
+            if self.repelling {
+                self.particles
+                    .push_away(self.cursor, REPEL_RADIUS, REPEL_ACCEL, real_dt);
+            }
+            self.update_grid(ctx.screen_width, ctx.screen_height);
+            // Speeds before the solver bounces anything off the sleepers decide what wakes them
+            self.sleep.record_speeds(&self.particles);
+            self.solve_contacts(ctx.screen_width, ctx.screen_height);
+            self.solver
+                .solve_obstacles(&self.spatial_hash, &mut self.particles, &self.obstacles);
+            // Sleepers held still by the solver wake when an awake particle reaches them
+            let touching = self.solver.touching();
+            self.sleep.wake_touched(
+                &mut self.particles,
+                touching.iter().map(|&(i, j)| (i as usize, j as usize)),
+            );
+            if real_dt > 0.0 {
+                self.sleep.update(&mut self.particles, touching, real_dt);
+            }
+            let (w, h) = (ctx.screen_width, ctx.screen_height);
+            self.constraints.apply_springs(&mut self.particles, real_dt);
+            let (solver, fields) = (&self.solver, &self.fields.forces);
+            let mut walls = self.ccd.integrate(
+                &mut self.particles,
+                &self.obstacles,
+                real_dt,
+                (w as f32, h as f32),
+                |system, t| solver.integrate(system, fields, t, w, h),
+            );
+            let hits = self.ccd.impact_pairs().iter().copied();
+            self.sleep.wake_touched(&mut self.particles, hits);
+            // The piston takes the place of the right edge
+            let container_width = self
+                .piston
+                .advance(&mut self.particles, real_dt, w, &mut walls);
+            self.constraints.project(&mut self.particles, real_dt);
+            self.blobs.project(&mut self.particles, real_dt);
+            let (e, mu) = (self.spawn_restitution, self.spawn_friction);
+            self.inflow.feed(
+                &mut self.particles,
+                real_dt,
+                (w as f32, h as f32),
+                &mut self.rng,
+                |x, y, vx, vy| {
+                    Particle::new(x, y, vx, vy, INFLOW_RADIUS)
+                        .with_restitution(e)
+                        .with_friction(mu)
+                },
+            );
+            if self
+                .emitters
+                .step(&mut self.particles, real_dt, &mut self.rng)
+            {
+                self.grabbed = None;
+            }
+            if self.particles.remove_escaped(w as f32, h as f32) > 0 {
+                // Indices have shifted
+                self.grabbed = None;
+            }
+            self.particles.update_trails(self.enable_traces);
+
+            let swept = self.ccd.swept_collisions();
+            self.stats
+                .record_collisions(self.solver.last_collisions() + swept, real_dt);
+            let area = container_width * h as f32;
+            if self.show_stats || self.piston.show_pressure {
+                self.stats.update(&self.particles, area);
+            }
+            self.piston.pressure.record(
+                walls,
+                real_dt,
+                (container_width, h as f32),
+                self.stats.temperature,
+                self.particles.len(),
+            );
+        }
+    }
+
+    fn render(&mut self, ctx: &GlobalContext, canvas: &mut Canvas<Window>) {
+        for obstacle in &self.obstacles {
+            obstacle.render(canvas);
+        }
+        self.piston.render(ctx, canvas);
+        self.emitters.render(canvas);
+        self.fields.render(canvas);
+        self.render_preview(canvas);
+        self.blobs.render(canvas, &self.particles);
+        self.particles.render(canvas, self.enable_traces);
+        self.constraints.render(canvas, &self.particles);
+
+        let particle_count = self.particles.len();
+        let text = format!("Total particles: {}", particle_count);
+        let x: i16 = 10;
+        let y: i16 = 10;
+
+        let r = 255;
+        let g = 255;
+        let b = 255;
+        let a = 255;
+
+        let _ = canvas.string(x, y, &text, (r, g, b, a));
+
+        let spawn_text = format!(
+            "New particles and obstacles: restitution {:.1} (E), friction {:.1} (F)",
+            self.spawn_restitution, self.spawn_friction
+        );
+        let _ = canvas.string(x, y + 15, &spawn_text, (r, g, b, a));
+
+        let threads_text = format!("Worker threads: {} ([ / ])", self.solver.threads());
+        let _ = canvas.string(x, y + 30, &threads_text, (r, g, b, a));
+
+        let _ = canvas.string(x, y + 45, &self.ccd.status(), (r, g, b, a));
+
+        let tool_text = format!("Tool (1-9): {}, right click repels", self.tool_label());
+        let _ = canvas.string(x, y + 60, &tool_text, (r, g, b, a));
+
+        let obstacle_text = format!(
+            "{}, {} absorbed (Backspace undo, Del clear, Ctrl+S / Ctrl+L {})",
+            self.layout_summary(),
+            self.emitters.absorbed(),
+            LAYOUT_FILE
+        );
+        let _ = canvas.string(x, y + 75, &obstacle_text, (r, g, b, a));
+        let _ = canvas.string(x, y + 90, &self.fields.status(), (r, g, b, a));
+        let _ = canvas.string(x, y + 105, &self.piston.status(), (r, g, b, a));
+        let boundary_text = format!(
+            "Edges (B x, Shift+B y): {}",
+            self.particles.boundaries.label()
+        );
+        let _ = canvas.string(x, y + 120, &boundary_text, (r, g, b, a));
+        let link_text = format!(
+            "Links: {} (new ones are {}, Y), {} pinned. {}",
+            self.constraints.links.len(),
+            self.link_label(),
+            self.constraints.pins.len(),
+            self.blobs.status()
+        );
+        let _ = canvas.string(x, y + 135, &link_text, (r, g, b, a));
+        let sleep_text = self.sleep.status(&self.particles);
+        let _ = canvas.string(x, y + 150, &sleep_text, (r, g, b, a));
+        let broadphase_text = format!(
+            "Broadphase (Q): {}, {} candidate pairs, contacts take {:.2} ms per frame",
+            self.broadphase_kind.label(),
+            self.solver.last_candidates(),
+            self.collision_ms
+        );
+        let _ = canvas.string(x, y + 165, &broadphase_text, (r, g, b, a));
+        let _ = canvas.string(x, y + 180, &self.status, (r, g, b, a));
+
+        if self.show_stats {
+            self.render_statistics(ctx, canvas);
+        }
+        if self.piston.show_pressure {
+            self.piston.render_pressure(ctx, canvas);
+        }
+    }
+
+    fn handle_event(&mut self, ctx: &mut GlobalContext, event: &Event) {
+        self.handle_mouse_event(event);
+        if let Event::KeyDown {
+            keycode: Some(k),
+            keymod,
+            ..
+        } = event
+        {
+            if let Some(tool) = Tool::from_key(*k) {
+                self.tool = tool;
+                self.drag_start = None;
+                self.link_from = None;
+                self.polygon_points.clear();
+                self.grabbed = None;
+                self.fields.release();
+            }
+            self.sleep.handle_key(*k);
+            self.ccd.handle_key(*k);
+            self.emitters.handle_key(*k);
+            let skin = self.new_particle((0.0, 0.0), (0.0, 0.0));
+            self.blobs
+                .handle_key(*k, &mut self.particles, self.cursor, &skin);
+            let fields_changed = self.fields.handle_key(*k, self.cursor);
+            let container_changed =
+                self.piston
+                    .handle_key(*k, &mut self.particles, ctx.screen_width);
+            if fields_changed || container_changed {
+                self.wake_all();
+            }
+            match k {
+                Keycode::Escape => {
+                    self.done = true;
+                }
+                Keycode::Space => {
+                    ctx.paused = !ctx.paused;
+                }
+                Keycode::Left => {
+                    ctx.simulation_speed -= 0.1;
+                }
+                Keycode::Right => {
+                    ctx.simulation_speed += 0.1;
+                }
+                Keycode::Down => {
+                    ctx.simulation_speed = -1.0;
+                }
+                Keycode::Up => {
+                    ctx.simulation_speed = 1.0;
+                }
+                Keycode::T => self.enable_traces = !self.enable_traces,
+                Keycode::H => self.show_stats = !self.show_stats,
+                Keycode::Q => self.cycle_broadphase(),
+                Keycode::B => {
+                    self.cycle_boundary(keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD));
+                }
+                Keycode::R => {
+                    self.grabbed = None;
+                    self.particles.clear();
+                    self.constraints.clear();
+                    self.blobs.clear();
+                    self.piston.pressure.clear();
+                    self.rng = StdRng::seed_from_u64(DEFAULT_SEED);
+                    self.emitters.reset();
+                }
+                Keycode::LeftBracket => {
+                    self.solver = CollisionSolver::new(self.solver.threads().saturating_sub(1));
+                }
+                Keycode::RightBracket => {
+                    self.solver = CollisionSolver::new(self.solver.threads() + 1);
+                }
+                Keycode::Y => self.link_springs = !self.link_springs,
+                Keycode::O => {
+                    if self.tool == Tool::Draw {
+                        self.draw_shape = self.draw_shape.next();
+                    }
+                    self.tool = Tool::Draw;
+                    self.drag_start = None;
+                    self.polygon_points.clear();
+                }
+                Keycode::Backspace => self.undo_drawing(),
+                Keycode::Delete => {
+                    self.obstacles.clear();
+                    self.emitters.clear();
+                    self.constraints.clear();
+                    self.obstacles_dirty = true;
+                }
+                Keycode::S if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => {
+                    self.save_layout();
+                }
+                Keycode::L if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => {
+                    self.load_layout();
+                }
+                Keycode::E => {
+                    self.spawn_restitution = if self.spawn_restitution <= 0.05 {
+                        1.0
+                    } else {
+                        self.spawn_restitution - 0.1
+                    };
+                }
+                Keycode::F => {
+                    self.spawn_friction = if self.spawn_friction >= 0.95 {
+                        0.0
+                    } else {
+                        self.spawn_friction + 0.1
+                    };
+                }
+                Keycode::N | Keycode::M => {
+                    let rng = &mut self.rng;
+                    let num_particles =
+                        if keymod.contains(Mod::LSHIFTMOD) || keymod.contains(Mod::RSHIFTMOD) {
+                            100
+                        } else {
+                            1
+                        };
+                    // N spawns light balls, M spawns larger heavy ones ten times denser
+                    let (radius_range, density) = if *k == Keycode::M {
+                        (12.0..30.0, DEFAULT_DENSITY * 10.0)
+                    } else {
+                        (5.0..15.0, DEFAULT_DENSITY)
+                    };
+                    for _ in 0..num_particles {
+                        let radius = rng.gen_range(radius_range.clone());
+                        let px = rng.gen_range(0..ctx.screen_width) as f32;
+                        let py = rng.gen_range(0..ctx.screen_height) as f32;
+                        let vx = (rng.gen_range(-200..200) as f32) / 1.5;
+                        let vy = (rng.gen_range(-200..200) as f32) / 1.5;
+                        self.particles.push(
+                            Particle::new(px, py, vx, vy, radius)
+                                .with_density(density)
+                                .with_restitution(self.spawn_restitution)
+                                .with_friction(self.spawn_friction),
+                        );
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.done
+    }
+}
@@ -0,0 +1,275 @@
+use crate::engine::GlobalContext;
+use crate::models::boundary::{Boundaries, BoundaryMode};
+use crate::models::particle_system::{ParticleSystem, WallImpulses};
+use crate::models::piston::{Piston, PistonMotion};
+use crate::utils::pressure::PressureGauge;
+use sdl2::gfx::primitives::DrawRenderer;
+use sdl2::keyboard::Keycode;
+use sdl2::{render::Canvas, video::Window};
+
+/// Where a new piston starts, as a fraction of the screen width, and how close it may come to
+/// the right edge.
+const PISTON_START: f32 = 0.75;
+const PISTON_MARGIN: f32 = 40.0;
+/// Scripted compression: the piston swings from its position down to this fraction of it and
+/// back, slowly enough compared to thermal speeds to stay close to adiabatic.
+const PISTON_COMPRESSION: f32 = 0.4;
+const PISTON_PERIOD: f32 = 40.0;
+/// Ratio of heat capacities of a 2D monatomic gas, (f + 2) / f with f = 2.
+const ADIABATIC_INDEX: f32 = 2.0;
+
+/// Movable right wall of the container, toggled with P, and the gauge behind the P-V
+/// diagram shown with V.
+pub struct PistonControls {
+    piston: Option<Piston>,
+    dragging: bool,
+    pub pressure: PressureGauge,
+    pub show_pressure: bool,
+}
+
+impl PistonControls {
+    pub fn new() -> Self {
+        PistonControls {
+            piston: None,
+            dragging: false,
+            pressure: PressureGauge::new(),
+            show_pressure: true,
+        }
+    }
+
+    /// Returns whether the container changed.
+    pub fn handle_key(
+        &mut self,
+        key: Keycode,
+        particles: &mut ParticleSystem,
+        screen_w: u32,
+    ) -> bool {
+        match key {
+            Keycode::P => {
+                self.toggle(particles, screen_w);
+                true
+            }
+            Keycode::J => {
+                self.cycle_motion();
+                false
+            }
+            Keycode::V => {
+                self.show_pressure = !self.show_pressure;
+                false
+            }
+            _ => false,
+        }
+    }
+
+    fn toggle(&mut self, particles: &mut ParticleSystem, screen_w: u32) {
+        self.piston = match self.piston {
+            Some(_) => None,
+            None => Some(Piston::new(screen_w as f32 * PISTON_START)),
+        };
+        // The piston closes the container, so the x edges go back to reflecting
+        if self.piston.is_some() {
+            particles.boundaries.x = BoundaryMode::Reflective;
+        }
+        self.dragging = false;
+        self.pressure.clear();
+    }
+
+    /// Switches the piston between manual dragging and the scripted compression cycle.
+    fn cycle_motion(&mut self) {
+        let Some(piston) = &mut self.piston else {
+            return;
+        };
+        let motion = match piston.motion {
+            PistonMotion::Manual => PistonMotion::Oscillate {
+                low: piston.x * PISTON_COMPRESSION,
+                high: piston.x,
+                period: PISTON_PERIOD,
+            },
+            PistonMotion::Oscillate { .. } => PistonMotion::Manual,
+        };
+        piston.set_motion(motion);
+        self.dragging = false;
+        self.pressure.clear();
+    }
+
+    /// Takes changed edge modes into account: the piston only stays while the x edges reflect.
+    pub fn edges_changed(&mut self, boundaries: Boundaries) {
+        if boundaries.x != BoundaryMode::Reflective {
+            self.piston = None;
+            self.dragging = false;
+        }
+        self.pressure.clear();
+    }
+
+    /// Whether a click at `x` grabs a piston that is free to be dragged.
+    pub fn is_at(&self, x: f32) -> bool {
+        self.piston
+            .as_ref()
+            .is_some_and(|p| p.motion == PistonMotion::Manual && p.is_near(x))
+    }
+
+    pub fn grab(&mut self) {
+        self.dragging = true;
+    }
+
+    pub fn drag_to(&mut self, x: f32) {
+        if let (true, Some(piston)) = (self.dragging, &mut self.piston) {
+            piston.drag_to(x);
+        }
+    }
+
+    pub fn release(&mut self) {
+        self.dragging = false;
+    }
+
+    /// Moves the piston over `dt` and keeps the particles to its left, counting what they hand
+    /// it as the right wall's share of `walls`. Returns the width of the container.
+    pub fn advance(
+        &mut self,
+        particles: &mut ParticleSystem,
+        dt: f32,
+        screen_w: u32,
+        walls: &mut WallImpulses,
+    ) -> f32 {
+        let Some(piston) = &mut self.piston else {
+            return screen_w as f32;
+        };
+        piston.advance(dt, screen_w as f32 - PISTON_MARGIN);
+        walls.right = piston.confine(particles);
+        piston.x
+    }
+
+    pub fn status(&self) -> String {
+        match &self.piston {
+            None => "Piston off (P)".to_string(),
+            Some(piston) => {
+                let motion = match piston.motion {
+                    PistonMotion::Manual => "drag to move".to_string(),
+                    PistonMotion::Oscillate { period, .. } => {
+                        format!("compression cycle of {:.0} s", period)
+                    }
+                };
+                format!(
+                    "Piston (P) at {:.0} px, {:.0} px/s, {} (J switches), P-V panel (V)",
+                    piston.x, piston.velocity, motion
+                )
+            }
+        }
+    }
+
+    pub fn render(&self, ctx: &GlobalContext, canvas: &mut Canvas<Window>) {
+        if let Some(piston) = &self.piston {
+            piston.render(canvas, ctx.screen_width, ctx.screen_height);
+        }
+    }
+
+    /// P-V diagram of the container in the bottom left corner, with the adiabat P V^γ through
+    /// the latest measurement for comparison.
+    pub fn render_pressure(&self, ctx: &GlobalContext, canvas: &mut Canvas<Window>) {
+        let (w, h) = (400.0, 200.0);
+        let x0 = 20.0;
+        let y0 = ctx.screen_height as f32 - h - 90.0;
+        let _ = canvas.box_(
+            x0 as i16,
+            y0 as i16,
+            (x0 + w) as i16,
+            (y0 + h + 70.0) as i16,
+            (0, 0, 0, 200),
+        );
+        let axis = (120, 120, 130, 255);
+        let _ = canvas.hline(x0 as i16, (x0 + w) as i16, (y0 + h) as i16, axis);
+        let _ = canvas.vline(x0 as i16, y0 as i16, (y0 + h) as i16, axis);
+
+        let history = &self.pressure.history;
+        let (v_min, v_max, p_max) =
+            history
+                .iter()
+                .fold((f32::MAX, f32::MIN, 1e-9f32), |(v_min, v_max, p_max), s| {
+                    (
+                        v_min.min(s.volume),
+                        v_max.max(s.volume),
+                        p_max.max(s.pressure),
+                    )
+                });
+        // A fixed container still gets a readable horizontal range
+        let pad = ((v_max - v_min) * 0.05).max(v_max.abs() * 0.01).max(1.0);
+        let (v_min, v_max, p_max) = (v_min - pad, v_max + pad, p_max * 1.2);
+        let to_screen = |volume: f32, pressure: f32| {
+            (
+                (x0 + (volume - v_min) / (v_max - v_min) * w) as i16,
+                (y0 + h - pressure / p_max * h) as i16,
+            )
+        };
+
+        let latest = self.pressure.latest();
+        if let Some(last) = latest {
+            let invariant = last.pressure * last.volume.powf(ADIABATIC_INDEX);
+            let adiabat: Vec<(i16, i16)> = (0..=40)
+                .map(|k| v_min + (v_max - v_min) * k as f32 / 40.0)
+                .map(|v| (v, invariant / v.powf(ADIABATIC_INDEX)))
+                .filter(|&(_, p)| p <= p_max)
+                .map(|(v, p)| to_screen(v, p))
+                .collect();
+            for pair in adiabat.windows(2) {
+                let _ = canvas.line(
+                    pair[0].0,
+                    pair[0].1,
+                    pair[1].0,
+                    pair[1].1,
+                    (255, 180, 80, 255),
+                );
+            }
+        }
+        let points: Vec<(i16, i16)> = history
+            .iter()
+            .map(|s| to_screen(s.volume, s.pressure))
+            .collect();
+        for pair in points.windows(2) {
+            let _ = canvas.line(
+                pair[0].0,
+                pair[0].1,
+                pair[1].0,
+                pair[1].1,
+                (90, 140, 220, 255),
+            );
+        }
+        if let Some(&(px, py)) = points.last() {
+            let _ = canvas.filled_circle(px, py, 3, (255, 255, 255, 255));
+        }
+
+        let white = (255, 255, 255, 255);
+        let walls = self.pressure.walls;
+        let mut lines = vec![format!(
+            "P-V diagram, area {:.0}-{:.0} px^2, adiabat P V^2 (V hides)",
+            v_min, v_max
+        )];
+        match latest {
+            Some(last) => {
+                lines.push(format!(
+                    "Pressure {:.3e} at t = {:.1} s, P V^2 = {:.3e}",
+                    last.pressure,
+                    last.time,
+                    last.pressure * last.volume.powf(ADIABATIC_INDEX)
+                ));
+                lines.push(format!(
+                    "Walls: left {:.2e}, right {:.2e}, top {:.2e}, bottom {:.2e}",
+                    walls[0], walls[1], walls[2], walls[3]
+                ));
+                lines.push(format!(
+                    "kT {:.3e}, P V / N kT = {:.2}",
+                    last.temperature,
+                    last.compressibility()
+                ));
+            }
+            None => lines.push("Waiting for wall collisions".to_string()),
+        }
+        for (k, line) in lines.iter().enumerate() {
+            let _ = canvas.string(
+                x0 as i16 + 6,
+                (y0 + h + 6.0 + 15.0 * k as f32) as i16,
+                line,
+                white,
+            );
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use crate::models::particle_system::ParticleSystem;
+use crate::utils::sleep::Sleep;
+use sdl2::keyboard::Keycode;
+
+/// Particles slower than this on average, in px/s, count as resting, and a resting pile falls
+/// asleep after this many seconds. Sleepers wake when hit faster than `WAKE_SPEED`.
+const SLEEP_SPEED: f32 = 16.0;
+const WAKE_SPEED: f32 = 200.0;
+const SLEEP_TIME: f32 = 0.5;
+
+/// Puts resting piles to sleep, toggled with Z.
+pub struct SleepControls {
+    sleep: Sleep,
+    /// Particle speeds before the contact solve, which decide what wakes sleepers
+    speeds: Vec<f32>,
+}
+
+impl SleepControls {
+    pub fn new() -> Self {
+        SleepControls {
+            sleep: Sleep::new(SLEEP_SPEED, WAKE_SPEED, SLEEP_TIME),
+            speeds: Vec::new(),
+        }
+    }
+
+    pub fn handle_key(&mut self, key: Keycode) {
+        if key == Keycode::Z {
+            self.sleep.enabled = !self.sleep.enabled;
+        }
+    }
+
+    /// Remembers the speeds before the solver bounces anything off the sleepers.
+    pub fn record_speeds(&mut self, particles: &ParticleSystem) {
+        self.speeds.clear();
+        if self.sleep.enabled {
+            let ParticleSystem { vx, vy, .. } = particles;
+            self.speeds
+                .extend(vx.iter().zip(vy).map(|(vx, vy)| vx.hypot(*vy)));
+        }
+    }
+
+    /// Wakes the sleeping islands that an awake particle reached in `pairs`.
+    pub fn wake_touched(
+        &mut self,
+        particles: &mut ParticleSystem,
+        pairs: impl Iterator<Item = (usize, usize)>,
+    ) {
+        let speeds = &self.speeds;
+        self.sleep
+            .wake_touched(&mut particles.sleep, |i| speeds[i], pairs);
+    }
+
+    /// Wakes the sleepers that something gave a velocity, counts the resting time of each
+    /// particle and puts resting piles to sleep, then stops the new sleepers so that nothing is
+    /// left to wake them but a touch or a push.
+    pub fn update(&mut self, particles: &mut ParticleSystem, touching: &[(u32, u32)], dt: f32) {
+        let ParticleSystem {
+            x,
+            y,
+            vx,
+            vy,
+            sleep,
+            ..
+        } = particles;
+        let pushed = (0..sleep.len()).filter(|&i| vx[i] != 0.0 || vy[i] != 0.0);
+        self.sleep.wake(sleep, pushed);
+        self.sleep.update(
+            sleep,
+            |i| Some([x[i], y[i], 0.0]),
+            touching.iter().map(|&(i, j)| (i as usize, j as usize)),
+            dt,
+        );
+        for (i, state) in sleep.iter().enumerate() {
+            if state.is_asleep() {
+                vx[i] = 0.0;
+                vy[i] = 0.0;
+            }
+        }
+    }
+
+    pub fn status(&self, particles: &ParticleSystem) -> String {
+        let (awake, asleep) = Sleep::counts(&particles.sleep);
+        format!(
+            "Sleeping {} (Z): {} awake, {} asleep in {} islands",
+            if self.sleep.enabled { "on" } else { "off" },
+            awake,
+            asleep,
+            self.sleep.islands()
+        )
+    }
+}
@@ -56,6 +56,13 @@ impl Pendulum {
     }
 }
 
+/// Draws a rod from `pivot` to `bob` with a filled bob at the end.
+pub fn draw_pendulum(canvas: &mut Canvas<Window>, pivot: (i32, i32), bob: (i32, i32), radius: i16) {
+    canvas.set_draw_color(Color::RGB(255, 255, 255));
+    let _ = canvas.draw_line(pivot, bob);
+    let _ = canvas.filled_circle(bob.0 as i16, bob.1 as i16, radius, Color::RGB(0, 255, 0));
+}
+
 impl Scene for Pendulum {
     fn handle_event(&mut self, ctx: &mut GlobalContext, event: &Event) {
         match event {
@@ -107,9 +114,7 @@ impl Scene for Pendulum {
             let y = -scale * self.length * theta.cos();
 
             let point = (window_center_x + x as i32, window_center_y + y as i32);
-            canvas.set_draw_color(sdl2::pixels::Color::RGB(255, 255, 255));
-            canvas.draw_line((window_center_x, window_center_y), (point.0, point.1));
-            canvas.filled_circle(point.0 as i16, point.1 as i16, 30, Color::RGB(0, 255, 0));
+            draw_pendulum(canvas, (window_center_x, window_center_y), point, 30);
             // }
         }
     }
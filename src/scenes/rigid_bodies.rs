@@ -0,0 +1,416 @@
+use crate::engine::{GlobalContext, Scene};
+use crate::models::force_field::GRAVITY;
+use crate::models::rigid_body::{add, rotate, scale, sub, RigidBody, Vec2};
+use crate::utils::rigid_solver::RigidSolver;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use sdl2::gfx::primitives::DrawRenderer;
+use sdl2::mouse::MouseButton;
+use sdl2::{event::Event, keyboard::Keycode};
+use sdl2::{render::Canvas, video::Window};
+use std::f32::consts::TAU;
+
+/// Solver step. Stacks need a small, fixed step to stay put.
+const STEP: f32 = 1.0 / 120.0;
+const MAX_STEPS_PER_FRAME: usize = 8;
+/// Mass per square pixel, the same for every body.
+const DENSITY: f32 = 0.01;
+const GROUND_HEIGHT: f32 = 40.0;
+const BOX_SIZE: f32 = 40.0;
+const PYRAMID_ROWS: usize = 12;
+const DOMINO_COUNT: usize = 24;
+const DOMINO_SIZE: (f32, f32) = (12.0, 80.0);
+const DOMINO_SPACING: f32 = 50.0;
+/// Speed given to the first domino, in px/s.
+const DOMINO_FLICK: f32 = 60.0;
+const MIXED_COUNT: usize = 60;
+/// Natural frequency and damping ratio of the spring pulling a grabbed body to the cursor.
+const GRAB_FREQUENCY: f32 = 4.0;
+const GRAB_DAMPING_RATIO: f32 = 0.7;
+const SEED: u64 = 42;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Preset {
+    Pyramid,
+    Dominoes,
+    Mixed,
+}
+
+/// Shapes dropped with the right mouse button.
+#[derive(Clone, Copy, PartialEq)]
+enum Shape {
+    Box,
+    Circle,
+    Triangle,
+    Hexagon,
+}
+
+impl Shape {
+    fn next(self) -> Self {
+        match self {
+            Shape::Box => Shape::Circle,
+            Shape::Circle => Shape::Triangle,
+            Shape::Triangle => Shape::Hexagon,
+            Shape::Hexagon => Shape::Box,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Shape::Box => "box",
+            Shape::Circle => "circle",
+            Shape::Triangle => "triangle",
+            Shape::Hexagon => "hexagon",
+        }
+    }
+
+    fn make(self, position: Vec2, size: f32) -> RigidBody {
+        match self {
+            Shape::Box => RigidBody::rectangle(position, size, size, DENSITY),
+            Shape::Circle => RigidBody::circle(position, 0.5 * size, DENSITY),
+            Shape::Triangle => RigidBody::regular(position, 0.6 * size, 3, DENSITY),
+            Shape::Hexagon => RigidBody::regular(position, 0.55 * size, 6, DENSITY),
+        }
+    }
+}
+
+/// Rigid boxes, discs and polygons with friction: stack them into a pyramid, topple a row of
+/// dominoes or pour a pile of mixed shapes, and throw them around with the mouse.
+pub struct RigidBodies {
+    bodies: Vec<RigidBody>,
+    solver: RigidSolver,
+    preset: Preset,
+    shape: Shape,
+    show_contacts: bool,
+    /// Body held by the mouse and the held point in the body's own frame
+    grab: Option<(usize, Vec2)>,
+    cursor: Vec2,
+    pending: f32,
+    width: f32,
+    height: f32,
+    rng: StdRng,
+    done: bool,
+}
+
+impl RigidBodies {
+    pub fn new(ctx: &GlobalContext) -> Self {
+        let mut scene = RigidBodies {
+            bodies: Vec::new(),
+            solver: RigidSolver::new(),
+            preset: Preset::Pyramid,
+            shape: Shape::Box,
+            show_contacts: false,
+            grab: None,
+            cursor: (0.0, 0.0),
+            pending: 0.0,
+            width: ctx.screen_width as f32,
+            height: ctx.screen_height as f32,
+            rng: StdRng::seed_from_u64(SEED),
+            done: false,
+        };
+        scene.reset();
+        scene
+    }
+
+    /// Top of the ground.
+    fn floor(&self) -> f32 {
+        self.height - GROUND_HEIGHT
+    }
+
+    fn reset(&mut self) {
+        let (width, height) = (self.width, self.height);
+        self.bodies.clear();
+        self.solver.forget();
+        self.grab = None;
+        self.pending = 0.0;
+        self.rng = StdRng::seed_from_u64(SEED);
+        self.bodies.push(
+            RigidBody::rectangle(
+                (0.5 * width, height - 0.5 * GROUND_HEIGHT),
+                width,
+                GROUND_HEIGHT,
+                DENSITY,
+            )
+            .fixed(),
+        );
+        for x in [0.5 * GROUND_HEIGHT, width - 0.5 * GROUND_HEIGHT] {
+            self.bodies.push(
+                RigidBody::rectangle((x, 0.5 * height), GROUND_HEIGHT, height, DENSITY).fixed(),
+            );
+        }
+        match self.preset {
+            Preset::Pyramid => self.build_pyramid(),
+            Preset::Dominoes => self.build_dominoes(),
+            Preset::Mixed => self.build_mixed(),
+        }
+    }
+
+    fn build_pyramid(&mut self) {
+        let floor = self.floor();
+        for row in 0..PYRAMID_ROWS {
+            let count = PYRAMID_ROWS - row;
+            let left = 0.5 * self.width - 0.5 * (count - 1) as f32 * BOX_SIZE;
+            let y = floor - (row as f32 + 0.5) * BOX_SIZE;
+            for k in 0..count {
+                let shade = 150 + (40 * ((row + k) % 2)) as u8;
+                self.bodies.push(
+                    RigidBody::rectangle(
+                        (left + k as f32 * BOX_SIZE, y),
+                        BOX_SIZE,
+                        BOX_SIZE,
+                        DENSITY,
+                    )
+                    .with_color((shade, 130, 80)),
+                );
+            }
+        }
+    }
+
+    fn build_dominoes(&mut self) {
+        let floor = self.floor();
+        let (w, h) = DOMINO_SIZE;
+        let left = 0.5 * self.width - 0.5 * (DOMINO_COUNT - 1) as f32 * DOMINO_SPACING;
+        for k in 0..DOMINO_COUNT {
+            let mut domino = RigidBody::rectangle(
+                (left + k as f32 * DOMINO_SPACING, floor - 0.5 * h),
+                w,
+                h,
+                DENSITY,
+            )
+            .with_color((230, 230, 220));
+            if k == 0 {
+                // A flick at the top of the first one sets off the rest
+                let impulse = DOMINO_FLICK / domino.inverse_mass;
+                domino.apply_impulse((impulse, 0.0), (0.0, -0.5 * h));
+            }
+            self.bodies.push(domino);
+        }
+    }
+
+    fn build_mixed(&mut self) {
+        // Two ramps to tumble down
+        let (width, floor) = (self.width, self.floor());
+        for (x, angle) in [(0.3 * width, 0.35), (0.7 * width, -0.35)] {
+            self.bodies.push(
+                RigidBody::rectangle((x, 0.6 * floor), 0.35 * width, 20.0, DENSITY)
+                    .fixed()
+                    .with_angle(angle),
+            );
+        }
+        let shapes = [Shape::Box, Shape::Circle, Shape::Triangle, Shape::Hexagon];
+        for _ in 0..MIXED_COUNT {
+            let shape = shapes[self.rng.gen_range(0..shapes.len())];
+            let position = (
+                self.rng.gen_range(0.15 * width..0.85 * width),
+                self.rng.gen_range(-0.6 * floor..0.3 * floor),
+            );
+            let size = self.rng.gen_range(0.6 * BOX_SIZE..1.5 * BOX_SIZE);
+            let color = (
+                self.rng.gen_range(120..255),
+                self.rng.gen_range(120..255),
+                self.rng.gen_range(120..255),
+            );
+            let angle = self.rng.gen_range(0.0..TAU);
+            self.bodies.push(
+                shape
+                    .make(position, size)
+                    .with_angle(angle)
+                    .with_color(color),
+            );
+        }
+    }
+
+    fn body_at(&self, point: Vec2) -> Option<usize> {
+        self.bodies
+            .iter()
+            .position(|b| !b.is_static() && b.contains(point))
+    }
+
+    /// Pulls the held point towards the cursor with a damped spring, applied as an impulse so
+    /// that the body also turns about the point.
+    fn pull_grabbed(&mut self, dt: f32) {
+        let Some((i, local)) = self.grab else {
+            return;
+        };
+        let body = &mut self.bodies[i];
+        let r = rotate(local, body.angle);
+        let point = add(body.position, r);
+        let omega = TAU * GRAB_FREQUENCY;
+        let acceleration = sub(
+            scale(sub(self.cursor, point), omega * omega),
+            scale(body.velocity_at(point), 2.0 * GRAB_DAMPING_RATIO * omega),
+        );
+        body.apply_impulse(scale(acceleration, dt / body.inverse_mass), r);
+    }
+
+    fn on_off(on: bool) -> &'static str {
+        if on {
+            "on"
+        } else {
+            "off"
+        }
+    }
+}
+
+impl Scene for RigidBodies {
+    fn update(&mut self, ctx: &mut GlobalContext, dt: f32) {
+        if ctx.paused {
+            return;
+        }
+        self.pending += dt * ctx.simulation_speed.max(0.0);
+        let mut steps = 0;
+        while self.pending >= STEP && steps < MAX_STEPS_PER_FRAME {
+            self.pull_grabbed(STEP);
+            self.solver.step(&mut self.bodies, STEP);
+            self.pending -= STEP;
+            steps += 1;
+        }
+        self.pending = self.pending.min(STEP);
+        // Bodies thrown out of the window are lost for good
+        let limit = 2.0 * self.height;
+        if self.bodies.iter().any(|b| b.position.1 > limit) {
+            self.bodies.retain(|b| b.position.1 <= limit);
+            self.solver.forget();
+            self.grab = None;
+        }
+    }
+
+    fn render(&mut self, _ctx: &GlobalContext, canvas: &mut Canvas<Window>) {
+        for (i, body) in self.bodies.iter().enumerate() {
+            if self.solver.is_asleep(i) {
+                let (r, g, b) = body.color;
+                body.render_in(canvas, (r / 2, g / 2, b / 2));
+            } else {
+                body.render(canvas);
+            }
+        }
+        if self.show_contacts {
+            self.solver.render_contacts(canvas);
+        }
+        if let Some((i, local)) = self.grab {
+            let body = &self.bodies[i];
+            let point = add(body.position, rotate(local, body.angle));
+            let _ = canvas.line(
+                point.0 as i16,
+                point.1 as i16,
+                self.cursor.0 as i16,
+                self.cursor.1 as i16,
+                (255, 255, 120, 255),
+            );
+        }
+
+        let white = (255, 255, 255, 255);
+        let energy: f32 = self.bodies.iter().map(|b| b.kinetic_energy()).sum();
+        let (awake, asleep) = self.solver.sleep_counts(&self.bodies);
+        let lines = [
+            format!(
+                "{} bodies, {} contact points, kinetic energy {:.3e}",
+                self.bodies.len(),
+                self.solver.contact_count(),
+                energy
+            ),
+            format!(
+                "Iterations {} ([ / ]), warm starting {} (W), contacts {} (C), gravity {} (G)",
+                self.solver.iterations,
+                Self::on_off(self.solver.warm_starting),
+                Self::on_off(self.show_contacts),
+                Self::on_off(self.solver.gravity.1 != 0.0)
+            ),
+            format!(
+                "Sleeping {} (Z): {} awake, {} asleep in {} islands",
+                Self::on_off(self.solver.sleep.enabled),
+                awake,
+                asleep,
+                self.solver.sleep.islands()
+            ),
+            "1 pyramid, 2 dominoes, 3 mixed shapes, R reset".to_string(),
+            format!(
+                "Left drag: throw a body, right click: drop a {} (S to change)",
+                self.shape.name()
+            ),
+        ];
+        for (k, line) in lines.iter().enumerate() {
+            let _ = canvas.string(10, 10 + 15 * k as i16, line, white);
+        }
+    }
+
+    fn handle_event(&mut self, ctx: &mut GlobalContext, event: &Event) {
+        match *event {
+            Event::KeyDown {
+                keycode: Some(k), ..
+            } => match k {
+                Keycode::Escape => self.done = true,
+                Keycode::Space => ctx.paused = !ctx.paused,
+                Keycode::Left => ctx.simulation_speed -= 0.1,
+                Keycode::Right => ctx.simulation_speed += 0.1,
+                Keycode::Num1 => {
+                    self.preset = Preset::Pyramid;
+                    self.reset();
+                }
+                Keycode::Num2 => {
+                    self.preset = Preset::Dominoes;
+                    self.reset();
+                }
+                Keycode::Num3 => {
+                    self.preset = Preset::Mixed;
+                    self.reset();
+                }
+                Keycode::R => self.reset(),
+                Keycode::W => self.solver.warm_starting = !self.solver.warm_starting,
+                Keycode::C => self.show_contacts = !self.show_contacts,
+                Keycode::G => {
+                    let on = self.solver.gravity.1 != 0.0;
+                    self.solver.gravity = (0.0, if on { 0.0 } else { GRAVITY });
+                    self.solver.wake_all();
+                }
+                Keycode::Z => self.solver.sleep.enabled = !self.solver.sleep.enabled,
+                Keycode::S => self.shape = self.shape.next(),
+                Keycode::LeftBracket => {
+                    self.solver.iterations = self.solver.iterations.saturating_sub(1).max(1)
+                }
+                Keycode::RightBracket => self.solver.iterations += 1,
+                _ => {}
+            },
+            Event::MouseMotion { x, y, .. } => self.cursor = (x as f32, y as f32),
+            Event::MouseButtonDown {
+                mouse_btn: MouseButton::Left,
+                x,
+                y,
+                ..
+            } => {
+                let point = (x as f32, y as f32);
+                self.cursor = point;
+                self.grab = self.body_at(point).map(|i| {
+                    let body = &self.bodies[i];
+                    (i, rotate(sub(point, body.position), -body.angle))
+                });
+            }
+            Event::MouseButtonUp {
+                mouse_btn: MouseButton::Left,
+                ..
+            } => self.grab = None,
+            Event::MouseButtonDown {
+                mouse_btn: MouseButton::Right,
+                x,
+                y,
+                ..
+            } => {
+                let color = (
+                    self.rng.gen_range(120..255),
+                    self.rng.gen_range(120..255),
+                    self.rng.gen_range(120..255),
+                );
+                self.bodies.push(
+                    self.shape
+                        .make((x as f32, y as f32), BOX_SIZE)
+                        .with_color(color),
+                );
+            }
+            _ => {}
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.done
+    }
+}
@@ -0,0 +1,525 @@
+use crate::engine::{GlobalContext, Scene};
+use crate::models::force_field::{ForceFields, UniformGravity};
+use crate::models::layout::load_layout;
+use crate::models::obstacle::Obstacle;
+use crate::models::particle::Particle;
+use crate::models::particle_system::ParticleSystem;
+use crate::utils::collision::CollisionSolver;
+use crate::utils::spatial_hash::SpatialHash;
+use rayon::prelude::*;
+use sdl2::gfx::primitives::DrawRenderer;
+use sdl2::keyboard::Mod;
+use sdl2::mouse::MouseButton;
+use sdl2::{event::Event, keyboard::Keycode};
+use sdl2::{render::Canvas, video::Window};
+use std::f32::consts::PI;
+use std::path::Path;
+
+/// Kernel support radius h, in px. The grid uses it as its cell size, so every neighbour
+/// within h is in the 3×3 block of cells around a particle.
+const SMOOTHING: f32 = 16.0;
+/// Initial spacing of fluid particles; half the support radius gives about 12 neighbours.
+const SPACING: f32 = 8.0;
+const REST_DENSITY: f32 = 1.0;
+/// Radius used for wall and obstacle contacts and for drawing.
+const PARTICLE_RADIUS: f32 = 4.0;
+/// Fraction of normal velocity kept when fluid hits a wall or obstacle.
+const WALL_RESTITUTION: f32 = 0.1;
+/// Step in seconds, well inside the CFL limit 0.4 h / c for the default stiffness.
+const STEP: f32 = 1.0 / 500.0;
+/// Stiffest equation of state the step can take: the sound speed c = √stiffness must stay
+/// within the CFL limit 0.4 h / c ≥ `STEP`.
+const MAX_STIFFNESS: f32 = (0.4 * SMOOTHING / STEP) * (0.4 * SMOOTHING / STEP);
+const MAX_STEPS_PER_FRAME: usize = 16;
+/// Reach of the stirring brush and how fast it pulls fluid to the mouse velocity, in 1/s.
+const STIR_RADIUS: f32 = 60.0;
+const STIR_RATE: f32 = 30.0;
+const DROPLET_RADIUS: f32 = 40.0;
+/// Layout shared with the particle collision scene; only its obstacles are used here.
+const LAYOUT_FILE: &str = "layout.txt";
+
+/// 2D smoothing kernels with support `h`: poly6 for density, the gradient of spiky for
+/// pressure and the Laplacian of the viscosity kernel (Müller et al. 2003).
+struct Kernels {
+    h: f32,
+    h_sq: f32,
+    poly6: f32,
+    spiky_grad: f32,
+    viscosity_laplacian: f32,
+}
+
+impl Kernels {
+    fn new(h: f32) -> Self {
+        Kernels {
+            h,
+            h_sq: h * h,
+            poly6: 4.0 / (PI * h.powi(8)),
+            spiky_grad: -30.0 / (PI * h.powi(5)),
+            viscosity_laplacian: 40.0 / (PI * h.powi(5)),
+        }
+    }
+
+    fn poly6(&self, r_sq: f32) -> f32 {
+        let d = self.h_sq - r_sq;
+        self.poly6 * d * d * d
+    }
+
+    /// dW/dr of the spiky kernel, negative inside the support.
+    fn spiky_grad(&self, r: f32) -> f32 {
+        let d = self.h - r;
+        self.spiky_grad * d * d
+    }
+
+    fn viscosity_laplacian(&self, r: f32) -> f32 {
+        self.viscosity_laplacian * (self.h - r)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Preset {
+    DamBreak,
+    Droplet,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ColorMode {
+    Density,
+    Velocity,
+}
+
+/// Blue through cyan to white as `t` goes from 0 to 1.
+fn ramp(t: f32) -> (u8, u8, u8, u8) {
+    let t = t.clamp(0.0, 1.0);
+    if t < 0.5 {
+        let s = t * 2.0;
+        ((30.0 + 20.0 * s) as u8, (80.0 + 150.0 * s) as u8, 230, 255)
+    } else {
+        let s = (t - 0.5) * 2.0;
+        (
+            (50.0 + 205.0 * s) as u8,
+            (230.0 + 25.0 * s) as u8,
+            (230.0 + 25.0 * s) as u8,
+            255,
+        )
+    }
+}
+
+/// Weakly compressible SPH fluid. Neighbours come from the same uniform grid the collision
+/// scene uses, with the support radius as cell size, and walls and obstacles are handled by
+/// the collision solver's obstacle pass.
+pub struct SphFluid {
+    particles: ParticleSystem,
+    density: Vec<f32>,
+    pressure: Vec<f32>,
+    acceleration: Vec<(f32, f32)>,
+    hash: SpatialHash,
+    solver: CollisionSolver,
+    obstacles: Vec<Obstacle>,
+    obstacles_dirty: bool,
+    fields: ForceFields,
+    kernels: Kernels,
+    /// Particle mass chosen so a square lattice at `SPACING` sits at the rest density
+    mass: f32,
+    stiffness: f32,
+    viscosity: f32,
+    surface_tension: f32,
+    preset: Preset,
+    color_mode: ColorMode,
+    stirring: bool,
+    cursor: (f32, f32),
+    last_cursor: (f32, f32),
+    pending: f32,
+    status: String,
+    width: f32,
+    height: f32,
+    done: bool,
+}
+
+impl SphFluid {
+    pub fn new(ctx: &GlobalContext) -> Self {
+        let kernels = Kernels::new(SMOOTHING);
+        let mut scene = SphFluid {
+            particles: ParticleSystem::new(),
+            density: Vec::new(),
+            pressure: Vec::new(),
+            acceleration: Vec::new(),
+            hash: SpatialHash::new(SMOOTHING, ctx.screen_width, ctx.screen_height),
+            solver: CollisionSolver::new(
+                std::thread::available_parallelism()
+                    .map(|n| n.get())
+                    .unwrap_or(1),
+            ),
+            obstacles: Vec::new(),
+            obstacles_dirty: true,
+            fields: ForceFields::default(),
+            mass: Self::lattice_mass(&kernels),
+            kernels,
+            stiffness: 3.0e6,
+            viscosity: 200.0,
+            surface_tension: 2.0e4,
+            preset: Preset::DamBreak,
+            color_mode: ColorMode::Velocity,
+            stirring: false,
+            cursor: (0.0, 0.0),
+            last_cursor: (0.0, 0.0),
+            pending: 0.0,
+            status: String::new(),
+            width: ctx.screen_width as f32,
+            height: ctx.screen_height as f32,
+            done: false,
+        };
+        scene.reset();
+        scene
+    }
+
+    fn lattice_mass(kernels: &Kernels) -> f32 {
+        let reach = (SMOOTHING / SPACING).ceil() as i32;
+        let mut sum = 0.0;
+        for j in -reach..=reach {
+            for i in -reach..=reach {
+                let r_sq = ((i * i + j * j) as f32) * SPACING * SPACING;
+                if r_sq < kernels.h_sq {
+                    sum += kernels.poly6(r_sq);
+                }
+            }
+        }
+        REST_DENSITY / sum
+    }
+
+    fn reset(&mut self) {
+        self.particles.clear();
+        self.pending = 0.0;
+        let (w, h) = (self.width, self.height);
+        match self.preset {
+            Preset::DamBreak => {
+                // A column of water against the left wall, with a post in its path
+                self.fill_rect(SPACING, h * 0.5, w * 0.15, h - SPACING);
+                self.obstacles = vec![
+                    Obstacle::circle((w * 0.55, h - 140.0), 60.0, WALL_RESTITUTION, 0.0),
+                    Obstacle::segment((w * 0.75, h - 1.0), (w - 1.0, h - 220.0), 0.0, 0.0),
+                ];
+            }
+            Preset::Droplet => {
+                // A shallow pool and a drop falling into it
+                self.fill_rect(SPACING, h - 100.0, w - SPACING, h - SPACING);
+                self.fill_disc((w * 0.5, h * 0.3), 90.0);
+                self.obstacles.clear();
+            }
+        }
+        self.obstacles_dirty = true;
+    }
+
+    fn spawn(&mut self, x: f32, y: f32) {
+        self.particles.push(
+            Particle::new(x, y, 0.0, 0.0, PARTICLE_RADIUS)
+                .with_mass(self.mass)
+                .with_restitution(WALL_RESTITUTION),
+        );
+    }
+
+    fn fill_rect(&mut self, x0: f32, y0: f32, x1: f32, y1: f32) {
+        let mut y = y0;
+        while y <= y1 {
+            let mut x = x0;
+            while x <= x1 {
+                self.spawn(x, y);
+                x += SPACING;
+            }
+            y += SPACING;
+        }
+    }
+
+    fn fill_disc(&mut self, (cx, cy): (f32, f32), radius: f32) {
+        let steps = (radius / SPACING) as i32;
+        for j in -steps..=steps {
+            for i in -steps..=steps {
+                let (dx, dy) = (i as f32 * SPACING, j as f32 * SPACING);
+                if dx * dx + dy * dy <= radius * radius {
+                    self.spawn(cx + dx, cy + dy);
+                }
+            }
+        }
+    }
+
+    /// Density at every particle, then pressure from the linear equation of state. Pressure
+    /// is clamped at zero so sparse regions do not pull together; cohesion is left to the
+    /// surface tension term.
+    fn compute_density(&mut self) {
+        let (p, hash, kernels, mass) = (&self.particles, &self.hash, &self.kernels, self.mass);
+        let n = p.len();
+        self.density.resize(n, 0.0);
+        self.pressure.resize(n, 0.0);
+        let (stiffness, density) = (self.stiffness, &mut self.density);
+        density
+            .par_iter_mut()
+            .zip(self.pressure.par_iter_mut())
+            .enumerate()
+            .for_each(|(i, (rho, pressure))| {
+                let mut sum = 0.0;
+                hash.for_each_neighbour(p.x[i], p.y[i], |j| {
+                    let (dx, dy) = (p.x[i] - p.x[j], p.y[i] - p.y[j]);
+                    let r_sq = dx * dx + dy * dy;
+                    if r_sq < kernels.h_sq {
+                        sum += mass * kernels.poly6(r_sq);
+                    }
+                });
+                *rho = sum;
+                *pressure = (stiffness * (sum - REST_DENSITY)).max(0.0);
+            });
+    }
+
+    /// Pressure, viscosity and surface tension accelerations on every particle.
+    fn compute_forces(&mut self) {
+        let (p, hash, kernels, mass) = (&self.particles, &self.hash, &self.kernels, self.mass);
+        let (density, pressure) = (&self.density, &self.pressure);
+        let (viscosity, tension) = (self.viscosity, self.surface_tension);
+        self.acceleration.resize(p.len(), (0.0, 0.0));
+        self.acceleration
+            .par_iter_mut()
+            .enumerate()
+            .for_each(|(i, a)| {
+                let (xi, yi, rho_i) = (p.x[i], p.y[i], density[i]);
+                let pressure_term = pressure[i] / (rho_i * rho_i);
+                let (mut ax, mut ay) = (0.0, 0.0);
+                hash.for_each_neighbour(xi, yi, |j| {
+                    let (dx, dy) = (xi - p.x[j], yi - p.y[j]);
+                    let r_sq = dx * dx + dy * dy;
+                    if j == i || r_sq >= kernels.h_sq || r_sq < 1e-12 {
+                        return;
+                    }
+                    let r = r_sq.sqrt();
+                    let rho_j = density[j];
+
+                    // Symmetric pressure gradient, pushing i away from j
+                    let grad = kernels.spiky_grad(r) / r;
+                    let shared = pressure_term + pressure[j] / (rho_j * rho_j);
+                    ax -= mass * shared * grad * dx;
+                    ay -= mass * shared * grad * dy;
+
+                    let lap = viscosity * mass * kernels.viscosity_laplacian(r) / (rho_i * rho_j);
+                    ax += lap * (p.vx[j] - p.vx[i]);
+                    ay += lap * (p.vy[j] - p.vy[i]);
+
+                    // Pairwise cohesion (Becker and Teschner 2007)
+                    let pull = tension * kernels.poly6(r_sq);
+                    ax -= pull * dx;
+                    ay -= pull * dy;
+                });
+                *a = (ax, ay);
+            });
+    }
+
+    fn step(&mut self, mouse_velocity: (f32, f32), ctx: &GlobalContext) {
+        if self.obstacles_dirty {
+            self.hash.build_static(&self.obstacles);
+            self.obstacles_dirty = false;
+        }
+        self.hash.build(self.particles.positions());
+        self.compute_density();
+        self.compute_forces();
+
+        let p = &mut self.particles;
+        for (i, &(ax, ay)) in self.acceleration.iter().enumerate() {
+            p.vx[i] += ax * STEP;
+            p.vy[i] += ay * STEP;
+        }
+        if self.stirring {
+            let (cx, cy) = self.cursor;
+            let blend = (STIR_RATE * STEP).min(1.0);
+            for i in 0..p.len() {
+                let (dx, dy) = (p.x[i] - cx, p.y[i] - cy);
+                if dx * dx + dy * dy < STIR_RADIUS * STIR_RADIUS {
+                    p.vx[i] += (mouse_velocity.0 - p.vx[i]) * blend;
+                    p.vy[i] += (mouse_velocity.1 - p.vy[i]) * blend;
+                }
+            }
+        }
+        self.solver
+            .integrate(p, &self.fields, STEP, ctx.screen_width, ctx.screen_height);
+        self.solver
+            .solve_obstacles(&self.hash, &mut self.particles, &self.obstacles);
+    }
+
+    fn toggle_gravity(&mut self) {
+        if !self.fields.remove_named("gravity") {
+            self.fields.push(Box::new(UniformGravity::default()));
+        }
+    }
+
+    fn load_layout(&mut self) {
+        self.status = match load_layout(Path::new(LAYOUT_FILE)).map(|layout| layout.obstacles) {
+            Ok(obstacles) => {
+                self.obstacles = obstacles;
+                self.obstacles_dirty = true;
+                format!(
+                    "Loaded {} obstacles from {}",
+                    self.obstacles.len(),
+                    LAYOUT_FILE
+                )
+            }
+            Err(e) => format!("Could not load {}", e),
+        };
+    }
+
+    fn particle_color(&self, i: usize) -> (u8, u8, u8, u8) {
+        match self.color_mode {
+            ColorMode::Density => {
+                let rho = self.density.get(i).copied().unwrap_or(REST_DENSITY);
+                ramp((rho / REST_DENSITY - 0.7) / 0.5)
+            }
+            ColorMode::Velocity => {
+                let p = &self.particles;
+                ramp(p.vx[i].hypot(p.vy[i]) / 800.0)
+            }
+        }
+    }
+}
+
+impl Scene for SphFluid {
+    fn update(&mut self, ctx: &mut GlobalContext, dt: f32) {
+        let mouse_velocity = if dt > 0.0 {
+            (
+                (self.cursor.0 - self.last_cursor.0) / dt,
+                (self.cursor.1 - self.last_cursor.1) / dt,
+            )
+        } else {
+            (0.0, 0.0)
+        };
+        self.last_cursor = self.cursor;
+        if ctx.paused {
+            return;
+        }
+        self.pending += dt * ctx.simulation_speed.max(0.0);
+        let mut steps = 0;
+        while self.pending >= STEP && steps < MAX_STEPS_PER_FRAME {
+            self.step(mouse_velocity, ctx);
+            self.pending -= STEP;
+            steps += 1;
+        }
+        self.pending = self.pending.min(STEP);
+    }
+
+    fn render(&mut self, _ctx: &GlobalContext, canvas: &mut Canvas<Window>) {
+        for obstacle in &self.obstacles {
+            obstacle.render(canvas);
+        }
+        let p = &self.particles;
+        for i in 0..p.len() {
+            let color = self.particle_color(i);
+            let _ =
+                canvas.filled_circle(p.x[i] as i16, p.y[i] as i16, PARTICLE_RADIUS as i16, color);
+        }
+        if self.stirring {
+            let (x, y) = self.cursor;
+            let _ = canvas.circle(x as i16, y as i16, STIR_RADIUS as i16, (200, 200, 200, 255));
+        }
+
+        let (max_density, mean_density) = if self.density.is_empty() {
+            (0.0, 0.0)
+        } else {
+            (
+                self.density.iter().copied().fold(0.0, f32::max),
+                self.density.iter().sum::<f32>() / self.density.len() as f32,
+            )
+        };
+        let white = (255, 255, 255, 255);
+        let lines = [
+            format!(
+                "{} particles, density mean {:.3} max {:.3} (rest {:.1})",
+                p.len(),
+                mean_density,
+                max_density,
+                REST_DENSITY
+            ),
+            format!(
+                "Presets: 1 dam break, 2 droplet, R reset. Colour by {} (C), gravity {} (G)",
+                match self.color_mode {
+                    ColorMode::Density => "density",
+                    ColorMode::Velocity => "velocity",
+                },
+                if self.fields.contains("gravity") {
+                    "on"
+                } else {
+                    "off"
+                }
+            ),
+            format!(
+                "Stiffness {:.1e} (Up/Down), viscosity {:.0} ([ / ]), surface tension {:.1e} (, / .)",
+                self.stiffness, self.viscosity, self.surface_tension
+            ),
+            "Left drag: stir, right click: drop fluid, Ctrl+L: load obstacles, Delete: clear obstacles"
+                .to_string(),
+        ];
+        for (k, line) in lines.iter().enumerate() {
+            let _ = canvas.string(10, 10 + 15 * k as i16, line, white);
+        }
+        if !self.status.is_empty() {
+            let _ = canvas.string(10, 70, &self.status, white);
+        }
+    }
+
+    fn handle_event(&mut self, ctx: &mut GlobalContext, event: &Event) {
+        match *event {
+            Event::KeyDown {
+                keycode: Some(k),
+                keymod,
+                ..
+            } => match k {
+                Keycode::Escape => self.done = true,
+                Keycode::Space => ctx.paused = !ctx.paused,
+                Keycode::Left => ctx.simulation_speed -= 0.1,
+                Keycode::Right => ctx.simulation_speed += 0.1,
+                Keycode::Num1 => {
+                    self.preset = Preset::DamBreak;
+                    self.reset();
+                }
+                Keycode::Num2 => {
+                    self.preset = Preset::Droplet;
+                    self.reset();
+                }
+                Keycode::R => self.reset(),
+                Keycode::C => {
+                    self.color_mode = match self.color_mode {
+                        ColorMode::Density => ColorMode::Velocity,
+                        ColorMode::Velocity => ColorMode::Density,
+                    };
+                }
+                Keycode::G => self.toggle_gravity(),
+                Keycode::Up => self.stiffness = (self.stiffness * 1.5).min(MAX_STIFFNESS),
+                Keycode::Down => self.stiffness /= 1.5,
+                Keycode::LeftBracket => self.viscosity /= 1.5,
+                Keycode::RightBracket => self.viscosity *= 1.5,
+                Keycode::Comma => self.surface_tension /= 1.5,
+                Keycode::Period => self.surface_tension *= 1.5,
+                Keycode::L if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => {
+                    self.load_layout()
+                }
+                Keycode::Delete => {
+                    self.obstacles.clear();
+                    self.obstacles_dirty = true;
+                }
+                _ => {}
+            },
+            Event::MouseMotion { x, y, .. } => self.cursor = (x as f32, y as f32),
+            Event::MouseButtonDown {
+                mouse_btn: MouseButton::Left,
+                ..
+            } => self.stirring = true,
+            Event::MouseButtonUp {
+                mouse_btn: MouseButton::Left,
+                ..
+            } => self.stirring = false,
+            Event::MouseButtonDown {
+                mouse_btn: MouseButton::Right,
+                x,
+                y,
+                ..
+            } => self.fill_disc((x as f32, y as f32), DROPLET_RADIUS),
+            _ => {}
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.done
+    }
+}
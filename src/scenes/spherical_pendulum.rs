@@ -0,0 +1,217 @@
+use crate::engine::{GlobalContext, Scene};
+use crate::utils::camera::Camera;
+use crate::utils::RK4::rk4;
+use sdl2::gfx::primitives::DrawRenderer;
+use sdl2::pixels::Color;
+use sdl2::{event::Event, keyboard::Keycode};
+use sdl2::{render::Canvas, video::Window};
+
+const TRAIL_LENGTH: usize = 2000;
+
+/// Spherical pendulum integrated in Cartesian coordinates, which avoids the pole singularity of
+/// the spherical-angle form. State is `[x, y, z, vx, vy, vz]`, with y pointing down (towards
+/// gravity), x east and z north. The rod is kept at constant length by the tension term.
+pub struct SphericalPendulum {
+    solutions: Option<(Vec<f32>, Vec<Vec<f32>>)>,
+    current_index: usize,
+    length: f32,
+    gravity: f32,
+    /// Rotation rate of the reference frame (rad/s). Exaggerated so the precession is visible.
+    frame_rotation: f32,
+    latitude: f32,
+    foucault: bool,
+    camera: Camera,
+    done: bool,
+}
+
+impl SphericalPendulum {
+    pub fn new() -> Self {
+        let mut camera = Camera::new(10.0, 2000.0);
+        camera.rotation = (-0.5, 0.3);
+        let mut pendulum = SphericalPendulum {
+            solutions: None,
+            current_index: 0,
+            length: 2.0,
+            gravity: 9.8,
+            frame_rotation: 0.2,
+            latitude: 45f32.to_radians(),
+            foucault: false,
+            camera,
+            done: false,
+        };
+        pendulum.solve();
+        pendulum
+    }
+
+    /// Angular velocity of the frame expressed in local (east, down, north) coordinates.
+    fn omega(&self) -> (f32, f32, f32) {
+        if !self.foucault {
+            return (0.0, 0.0, 0.0);
+        }
+        (
+            0.0,
+            -self.frame_rotation * self.latitude.sin(),
+            self.frame_rotation * self.latitude.cos(),
+        )
+    }
+
+    fn derivatives(&self, _t: f32, state: &[f32]) -> Vec<f32> {
+        let (x, y, z) = (state[0], state[1], state[2]);
+        let (vx, vy, vz) = (state[3], state[4], state[5]);
+        let (ox, oy, oz) = self.omega();
+
+        // Gravity plus the Coriolis term -2 Ω × v
+        let fx = -2.0 * (oy * vz - oz * vy);
+        let fy = self.gravity - 2.0 * (oz * vx - ox * vz);
+        let fz = -2.0 * (ox * vy - oy * vx);
+
+        // Rod tension keeps r·r = L², which requires r·a + v·v = 0
+        let v_sq = vx * vx + vy * vy + vz * vz;
+        let tension = (x * fx + y * fy + z * fz + v_sq) / (self.length * self.length);
+
+        vec![
+            vx,
+            vy,
+            vz,
+            fx - tension * x,
+            fy - tension * y,
+            fz - tension * z,
+        ]
+    }
+
+    pub fn solve(&mut self) {
+        let theta0 = 30f32.to_radians();
+        let state0 = vec![
+            self.length * theta0.sin(),
+            self.length * theta0.cos(),
+            0.0,
+            0.0,
+            0.0,
+            0.3,
+        ];
+        let (a, b) = (0.0, 50.0);
+        let n: u32 = 10000;
+        let solutions = rk4(a, b, state0, |t, state| self.derivatives(t, state), n);
+        self.solutions = Some(solutions);
+        self.current_index = 0;
+    }
+}
+
+impl Scene for SphericalPendulum {
+    fn handle_event(&mut self, ctx: &mut GlobalContext, event: &Event) {
+        if self.camera.handle_event(event) {
+            return;
+        }
+        if let Event::KeyDown {
+            keycode: Some(k), ..
+        } = event
+        {
+            match k {
+                Keycode::Escape => {
+                    self.done = true;
+                }
+                Keycode::R => {
+                    self.current_index = 0;
+                }
+                Keycode::Left => {
+                    ctx.simulation_speed -= 0.1;
+                }
+                Keycode::Right => {
+                    ctx.simulation_speed += 0.1;
+                }
+                Keycode::F => {
+                    self.foucault = !self.foucault;
+                    self.solve();
+                }
+                Keycode::Up => {
+                    self.frame_rotation *= 1.5;
+                    self.solve();
+                }
+                Keycode::Down => {
+                    self.frame_rotation /= 1.5;
+                    self.solve();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn update(&mut self, ctx: &mut GlobalContext, _dt: f32) {
+        if self.solutions.is_none() {
+            self.solve();
+        }
+        if let Some((_, ref sol)) = self.solutions {
+            if (self.current_index + 1 + (ctx.simulation_speed * 10.) as usize) < sol.len() {
+                self.current_index += 1 + (ctx.simulation_speed * 10.) as usize;
+            } else {
+                self.current_index = sol.len() - 1
+            }
+        }
+    }
+
+    fn render(&mut self, _ctx: &GlobalContext, canvas: &mut Canvas<Window>) {
+        if let Some((_, ref solutions)) = self.solutions {
+            let (width, height) = canvas.output_size().unwrap_or((800, 600));
+
+            // Reference circle on the floor under the pivot
+            let mut last_projected = None;
+            for i in 0..=64 {
+                let angle = i as f32 / 64.0 * std::f32::consts::TAU;
+                let point = (
+                    self.length * angle.cos(),
+                    self.length,
+                    self.length * angle.sin(),
+                );
+                let projected = self.camera.to_screen(point, width, height);
+                if let Some(last) = last_projected {
+                    canvas.set_draw_color(Color::RGB(60, 60, 60));
+                    let _ = canvas.draw_line(last, projected);
+                }
+                last_projected = Some(projected);
+            }
+
+            let trail_start = self.current_index.saturating_sub(TRAIL_LENGTH);
+            let mut last_projected = None;
+            for (i, s) in solutions[trail_start..self.current_index]
+                .iter()
+                .enumerate()
+            {
+                let projected = self.camera.to_screen((s[0], s[1], s[2]), width, height);
+                let t = i as f32 / TRAIL_LENGTH as f32;
+                if let Some(last) = last_projected {
+                    canvas.set_draw_color(Color::RGB(
+                        (255.0 * (1.0 - t)) as u8,
+                        (255.0 * t) as u8,
+                        0,
+                    ));
+                    let _ = canvas.draw_line(last, projected);
+                }
+                last_projected = Some(projected);
+            }
+
+            let state = &solutions[self.current_index];
+            let pivot = self.camera.to_screen((0.0, 0.0, 0.0), width, height);
+            let bob = self
+                .camera
+                .to_screen((state[0], state[1], state[2]), width, height);
+            canvas.set_draw_color(Color::RGB(255, 255, 255));
+            let _ = canvas.draw_line(pivot, bob);
+            let _ = canvas.filled_circle(bob.0 as i16, bob.1 as i16, 20, Color::RGB(0, 255, 0));
+
+            let status = if self.foucault {
+                format!(
+                    "Foucault frame ON: {:.3} rad/s at {:.0} deg latitude (F to toggle, Up/Down rate)",
+                    self.frame_rotation,
+                    self.latitude.to_degrees()
+                )
+            } else {
+                "Foucault frame OFF (F to toggle)".to_string()
+            };
+            let _ = canvas.string(10, 10, &status, (255, 255, 255, 255));
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.done
+    }
+}
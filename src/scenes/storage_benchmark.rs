@@ -0,0 +1,231 @@
+use crate::engine::{GlobalContext, Scene};
+use crate::models::force_field::{ForceFields, GRAVITY};
+use crate::models::particle::{wall_bounce, Particle, TRACE_LIMIT};
+use crate::models::particle_system::ParticleSystem;
+use crate::utils::benchmark::BenchmarkQueue;
+use crate::utils::collision::CollisionSolver;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use sdl2::{event::Event, keyboard::Keycode};
+use sdl2::{render::Canvas, video::Window};
+use std::time::Instant;
+
+const PARTICLE_COUNTS: [usize; 2] = [10_000, 100_000];
+const STEPS_PER_RUN: usize = 60;
+const BENCH_DT: f32 = 1.0 / 60.0;
+const SEED: u64 = 7;
+
+#[derive(Clone, Copy)]
+enum Layout {
+    ArrayOfStructs,
+    StructOfArrays,
+    StructOfArraysParallel,
+}
+
+impl Layout {
+    const ALL: [Layout; 3] = [
+        Layout::ArrayOfStructs,
+        Layout::StructOfArrays,
+        Layout::StructOfArraysParallel,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Layout::ArrayOfStructs => "Vec<struct> (per-particle traces)",
+            Layout::StructOfArrays => "ParticleSystem (1 thread)",
+            Layout::StructOfArraysParallel => "ParticleSystem (thread pool)",
+        }
+    }
+}
+
+/// The array-of-structs layout `ParticleCollisionScene` used before `ParticleSystem`: each
+/// particle owns its trail and drops the oldest point with `remove(0)` every step.
+struct LegacyParticle {
+    p: Particle,
+    traces: Vec<(f32, f32)>,
+}
+
+impl LegacyParticle {
+    fn update(&mut self, dt: f32, screen_w: u32, screen_h: u32) {
+        let p = &mut self.p;
+        p.vy += GRAVITY * dt;
+
+        p.x += p.vx * dt;
+        p.y += p.vy * dt;
+
+        let r = p.radius;
+        if p.x - r < 0.0 {
+            p.x = r;
+            (p.vx, p.vy) = wall_bounce(p.vx, p.vy, p.restitution, p.friction);
+        } else if p.x + r > screen_w as f32 {
+            p.x = (screen_w as f32) - r;
+            (p.vx, p.vy) = wall_bounce(p.vx, p.vy, p.restitution, p.friction);
+        }
+        if p.y - r < 0.0 {
+            p.y = r;
+            (p.vy, p.vx) = wall_bounce(p.vy, p.vx, p.restitution, p.friction);
+        } else if p.y + r > screen_h as f32 {
+            p.y = (screen_h as f32) - r;
+            (p.vy, p.vx) = wall_bounce(p.vy, p.vx, p.restitution, p.friction);
+        }
+
+        self.traces.push((p.x, p.y));
+        if self.traces.len() > TRACE_LIMIT {
+            self.traces.remove(0);
+        }
+    }
+}
+
+struct BenchResult {
+    layout: Layout,
+    count: usize,
+    ms_per_step: f64,
+}
+
+/// Times one integration step (gravity, motion, wall bounces and trails) for the old
+/// array-of-structs particles and the structure-of-arrays `ParticleSystem`, on one thread and
+/// on the worker pool, at two particle counts. Each row gives the speed-up over the
+/// array-of-structs run with the same count.
+pub struct StorageBenchmark {
+    queue: BenchmarkQueue<(Layout, usize), BenchResult>,
+    setup: Setup,
+    done: bool,
+}
+
+/// What every run starts from.
+struct Setup {
+    solver: CollisionSolver,
+    fields: ForceFields,
+    width: u32,
+    height: u32,
+}
+
+impl StorageBenchmark {
+    pub fn new(ctx: &GlobalContext) -> Self {
+        let threads = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1);
+        let runs = PARTICLE_COUNTS
+            .into_iter()
+            .flat_map(|count| Layout::ALL.into_iter().map(move |layout| (layout, count)));
+        StorageBenchmark {
+            queue: BenchmarkQueue::new(runs),
+            setup: Setup {
+                solver: CollisionSolver::new(threads),
+                fields: ForceFields::default(),
+                width: ctx.screen_width,
+                height: ctx.screen_height,
+            },
+            done: false,
+        }
+    }
+}
+
+impl Setup {
+    /// Same random population for every layout.
+    fn population(&self, count: usize) -> Vec<Particle> {
+        let mut rng = StdRng::seed_from_u64(SEED);
+        (0..count)
+            .map(|_| {
+                Particle::new(
+                    rng.gen_range(0.0..self.width as f32),
+                    rng.gen_range(0.0..self.height as f32),
+                    rng.gen_range(-150.0..150.0),
+                    rng.gen_range(-150.0..150.0),
+                    rng.gen_range(2.0..6.0),
+                )
+            })
+            .collect()
+    }
+
+    fn run(&self, layout: Layout, count: usize) -> f64 {
+        let (w, h) = (self.width, self.height);
+        match layout {
+            Layout::ArrayOfStructs => {
+                let mut particles: Vec<LegacyParticle> = self
+                    .population(count)
+                    .into_iter()
+                    .map(|p| LegacyParticle {
+                        traces: vec![(p.x, p.y)],
+                        p,
+                    })
+                    .collect();
+                let start = Instant::now();
+                for _ in 0..STEPS_PER_RUN {
+                    for p in &mut particles {
+                        p.update(BENCH_DT, w, h);
+                    }
+                }
+                start.elapsed().as_secs_f64()
+            }
+            Layout::StructOfArrays | Layout::StructOfArraysParallel => {
+                let mut system = ParticleSystem::new();
+                for p in self.population(count) {
+                    system.push(p);
+                }
+                let start = Instant::now();
+                for _ in 0..STEPS_PER_RUN {
+                    if let Layout::StructOfArraysParallel = layout {
+                        self.solver
+                            .integrate(&mut system, &self.fields, BENCH_DT, w, h);
+                    } else {
+                        system.integrate(&self.fields, BENCH_DT, w, h);
+                    }
+                    system.update_trails(true);
+                }
+                start.elapsed().as_secs_f64()
+            }
+        }
+    }
+}
+
+impl Scene for StorageBenchmark {
+    fn handle_event(&mut self, _ctx: &mut GlobalContext, event: &Event) {
+        if let Event::KeyDown {
+            keycode: Some(k), ..
+        } = event
+        {
+            match k {
+                Keycode::Escape => self.done = true,
+                _ => self.queue.handle_key(*k),
+            }
+        }
+    }
+
+    fn update(&mut self, _ctx: &mut GlobalContext, _dt: f32) {
+        let setup = &self.setup;
+        self.queue.update(|(layout, count)| BenchResult {
+            layout,
+            count,
+            ms_per_step: setup.run(layout, count) * 1000.0 / STEPS_PER_RUN as f64,
+        });
+    }
+
+    fn render(&mut self, _ctx: &GlobalContext, canvas: &mut Canvas<Window>) {
+        let title = format!(
+            "Integration + trails, {} steps per run, {} worker threads",
+            STEPS_PER_RUN,
+            self.setup.solver.threads()
+        );
+        let results = self.queue.results();
+        self.queue.render(canvas, &title, |result| {
+            // Speed-up relative to the array-of-structs run with the same particle count
+            let baseline = results
+                .iter()
+                .find(|r| r.count == result.count && matches!(r.layout, Layout::ArrayOfStructs))
+                .map(|r| r.ms_per_step)
+                .unwrap_or(result.ms_per_step);
+            format!(
+                "{:>7} particles  {:<38} {:>8.3} ms/step  x{:.1}",
+                result.count,
+                result.layout.label(),
+                result.ms_per_step,
+                baseline / result.ms_per_step.max(1e-9)
+            )
+        });
+    }
+
+    fn is_done(&self) -> bool {
+        self.done
+    }
+}
@@ -76,57 +76,38 @@ where
 
     for i in 1..=n {
         let ti = a + i as f32 * h;
-        let w_prev = &w[i as usize - 1];
-        let w_prev_array: [f32; 3] = [w_prev[0], w_prev[1], w_prev[2]];
+        let w_next = rk4_step(ti, h, &w[i as usize - 1], &f);
+        w.push(w_next);
+        t.push(ti)
+    }
 
-        let k1: Vec<f32> = f(ti, &w_prev_array).iter().map(|&val| h * val).collect();
-        let k2: Vec<f32> = f(
-            ti + h / 2.0,
-            &[
-                w_prev_array[0] + k1[0] / 2.0,
-                w_prev_array[1] + k1[1] / 2.0,
-                w_prev_array[2] + k1[2] / 2.0,
-            ],
-        )
+    (t, w)
+}
+
+/// Advances the state `w` by a single RK4 step of size `h`, for systems of any dimension.
+pub fn rk4_step<F>(t: f32, h: f32, w: &[f32], f: F) -> Vec<f32>
+where
+    F: Fn(f32, &[f32]) -> Vec<f32>,
+{
+    let offset = |k: &[f32], scale: f32| -> Vec<f32> {
+        w.iter().zip(k).map(|(&w_i, &k_i)| w_i + k_i * scale).collect()
+    };
+
+    let k1: Vec<f32> = f(t, w).iter().map(|&val| h * val).collect();
+    let k2: Vec<f32> = f(t + h / 2.0, &offset(&k1, 0.5))
         .iter()
         .map(|&val| h * val)
         .collect();
-        let k3: Vec<f32> = f(
-            ti + h / 2.0,
-            &[
-                w_prev_array[0] + k2[0] / 2.0,
-                w_prev_array[1] + k2[1] / 2.0,
-                w_prev_array[2] + k2[2] / 2.0,
-            ],
-        )
+    let k3: Vec<f32> = f(t + h / 2.0, &offset(&k2, 0.5))
         .iter()
         .map(|&val| h * val)
         .collect();
-        let k4: Vec<f32> = f(
-            ti + h,
-            &[
-                w_prev_array[0] + k3[0],
-                w_prev_array[1] + k3[1],
-                w_prev_array[2] + k3[2],
-            ],
-        )
+    let k4: Vec<f32> = f(t + h, &offset(&k3, 1.0))
         .iter()
         .map(|&val| h * val)
         .collect();
 
-        let w_next = (0..w_prev.len())
-            .map(|i| {
-                let w_i = w_prev[i];
-                let k1_i = k1[i];
-                let k2_i = k2[i];
-                let k3_i = k3[i];
-                let k4_i = k4[i];
-                w_i + (k1_i + 2.0 * k2_i + 2.0 * k3_i + k4_i) / 6.0
-            })
-            .collect();
-        w.push(w_next);
-        t.push(ti)
-    }
-
-    (t, w)
+    (0..w.len())
+        .map(|i| w[i] + (k1[i] + 2.0 * k2[i] + 2.0 * k3[i] + k4[i]) / 6.0)
+        .collect()
 }
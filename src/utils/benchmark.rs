@@ -0,0 +1,62 @@
+use sdl2::gfx::primitives::DrawRenderer;
+use sdl2::keyboard::Keycode;
+use sdl2::render::{Canvas, RenderTarget};
+
+/// Runs of a benchmark scene and their results so far.
+///
+/// `update` executes one run per call, so a scene calling it once per frame stays responsive
+/// while the table fills in; R starts over.
+pub struct BenchmarkQueue<R, T> {
+    runs: Vec<R>,
+    results: Vec<T>,
+}
+
+impl<R: Copy, T> BenchmarkQueue<R, T> {
+    pub fn new(runs: impl IntoIterator<Item = R>) -> Self {
+        BenchmarkQueue {
+            runs: runs.into_iter().collect(),
+            results: Vec::new(),
+        }
+    }
+
+    /// Results of the runs done so far, in the order of the runs.
+    pub fn results(&self) -> &[T] {
+        &self.results
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.results.len() < self.runs.len()
+    }
+
+    /// Executes the next run, if any is left.
+    pub fn update(&mut self, run: impl FnOnce(R) -> T) {
+        if let Some(&next) = self.runs.get(self.results.len()) {
+            self.results.push(run(next));
+        }
+    }
+
+    /// R drops the results and starts over.
+    pub fn handle_key(&mut self, key: Keycode) {
+        if key == Keycode::R {
+            self.results.clear();
+        }
+    }
+
+    /// Draws `title` above one line per result.
+    pub fn render<C, F>(&self, canvas: &mut Canvas<C>, title: &str, row: F)
+    where
+        C: RenderTarget,
+        F: Fn(&T) -> String,
+    {
+        let white = (255, 255, 255, 255);
+        let _ = canvas.string(10, 10, &format!("{} (R to rerun)", title), white);
+        let mut y = 40;
+        for result in &self.results {
+            let _ = canvas.string(10, y, &row(result), white);
+            y += 15;
+        }
+        if self.is_running() {
+            let _ = canvas.string(10, y + 10, "Running...", white);
+        }
+    }
+}
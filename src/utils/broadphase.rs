@@ -0,0 +1,784 @@
+use crate::models::particle_system::ParticleSystem;
+use crate::utils::spatial_hash::SpatialHash;
+
+/// Smallest cell the grid broadphase uses, so that a swarm of tiny particles does not blow up
+/// the number of cells.
+const MIN_CELL_SIZE: f32 = 4.0;
+/// Distance, in px, by which the tree's leaf boxes exceed their particles, so that a particle
+/// only has to be reinserted once it has moved this far.
+const TREE_MARGIN: f32 = 1.0;
+
+/// Axis-aligned bounding box.
+#[derive(Clone, Copy, Debug)]
+pub struct Aabb {
+    pub min_x: f32,
+    pub min_y: f32,
+    pub max_x: f32,
+    pub max_y: f32,
+}
+
+impl Aabb {
+    pub fn around(x: f32, y: f32, radius: f32) -> Self {
+        Aabb {
+            min_x: x - radius,
+            min_y: y - radius,
+            max_x: x + radius,
+            max_y: y + radius,
+        }
+    }
+
+    pub fn overlaps(&self, other: &Aabb) -> bool {
+        self.min_x <= other.max_x
+            && other.min_x <= self.max_x
+            && self.min_y <= other.max_y
+            && other.min_y <= self.max_y
+    }
+
+    pub fn contains(&self, other: &Aabb) -> bool {
+        self.min_x <= other.min_x
+            && self.min_y <= other.min_y
+            && other.max_x <= self.max_x
+            && other.max_y <= self.max_y
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min_x: self.min_x.min(other.min_x),
+            min_y: self.min_y.min(other.min_y),
+            max_x: self.max_x.max(other.max_x),
+            max_y: self.max_y.max(other.max_y),
+        }
+    }
+
+    pub fn expanded(&self, margin: f32) -> Aabb {
+        Aabb {
+            min_x: self.min_x - margin,
+            min_y: self.min_y - margin,
+            max_x: self.max_x + margin,
+            max_y: self.max_y + margin,
+        }
+    }
+
+    pub fn perimeter(&self) -> f32 {
+        2.0 * ((self.max_x - self.min_x) + (self.max_y - self.min_y))
+    }
+
+    fn shifted(&self, dx: f32, dy: f32) -> Aabb {
+        Aabb {
+            min_x: self.min_x + dx,
+            min_y: self.min_y + dy,
+            max_x: self.max_x + dx,
+            max_y: self.max_y + dy,
+        }
+    }
+}
+
+/// Finds the pairs of boxes that may overlap, so that the narrow phase only tests those.
+pub trait Broadphase {
+    /// Collects candidate pairs as indices into `boxes`. Every overlapping pair must be among
+    /// them; each unordered pair appears once and no box is paired with itself. Most boxes
+    /// keep their index from one call to the next, which lets implementations reuse what they
+    /// learnt in the previous frame, but any index may refer to a different box.
+    fn find_pairs(&mut self, boxes: &[Aabb], out: &mut Vec<(u32, u32)>);
+}
+
+/// The broadphases the particle scenes can switch between.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BroadphaseKind {
+    Grid,
+    SortAndSweep,
+    Tree,
+}
+
+impl BroadphaseKind {
+    pub const ALL: [BroadphaseKind; 3] = [
+        BroadphaseKind::Grid,
+        BroadphaseKind::SortAndSweep,
+        BroadphaseKind::Tree,
+    ];
+
+    pub fn next(self) -> Self {
+        match self {
+            BroadphaseKind::Grid => BroadphaseKind::SortAndSweep,
+            BroadphaseKind::SortAndSweep => BroadphaseKind::Tree,
+            BroadphaseKind::Tree => BroadphaseKind::Grid,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            BroadphaseKind::Grid => "grid",
+            BroadphaseKind::SortAndSweep => "sort and sweep",
+            BroadphaseKind::Tree => "AABB tree",
+        }
+    }
+
+    pub fn create(self) -> Box<dyn Broadphase> {
+        match self {
+            BroadphaseKind::Grid => Box::new(GridBroadphase::new()),
+            BroadphaseKind::SortAndSweep => Box::new(SortAndSweep::new()),
+            BroadphaseKind::Tree => Box::new(DynamicTree::new(TREE_MARGIN)),
+        }
+    }
+}
+
+/// Uniform grid with cells as wide as the largest box, so that overlapping boxes are always
+/// in the same or adjacent cells. One large box makes every cell large, and a dense cluster
+/// puts many boxes in one cell; either way most candidates are far apart.
+pub struct GridBroadphase {
+    hash: SpatialHash,
+    centres: Vec<(f32, f32)>,
+}
+
+impl GridBroadphase {
+    pub fn new() -> Self {
+        GridBroadphase {
+            hash: SpatialHash::new(MIN_CELL_SIZE, 1, 1),
+            centres: Vec::new(),
+        }
+    }
+}
+
+impl Broadphase for GridBroadphase {
+    fn find_pairs(&mut self, boxes: &[Aabb], out: &mut Vec<(u32, u32)>) {
+        out.clear();
+        let Some(first) = boxes.first() else {
+            return;
+        };
+        let bounds = boxes.iter().fold(*first, |b, other| b.union(other));
+        let cell_size = boxes
+            .iter()
+            .map(|b| (b.max_x - b.min_x).max(b.max_y - b.min_y))
+            .fold(MIN_CELL_SIZE, f32::max);
+        self.hash.resize_area(
+            cell_size,
+            bounds.max_x - bounds.min_x,
+            bounds.max_y - bounds.min_y,
+        );
+        self.centres.clear();
+        self.centres.extend(boxes.iter().map(|b| {
+            (
+                0.5 * (b.min_x + b.max_x) - bounds.min_x,
+                0.5 * (b.min_y + b.max_y) - bounds.min_y,
+            )
+        }));
+        self.hash.build(self.centres.iter().copied());
+        self.hash.pairs(out);
+    }
+}
+
+/// Sort and sweep: boxes are sorted by their lower edge along the axis on which their
+/// centres spread most, then each box is paired with those that start before it ends.
+///
+/// The order is kept from frame to frame. Boxes move little between frames, so it is nearly
+/// sorted already, and the adaptive merge sort of `sort_by` finishes in close to linear time.
+pub struct SortAndSweep {
+    /// Lower edge along the sweep axis and index of each box, in sweep order
+    order: Vec<(f32, u32)>,
+    /// The boxes in sweep order, so that the sweep reads memory in sequence
+    sorted: Vec<(Aabb, u32)>,
+}
+
+impl SortAndSweep {
+    pub fn new() -> Self {
+        SortAndSweep {
+            order: Vec::new(),
+            sorted: Vec::new(),
+        }
+    }
+
+    /// Whether the centres spread more along y than along x.
+    fn sweep_along_y(boxes: &[Aabb]) -> bool {
+        let n = boxes.len() as f32;
+        let (mut sum, mut sum_sq) = ([0.0f32; 2], [0.0f32; 2]);
+        for b in boxes {
+            let centre = [0.5 * (b.min_x + b.max_x), 0.5 * (b.min_y + b.max_y)];
+            for axis in 0..2 {
+                sum[axis] += centre[axis];
+                sum_sq[axis] += centre[axis] * centre[axis];
+            }
+        }
+        let variance = |axis: usize| sum_sq[axis] / n - (sum[axis] / n).powi(2);
+        variance(1) > variance(0)
+    }
+}
+
+impl Broadphase for SortAndSweep {
+    fn find_pairs(&mut self, boxes: &[Aabb], out: &mut Vec<(u32, u32)>) {
+        out.clear();
+        // Boxes past the end are gone and new ones start out at the end of the order
+        let count = boxes.len() as u32;
+        self.order.retain(|&(_, i)| i < count);
+        let known = self.order.len() as u32;
+        self.order.extend((known..count).map(|i| (0.0, i)));
+        // Lower and upper edge along the sweep axis
+        let span: fn(&Aabb) -> (f32, f32) = if Self::sweep_along_y(boxes) {
+            |b| (b.min_y, b.max_y)
+        } else {
+            |b| (b.min_x, b.max_x)
+        };
+        for (start, i) in self.order.iter_mut() {
+            *start = span(&boxes[*i as usize]).0;
+        }
+        self.order.sort_by(|a, b| a.0.total_cmp(&b.0));
+        self.sorted.clear();
+        self.sorted
+            .extend(self.order.iter().map(|&(_, i)| (boxes[i as usize], i)));
+
+        for (k, (a, i)) in self.sorted.iter().enumerate() {
+            let end = span(a).1;
+            for (b, j) in &self.sorted[k + 1..] {
+                if span(b).0 > end {
+                    break;
+                }
+                if a.overlaps(b) {
+                    out.push((*i, *j));
+                }
+            }
+        }
+    }
+}
+
+const NULL: u32 = u32::MAX;
+
+struct Node {
+    /// Exactly the union of the children's boxes, or the enlarged box of a leaf
+    aabb: Aabb,
+    parent: u32,
+    /// Children, `NULL` for a leaf
+    left: u32,
+    right: u32,
+    /// Longest path down to a leaf, 0 for a leaf
+    height: u32,
+    /// Box index of a leaf
+    item: u32,
+}
+
+impl Node {
+    fn is_leaf(&self) -> bool {
+        self.left == NULL
+    }
+}
+
+/// Dynamic bounding volume tree, as in Box2D: a binary tree whose leaves hold the boxes
+/// enlarged by a margin and whose inner nodes bound their children.
+///
+/// A leaf is only moved when its box leaves the enlarged one, by removing it and inserting it
+/// again where it adds the least perimeter to the tree, and rotations on the way back up keep
+/// the tree balanced. Whole subtrees far from each other are skipped when pairing, so neither
+/// large boxes nor clusters cost more than the overlaps they actually have.
+pub struct DynamicTree {
+    nodes: Vec<Node>,
+    free: Vec<u32>,
+    root: u32,
+    /// Leaf node of each box
+    leaves: Vec<u32>,
+    margin: f32,
+    /// Pairs of nodes still to be tested; a node paired with itself stands for all pairs
+    /// within its subtree
+    stack: Vec<(u32, u32)>,
+}
+
+impl DynamicTree {
+    pub fn new(margin: f32) -> Self {
+        DynamicTree {
+            nodes: Vec::new(),
+            free: Vec::new(),
+            root: NULL,
+            leaves: Vec::new(),
+            margin,
+            stack: Vec::new(),
+        }
+    }
+
+    fn allocate(&mut self, aabb: Aabb, item: u32) -> u32 {
+        let node = Node {
+            aabb,
+            parent: NULL,
+            left: NULL,
+            right: NULL,
+            height: 0,
+            item,
+        };
+        if let Some(id) = self.free.pop() {
+            self.nodes[id as usize] = node;
+            id
+        } else {
+            self.nodes.push(node);
+            (self.nodes.len() - 1) as u32
+        }
+    }
+
+    /// Recomputes the box and height of an inner node from its children.
+    fn fit(&mut self, id: u32) {
+        let node = &self.nodes[id as usize];
+        let (left, right) = (
+            &self.nodes[node.left as usize],
+            &self.nodes[node.right as usize],
+        );
+        let (aabb, height) = (
+            left.aabb.union(&right.aabb),
+            1 + left.height.max(right.height),
+        );
+        let node = &mut self.nodes[id as usize];
+        node.aabb = aabb;
+        node.height = height;
+    }
+
+    /// Points whatever pointed at `old`, its parent or the root, at `new`.
+    fn replace_child(&mut self, parent: u32, old: u32, new: u32) {
+        if parent == NULL {
+            self.root = new;
+            return;
+        }
+        let node = &mut self.nodes[parent as usize];
+        if node.left == old {
+            node.left = new;
+        } else {
+            node.right = new;
+        }
+    }
+
+    /// Balances and refits the nodes from `id` up to the root.
+    fn refit(&mut self, mut id: u32) {
+        while id != NULL {
+            id = self.balance(id);
+            self.fit(id);
+            id = self.nodes[id as usize].parent;
+        }
+    }
+
+    /// If one child of `a` is more than one level taller than the other, lifts it into the
+    /// place of `a`, as in an AVL tree. The taller grandchild stays under it and the shorter
+    /// one goes to `a`. Returns the node now in the place of `a`.
+    fn balance(&mut self, a: u32) -> u32 {
+        let node = &self.nodes[a as usize];
+        if node.height < 2 {
+            return a;
+        }
+        let (left, right) = (node.left, node.right);
+        let height = |id: u32| self.nodes[id as usize].height as i64;
+        let (up, up_is_right) = match height(right) - height(left) {
+            lean if lean > 1 => (right, true),
+            lean if lean < -1 => (left, false),
+            _ => return a,
+        };
+        let (f, g) = (self.nodes[up as usize].left, self.nodes[up as usize].right);
+        let (keep, give) = if height(f) > height(g) {
+            (f, g)
+        } else {
+            (g, f)
+        };
+
+        let parent = self.nodes[a as usize].parent;
+        self.replace_child(parent, a, up);
+        let lifted = &mut self.nodes[up as usize];
+        lifted.parent = parent;
+        lifted.left = a;
+        lifted.right = keep;
+        let lowered = &mut self.nodes[a as usize];
+        lowered.parent = up;
+        if up_is_right {
+            lowered.right = give;
+        } else {
+            lowered.left = give;
+        }
+        self.nodes[give as usize].parent = a;
+        self.fit(a);
+        self.fit(up);
+        up
+    }
+
+    fn insert(&mut self, leaf: u32) {
+        if self.root == NULL {
+            self.root = leaf;
+            self.nodes[leaf as usize].parent = NULL;
+            return;
+        }
+
+        // Walk down to the sibling that makes the tree grow least: the cost of pairing with a
+        // node is the perimeter of the new parent, plus what every ancestor grows by
+        let aabb = self.nodes[leaf as usize].aabb;
+        let mut sibling = self.root;
+        while !self.nodes[sibling as usize].is_leaf() {
+            let node = &self.nodes[sibling as usize];
+            let perimeter = node.aabb.perimeter();
+            let combined = node.aabb.union(&aabb).perimeter();
+            // Pairing with this node makes a new parent; descending pushes its box up
+            let cost = 2.0 * combined;
+            let inherited = 2.0 * (combined - perimeter);
+            let child_cost = |child: u32| {
+                let child = &self.nodes[child as usize];
+                let grown = child.aabb.union(&aabb).perimeter();
+                if child.is_leaf() {
+                    grown + inherited
+                } else {
+                    grown - child.aabb.perimeter() + inherited
+                }
+            };
+            let (left, right) = (node.left, node.right);
+            let (cost_left, cost_right) = (child_cost(left), child_cost(right));
+            if cost < cost_left && cost < cost_right {
+                break;
+            }
+            sibling = if cost_left < cost_right { left } else { right };
+        }
+
+        let old_parent = self.nodes[sibling as usize].parent;
+        let union = self.nodes[sibling as usize].aabb.union(&aabb);
+        let parent = self.allocate(union, NULL);
+        let node = &mut self.nodes[parent as usize];
+        node.parent = old_parent;
+        node.left = sibling;
+        node.right = leaf;
+        self.nodes[sibling as usize].parent = parent;
+        self.nodes[leaf as usize].parent = parent;
+        self.replace_child(old_parent, sibling, parent);
+        self.refit(parent);
+    }
+
+    /// Unlinks a leaf, putting its sibling in place of their parent.
+    fn remove(&mut self, leaf: u32) {
+        if leaf == self.root {
+            self.root = NULL;
+            return;
+        }
+        let parent = self.nodes[leaf as usize].parent;
+        let node = &self.nodes[parent as usize];
+        let grandparent = node.parent;
+        let sibling = if node.left == leaf {
+            node.right
+        } else {
+            node.left
+        };
+        self.free.push(parent);
+        self.nodes[sibling as usize].parent = grandparent;
+        self.replace_child(grandparent, parent, sibling);
+        self.refit(grandparent);
+    }
+
+    /// Drops the leaves of boxes past the end, moves the leaves whose boxes left their
+    /// enlarged box and adds leaves for new boxes.
+    fn update(&mut self, boxes: &[Aabb]) {
+        while self.leaves.len() > boxes.len() {
+            if let Some(leaf) = self.leaves.pop() {
+                self.remove(leaf);
+                self.free.push(leaf);
+            }
+        }
+        for (i, b) in boxes.iter().enumerate().skip(self.leaves.len()) {
+            let leaf = self.allocate(b.expanded(self.margin), i as u32);
+            self.leaves.push(leaf);
+            self.insert(leaf);
+        }
+        for (i, b) in boxes.iter().enumerate() {
+            let leaf = self.leaves[i];
+            if self.nodes[leaf as usize].aabb.contains(b) {
+                continue;
+            }
+            self.remove(leaf);
+            self.nodes[leaf as usize].aabb = b.expanded(self.margin);
+            self.insert(leaf);
+        }
+    }
+}
+
+impl Broadphase for DynamicTree {
+    /// Walks the tree against itself: two subtrees are only opened if their boxes overlap,
+    /// so every pair of leaves is met at most once and far apart regions are never compared.
+    fn find_pairs(&mut self, boxes: &[Aabb], out: &mut Vec<(u32, u32)>) {
+        out.clear();
+        self.update(boxes);
+        if self.root == NULL {
+            return;
+        }
+        self.stack.clear();
+        self.stack.push((self.root, self.root));
+        while let Some((a, b)) = self.stack.pop() {
+            let (na, nb) = (&self.nodes[a as usize], &self.nodes[b as usize]);
+            if a == b {
+                if !na.is_leaf() {
+                    let (left, right) = (na.left, na.right);
+                    self.stack
+                        .extend([(left, left), (right, right), (left, right)]);
+                }
+                continue;
+            }
+            if !na.aabb.overlaps(&nb.aabb) {
+                continue;
+            }
+            // Open the larger of the two, or the one that is not a leaf
+            let open_a = match (na.is_leaf(), nb.is_leaf()) {
+                (true, true) => {
+                    let (i, j) = (na.item, nb.item);
+                    if boxes[i as usize].overlaps(&boxes[j as usize]) {
+                        out.push((i.min(j), i.max(j)));
+                    }
+                    continue;
+                }
+                (leaf_a, leaf_b) => leaf_b || !leaf_a && na.aabb.perimeter() >= nb.aabb.perimeter(),
+            };
+            if open_a {
+                self.stack.extend([(na.left, b), (na.right, b)]);
+            } else {
+                self.stack.extend([(a, nb.left), (a, nb.right)]);
+            }
+        }
+    }
+}
+
+/// Feeds particles to a broadphase as boxes. Along periodic axes a particle that sticks out
+/// past an edge gets a second box shifted by one period, so that it meets its neighbours
+/// across the seam, and pairs of images are mapped back to the particles.
+#[derive(Default)]
+pub struct ParticlePairs {
+    boxes: Vec<Aabb>,
+    /// Particle of each box
+    owner: Vec<u32>,
+    pairs: Vec<(u32, u32)>,
+}
+
+impl ParticlePairs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Collects the candidate pairs of particles in `system`, lower index first, in a box
+    /// `area` wide and high whose axes wrap around where `periodic` says so.
+    pub fn find(
+        &mut self,
+        broadphase: &mut dyn Broadphase,
+        system: &ParticleSystem,
+        area: (f32, f32),
+        periodic: (bool, bool),
+        out: &mut Vec<(u32, u32)>,
+    ) {
+        self.boxes.clear();
+        self.owner.clear();
+        let particles = system.positions().zip(&system.radius).enumerate();
+        for (i, ((x, y), &radius)) in particles {
+            self.boxes.push(Aabb::around(x, y, radius));
+            self.owner.push(i as u32);
+        }
+        let images = (periodic.0 || periodic.1) && self.add_images(area, periodic);
+
+        broadphase.find_pairs(&self.boxes, &mut self.pairs);
+        out.clear();
+        let owner = &self.owner;
+        out.extend(self.pairs.iter().filter_map(|&(a, b)| {
+            let (i, j) = (owner[a as usize], owner[b as usize]);
+            (i != j).then(|| (i.min(j), i.max(j)))
+        }));
+        if images {
+            // A pair near a seam is found both directly and through the images
+            out.sort_unstable();
+            out.dedup();
+        }
+    }
+
+    /// Adds the shifted boxes of the particles reaching a periodic edge. Returns
+    /// whether there were any.
+    fn add_images(&mut self, (width, height): (f32, f32), periodic: (bool, bool)) -> bool {
+        let originals = self.boxes.len();
+        for i in 0..originals {
+            let b = self.boxes[i];
+            let shift = |wraps: bool, min: f32, max: f32, period: f32| match wraps {
+                true if min <= 0.0 => Some(period),
+                true if max >= period => Some(-period),
+                _ => None,
+            };
+            let dx = shift(periodic.0, b.min_x, b.max_x, width);
+            let dy = shift(periodic.1, b.min_y, b.max_y, height);
+            let shifts = [dx.map(|dx| (dx, 0.0)), dy.map(|dy| (0.0, dy)), dx.zip(dy)];
+            for (dx, dy) in shifts.into_iter().flatten() {
+                self.boxes.push(b.shifted(dx, dy));
+                self.owner.push(i as u32);
+            }
+        }
+        self.boxes.len() > originals
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::particle::Particle;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::collections::HashSet;
+
+    const WIDTH: f32 = 640.0;
+    const HEIGHT: f32 = 480.0;
+    const FRAMES: usize = 30;
+
+    /// Checks that `out` holds no self-pairs and no pair twice, and that its pairs that
+    /// `overlap` are exactly `expected`. Only the grid may add pairs that do not overlap.
+    fn assert_pairs<F>(
+        kind: BroadphaseKind,
+        out: &[(u32, u32)],
+        expected: &HashSet<(u32, u32)>,
+        overlap: F,
+        frame: usize,
+    ) where
+        F: Fn(u32, u32) -> bool,
+    {
+        let name = kind.label();
+        let mut found = HashSet::new();
+        for &(i, j) in out {
+            assert_ne!(i, j, "{name} paired box {i} with itself in frame {frame}");
+            let pair = (i.min(j), i.max(j));
+            assert!(
+                found.insert(pair),
+                "{name} reported {pair:?} twice in frame {frame}"
+            );
+            if kind != BroadphaseKind::Grid {
+                assert!(
+                    overlap(i, j),
+                    "{name} reported {pair:?}, which does not overlap, in frame {frame}"
+                );
+            }
+        }
+        found.retain(|&(i, j)| overlap(i, j));
+        assert_eq!(&found, expected, "{name} in frame {frame}");
+    }
+
+    fn random_box(rng: &mut StdRng) -> (Aabb, (f32, f32)) {
+        // A few large boxes among many small ones
+        let radius = if rng.gen_bool(0.02) {
+            rng.gen_range(20.0..40.0)
+        } else {
+            rng.gen_range(1.0..6.0)
+        };
+        let centre = (rng.gen_range(0.0..WIDTH), rng.gen_range(0.0..HEIGHT));
+        let velocity = (rng.gen_range(-3.0..3.0), rng.gen_range(-3.0..3.0));
+        (Aabb::around(centre.0, centre.1, radius), velocity)
+    }
+
+    #[test]
+    fn broadphases_find_exactly_the_overlapping_boxes() {
+        for kind in BroadphaseKind::ALL {
+            let mut rng = StdRng::seed_from_u64(11);
+            let mut broadphase = kind.create();
+            let (mut boxes, mut velocities): (Vec<Aabb>, Vec<(f32, f32)>) =
+                (0..1500).map(|_| random_box(&mut rng)).unzip();
+            let mut out = Vec::new();
+            for frame in 0..FRAMES {
+                for (b, &(vx, vy)) in boxes.iter_mut().zip(&velocities) {
+                    *b = b.shifted(vx, vy);
+                }
+                // Boxes disappear from the middle, which gives the last one a new index, and
+                // from the end, and new boxes arrive, so the count both shrinks and grows
+                match frame % 3 {
+                    0 => {
+                        for _ in 0..rng.gen_range(1..100) {
+                            let k = rng.gen_range(0..boxes.len());
+                            boxes.swap_remove(k);
+                            velocities.swap_remove(k);
+                        }
+                    }
+                    1 => {
+                        let keep = boxes.len() - rng.gen_range(1..100);
+                        boxes.truncate(keep);
+                        velocities.truncate(keep);
+                    }
+                    _ => {
+                        for _ in 0..rng.gen_range(0..150) {
+                            let (b, v) = random_box(&mut rng);
+                            boxes.push(b);
+                            velocities.push(v);
+                        }
+                    }
+                }
+
+                let overlap = |i: u32, j: u32| boxes[i as usize].overlaps(&boxes[j as usize]);
+                let n = boxes.len() as u32;
+                let expected: HashSet<(u32, u32)> = (0..n)
+                    .flat_map(|i| (i + 1..n).map(move |j| (i, j)))
+                    .filter(|&(i, j)| overlap(i, j))
+                    .collect();
+                assert!(!expected.is_empty());
+                broadphase.find_pairs(&boxes, &mut out);
+                assert_pairs(kind, &out, &expected, overlap, frame);
+            }
+        }
+    }
+
+    /// Whether the boxes of two particles overlap, taking the shortest way around the
+    /// periodic axes.
+    fn overlap_wrapped(system: &ParticleSystem, periodic: (bool, bool), i: u32, j: u32) -> bool {
+        let (i, j) = (i as usize, j as usize);
+        let wrapped = |d: f32, period: f32, wraps: bool| {
+            let d = d.abs();
+            if wraps {
+                d.min(period - d)
+            } else {
+                d
+            }
+        };
+        let dx = wrapped(system.x[j] - system.x[i], WIDTH, periodic.0);
+        let dy = wrapped(system.y[j] - system.y[i], HEIGHT, periodic.1);
+        let reach = system.radius[i] + system.radius[j];
+        dx <= reach && dy <= reach
+    }
+
+    /// Rounds to a multiple of 1/8 px, so that box edges, distances and shifts by a period
+    /// are all exact and boxes that just touch count as overlapping every way they are tested.
+    fn snap(v: f32) -> f32 {
+        (v * 8.0).round() / 8.0
+    }
+
+    #[test]
+    fn particle_pairs_wrap_around_periodic_axes() {
+        for periodic in [(true, false), (false, true), (true, true)] {
+            for kind in BroadphaseKind::ALL {
+                let mut rng = StdRng::seed_from_u64(5);
+                let mut broadphase = kind.create();
+                let mut particle_pairs = ParticlePairs::new();
+                let mut system = ParticleSystem::new();
+                for _ in 0..1500 {
+                    let (b, (vx, vy)) = random_box(&mut rng);
+                    let (x, y) = (0.5 * (b.min_x + b.max_x), 0.5 * (b.min_y + b.max_y));
+                    let radius = snap(0.5 * (b.max_x - b.min_x));
+                    // Many particles start on the seams
+                    let x = if rng.gen_bool(0.1) { radius * 0.5 } else { x };
+                    let y = if rng.gen_bool(0.1) {
+                        HEIGHT - radius * 0.5
+                    } else {
+                        y
+                    };
+                    // Velocities are in px per frame
+                    system.push(Particle::new(snap(x), snap(y), snap(vx), snap(vy), radius));
+                }
+                let mut out = Vec::new();
+                for frame in 0..FRAMES {
+                    for (x, vx) in system.x.iter_mut().zip(&system.vx) {
+                        *x = (*x + vx).rem_euclid(WIDTH);
+                    }
+                    for (y, vy) in system.y.iter_mut().zip(&system.vy) {
+                        *y = (*y + vy).rem_euclid(HEIGHT);
+                    }
+                    if frame % 4 == 1 {
+                        let skip = rng.gen_range(5..50);
+                        system.retain(|i| i % skip != 0);
+                    }
+
+                    let overlap = |i: u32, j: u32| overlap_wrapped(&system, periodic, i, j);
+                    let n = system.len() as u32;
+                    let expected: HashSet<(u32, u32)> = (0..n)
+                        .flat_map(|i| (i + 1..n).map(move |j| (i, j)))
+                        .filter(|&(i, j)| overlap(i, j))
+                        .collect();
+                    particle_pairs.find(
+                        broadphase.as_mut(),
+                        &system,
+                        (WIDTH, HEIGHT),
+                        periodic,
+                        &mut out,
+                    );
+                    assert!(out.iter().all(|&(i, j)| i < j), "{}", kind.label());
+                    assert_pairs(kind, &out, &expected, overlap, frame);
+                }
+            }
+        }
+    }
+}
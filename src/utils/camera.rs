@@ -0,0 +1,86 @@
+use sdl2::event::Event;
+use sdl2::mouse::MouseButton;
+
+pub struct Camera {
+    pub rotation: (f32, f32),
+    pub zoom: f32,
+    distance: f32,
+    scale: f32,
+    is_mouse_down: bool,
+}
+
+impl Camera {
+    pub fn new(distance: f32, scale: f32) -> Self {
+        Camera {
+            rotation: (0.0, 0.0),
+            zoom: 1.0,
+            distance,
+            scale,
+            is_mouse_down: false,
+        }
+    }
+
+    pub fn rotate(&self, point: (f32, f32, f32)) -> (f32, f32, f32) {
+        let (x, y, z) = point;
+        let (theta_x, theta_y) = self.rotation;
+
+        let (x1, y1, z1) = (
+            x,
+            y * theta_x.cos() - z * theta_x.sin(),
+            y * theta_x.sin() + z * theta_x.cos(),
+        );
+
+        let (x2, y2, z2) = (
+            x1 * theta_y.cos() + z1 * theta_y.sin(),
+            y1,
+            -x1 * theta_y.sin() + z1 * theta_y.cos(),
+        );
+
+        (x2, y2, z2)
+    }
+
+    pub fn project(&self, point: (f32, f32, f32), width: u32, height: u32) -> (i32, i32) {
+        let (x, y, z) = point;
+
+        let d = self.distance;
+        let x_screen = x / (z + d) * self.scale * self.zoom + width as f32 / 2.0;
+        let y_screen = y / (z + d) * self.scale * self.zoom + height as f32 / 2.0;
+
+        (x_screen as i32, y_screen as i32)
+    }
+
+    /// Rotates and projects a world point in one go.
+    pub fn to_screen(&self, point: (f32, f32, f32), width: u32, height: u32) -> (i32, i32) {
+        self.project(self.rotate(point), width, height)
+    }
+
+    /// Handles mouse drag rotation and wheel zoom. Returns true if the event was consumed.
+    pub fn handle_event(&mut self, event: &Event) -> bool {
+        match event {
+            Event::MouseButtonDown { mouse_btn, .. } if *mouse_btn == MouseButton::Left => {
+                self.is_mouse_down = true;
+                true
+            }
+            Event::MouseButtonUp { mouse_btn, .. } if *mouse_btn == MouseButton::Left => {
+                self.is_mouse_down = false;
+                true
+            }
+            Event::MouseMotion { xrel, yrel, .. } => {
+                if self.is_mouse_down {
+                    self.rotation.0 += *yrel as f32 * 0.01;
+                    self.rotation.1 += *xrel as f32 * 0.01;
+                }
+                self.is_mouse_down
+            }
+            Event::MouseWheel { y, .. } => {
+                if *y > 0 {
+                    self.zoom *= 1.1;
+                } else if *y < 0 {
+                    self.zoom *= 0.9;
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,418 @@
+use crate::models::boundary::BoundaryMode;
+use crate::models::obstacle::{polygon_edges, Contact, Obstacle, Shape, WALL_HALF_WIDTH};
+use crate::models::particle::wall_bounce;
+use crate::models::particle_system::{ParticleSystem, WallImpulses};
+use crate::utils::broadphase::{Aabb, Broadphase, SortAndSweep};
+use crate::utils::collision::{resolve_impulse, resolve_obstacle_contact, ContactBody};
+use crate::utils::spatial_hash::{cell_size_for_radius, SpatialHash};
+
+/// Upper bound on impacts handled per frame; whatever is left of the frame after that is
+/// integrated in one go and left to the discrete solver.
+const MAX_SUBSTEPS: usize = 8;
+/// Extra distance at which a swept particle still counts as touching an obstacle, since the
+/// time of impact only brings it to the surface up to rounding.
+const CONTACT_SLOP: f32 = 0.5;
+/// Impacts no later than this after the earliest one, as a fraction of the frame, are resolved
+/// in the same substep, so that a dense scene does not run out of substeps. The later ones are
+/// resolved slightly early, while still apart by up to their closing speed times this.
+const IMPACT_WINDOW: f32 = 1.0 / MAX_SUBSTEPS as f32;
+
+/// Time until two circles moving in straight lines first touch, if that happens within
+/// `max_t`. Pairs that already overlap or are moving apart return `None` and are left to the
+/// discrete solver.
+pub fn circle_toi(
+    (x1, y1, vx1, vy1, r1): (f32, f32, f32, f32, f32),
+    (x2, y2, vx2, vy2, r2): (f32, f32, f32, f32, f32),
+    max_t: f32,
+) -> Option<f32> {
+    let (dx, dy) = (x2 - x1, y2 - y1);
+    let (wx, wy) = (vx2 - vx1, vy2 - vy1);
+    let r_sum = r1 + r2;
+
+    // |d + w t|² = R² as a t² + b t + c = 0
+    let a = wx * wx + wy * wy;
+    let b = 2.0 * (dx * wx + dy * wy);
+    let c = dx * dx + dy * dy - r_sum * r_sum;
+    if c <= 0.0 || b >= 0.0 || a <= f32::EPSILON {
+        return None;
+    }
+    let disc = b * b - 4.0 * a * c;
+    if disc < 0.0 {
+        return None;
+    }
+    let t = (-b - disc.sqrt()) / (2.0 * a);
+    (0.0..=max_t).contains(&t).then_some(t)
+}
+
+/// Time until a circle moving along one axis touches the `min` or `max` wall, if that happens
+/// within `max_t`.
+pub fn wall_toi(pos: f32, vel: f32, radius: f32, min: f32, max: f32, max_t: f32) -> Option<f32> {
+    let t = if vel < 0.0 {
+        (min + radius - pos) / vel
+    } else if vel > 0.0 {
+        (max - radius - pos) / vel
+    } else {
+        return None;
+    };
+    (0.0..=max_t).contains(&t).then_some(t)
+}
+
+/// Time until a moving circle touches the capsule of radius `half_width` around the segment
+/// `a`-`b`, if that happens within `max_t`. One-sided segments (polygon edges) are only hit
+/// from the side their outward normal points to.
+pub fn segment_toi(
+    sweep: (f32, f32, f32, f32, f32),
+    (a, b): ((f32, f32), (f32, f32)),
+    half_width: f32,
+    two_sided: bool,
+    max_t: f32,
+) -> Option<f32> {
+    let (x, y, vx, vy, r) = sweep;
+    let reach = r + half_width;
+    let (ex, ey) = (b.0 - a.0, b.1 - a.1);
+    let len_sq = ex * ex + ey * ey;
+
+    let mut earliest: Option<f32> = None;
+    let mut consider = |t: Option<f32>| {
+        if let Some(t) = t {
+            earliest = Some(earliest.map_or(t, |best| best.min(t)));
+        }
+    };
+    consider(circle_toi(sweep, (a.0, a.1, 0.0, 0.0, half_width), max_t));
+    consider(circle_toi(sweep, (b.0, b.1, 0.0, 0.0, half_width), max_t));
+
+    if len_sq > f32::EPSILON {
+        let len = len_sq.sqrt();
+        let (nx, ny) = (ey / len, -ex / len);
+        let s0 = (x - a.0) * nx + (y - a.1) * ny;
+        let sv = vx * nx + vy * ny;
+        let t = if s0 > reach && sv < 0.0 {
+            Some((reach - s0) / sv)
+        } else if two_sided && s0 < -reach && sv > 0.0 {
+            Some((-reach - s0) / sv)
+        } else {
+            None
+        };
+        if let Some(t) = t.filter(|t| (0.0..=max_t).contains(t)) {
+            // Only the flat side counts here, the rounded ends were handled above
+            let u = ((x + vx * t - a.0) * ex + (y + vy * t - a.1) * ey) / len_sq;
+            if (0.0..=1.0).contains(&u) {
+                consider(Some(t));
+            }
+        }
+    }
+    earliest
+}
+
+/// Time until a moving circle touches the obstacle, if that happens within `max_t`.
+pub fn obstacle_toi(
+    sweep: (f32, f32, f32, f32, f32),
+    obstacle: &Obstacle,
+    max_t: f32,
+) -> Option<f32> {
+    match &obstacle.shape {
+        Shape::Segment { a, b } => segment_toi(sweep, (*a, *b), WALL_HALF_WIDTH, true, max_t),
+        Shape::Circle { center, radius } => {
+            circle_toi(sweep, (center.0, center.1, 0.0, 0.0, *radius), max_t)
+        }
+        Shape::Polygon { points } => polygon_edges(points)
+            .filter_map(|edge| segment_toi(sweep, edge, 0.0, false, max_t))
+            .min_by(f32::total_cmp),
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Impact {
+    Pair(usize, usize),
+    /// Particle hitting a vertical (`x_axis`) or horizontal wall
+    Wall(usize, bool),
+    /// Particle hitting the obstacle with the given index
+    Obstacle(usize, usize),
+}
+
+/// Continuous collision detection: splits a frame at each time of impact so fast particles
+/// cannot tunnel through each other, the screen edges or thin obstacles.
+///
+/// Only particles faster than the threshold are swept. Each one looks for slow particles in
+/// the cells its path crosses, on its own grid of the usual cell size, so the collision grid
+/// and its static obstacle table are left alone; fast particles are paired with each other by
+/// sort and sweep over the boxes around their paths.
+pub struct Ccd {
+    pub enabled: bool,
+    /// Only particles faster than this (px/s) are swept; 0 sweeps everything
+    pub speed_threshold: f32,
+    hash: SpatialHash,
+    /// Indices of the particles being swept
+    fast: Vec<u32>,
+    /// Box around the path of each swept particle, in the order of `fast`
+    paths: Vec<Aabb>,
+    sweep: SortAndSweep,
+    /// Overlapping paths, as indices into `fast`
+    pairs: Vec<(u32, u32)>,
+    /// Impacts found in the current substep, then those that are resolved in it
+    impacts: Vec<(f32, Impact)>,
+    /// Particles already taking part in an impact of the current substep
+    engaged: Vec<bool>,
+    /// Substeps taken in the last frame, for the HUD
+    pub last_substeps: usize,
+    /// Particle pairs that collided at a swept time of impact in the last frame
+    pub last_pair_impacts: usize,
+    /// The pairs themselves, so that sleeping particles that were hit can be woken
+    pub last_impact_pairs: Vec<(usize, usize)>,
+    /// Momentum handed to the screen edges by swept wall impacts in the last frame
+    pub last_wall_impulses: WallImpulses,
+}
+
+impl Ccd {
+    pub fn new() -> Self {
+        Ccd {
+            enabled: true,
+            speed_threshold: 300.0,
+            hash: SpatialHash::new(1.0, 1, 1),
+            fast: Vec::new(),
+            paths: Vec::new(),
+            sweep: SortAndSweep::new(),
+            pairs: Vec::new(),
+            impacts: Vec::new(),
+            engaged: Vec::new(),
+            last_substeps: 0,
+            last_pair_impacts: 0,
+            last_impact_pairs: Vec::new(),
+            last_wall_impulses: WallImpulses::default(),
+        }
+    }
+
+    /// Collects the impacts within `max_t` that happen no more than `window` after the earliest
+    /// one, at most one per particle, into `impacts` and returns the earliest time. There are
+    /// few obstacles, so fast particles are tested against all of them.
+    fn find_impacts(
+        &mut self,
+        system: &ParticleSystem,
+        obstacles: &[Obstacle],
+        max_t: f32,
+        window: f32,
+        (width, height): (f32, f32),
+    ) -> Option<f32> {
+        let threshold_sq = self.speed_threshold * self.speed_threshold;
+        let is_fast =
+            |i: usize| system.vx[i] * system.vx[i] + system.vy[i] * system.vy[i] > threshold_sq;
+        let sweep = |i: usize| {
+            (
+                system.x[i],
+                system.y[i],
+                system.vx[i],
+                system.vy[i],
+                system.radius[i],
+            )
+        };
+        let path = |i: usize| {
+            let (x, y, vx, vy, r) = sweep(i);
+            Aabb::around(x, y, r).union(&Aabb::around(x + vx * max_t, y + vy * max_t, r))
+        };
+
+        let max_radius = system.max_radius();
+        self.hash.resize(
+            cell_size_for_radius(max_radius),
+            width as u32,
+            height as u32,
+        );
+        self.hash.build(system.positions());
+        self.fast.clear();
+        self.fast
+            .extend((0..system.len()).filter(|&i| is_fast(i)).map(|i| i as u32));
+
+        let impacts = &mut self.impacts;
+        impacts.clear();
+        let mut consider = |t: Option<f32>, impact: Impact| {
+            if let Some(t) = t {
+                impacts.push((t, impact));
+            }
+        };
+
+        // A slow particle moves at most this far, so one that can meet a fast particle starts
+        // within reach of the box around the fast one's path
+        let reach = max_radius + self.speed_threshold * max_t;
+        for &i in &self.fast {
+            let i = i as usize;
+            let around = path(i).expanded(reach);
+            self.hash.for_each_in_box(
+                (around.min_x, around.min_y),
+                (around.max_x, around.max_y),
+                |j| {
+                    if !is_fast(j) {
+                        consider(circle_toi(sweep(i), sweep(j), max_t), Impact::Pair(i, j));
+                    }
+                },
+            );
+            // Only reflective edges stop particles
+            let (x, y, vx, vy, r) = sweep(i);
+            if system.boundaries.x == BoundaryMode::Reflective {
+                consider(wall_toi(x, vx, r, 0.0, width, max_t), Impact::Wall(i, true));
+            }
+            if system.boundaries.y == BoundaryMode::Reflective {
+                consider(
+                    wall_toi(y, vy, r, 0.0, height, max_t),
+                    Impact::Wall(i, false),
+                );
+            }
+            for (k, obstacle) in obstacles.iter().enumerate() {
+                consider(
+                    obstacle_toi(sweep(i), obstacle, max_t),
+                    Impact::Obstacle(i, k),
+                );
+            }
+        }
+
+        // Two fast particles can only meet where the boxes around their paths overlap
+        self.paths.clear();
+        self.paths
+            .extend(self.fast.iter().map(|&i| path(i as usize)));
+        self.sweep.find_pairs(&self.paths, &mut self.pairs);
+        for &(a, b) in &self.pairs {
+            let (i, j) = (
+                self.fast[a as usize] as usize,
+                self.fast[b as usize] as usize,
+            );
+            consider(circle_toi(sweep(i), sweep(j), max_t), Impact::Pair(i, j));
+        }
+
+        let earliest = self
+            .impacts
+            .iter()
+            .map(|&(t, _)| t)
+            .min_by(f32::total_cmp)?;
+        // Keep the impacts in the window, earliest first, skipping any that involve a particle
+        // already taken; those are found again in a later substep
+        self.impacts.retain(|&(t, _)| t <= earliest + window);
+        self.impacts.sort_by(|a, b| a.0.total_cmp(&b.0));
+        self.engaged.clear();
+        self.engaged.resize(system.len(), false);
+        let engaged = &mut self.engaged;
+        self.impacts.retain(|&(_, impact)| {
+            let (i, j) = match impact {
+                Impact::Pair(i, j) => (i, Some(j)),
+                Impact::Wall(i, _) | Impact::Obstacle(i, _) => (i, None),
+            };
+            if engaged[i] || j.is_some_and(|j| engaged[j]) {
+                return false;
+            }
+            engaged[i] = true;
+            if let Some(j) = j {
+                engaged[j] = true;
+            }
+            true
+        });
+        Some(earliest)
+    }
+
+    /// Applies the impulse for an impact. Returns whether two particles collided; wall bounces
+    /// add their momentum transfer to `walls`.
+    fn resolve(
+        system: &mut ParticleSystem,
+        obstacles: &[Obstacle],
+        impact: Impact,
+        walls: &mut WallImpulses,
+    ) -> bool {
+        match impact {
+            Impact::Pair(a, b) => {
+                let mut b1 = ContactBody::from_system(system, a);
+                let mut b2 = ContactBody::from_system(system, b);
+                let (dx, dy) = (b2.x - b1.x, b2.y - b1.y);
+                let dist = (dx * dx + dy * dy).sqrt();
+                let collided = dist > 1e-6
+                    && b1.inv_mass + b2.inv_mass > 0.0
+                    && resolve_impulse(&mut b1, &mut b2, dx / dist, dy / dist);
+                b1.apply_to(system, a);
+                b2.apply_to(system, b);
+                collided
+            }
+            Impact::Wall(i, x_axis) => {
+                let (e, mu, m) = (system.restitution[i], system.friction[i], system.mass[i]);
+                if x_axis {
+                    let vx = system.vx[i];
+                    (system.vx[i], system.vy[i]) = wall_bounce(vx, system.vy[i], e, mu);
+                    let impulse = m * (system.vx[i] - vx).abs();
+                    if vx < 0.0 {
+                        walls.left += impulse;
+                    } else {
+                        walls.right += impulse;
+                    }
+                } else {
+                    let vy = system.vy[i];
+                    (system.vy[i], system.vx[i]) = wall_bounce(vy, system.vx[i], e, mu);
+                    let impulse = m * (system.vy[i] - vy).abs();
+                    if vy < 0.0 {
+                        walls.top += impulse;
+                    } else {
+                        walls.bottom += impulse;
+                    }
+                }
+                false
+            }
+            Impact::Obstacle(i, k) => {
+                let obstacle = &obstacles[k];
+                let mut body = ContactBody::from_system(system, i);
+                if let Some(contact) = obstacle.contact(body.x, body.y, body.radius + CONTACT_SLOP)
+                {
+                    // Already at the surface, so only the velocity changes
+                    let contact = Contact {
+                        depth: 0.0,
+                        ..contact
+                    };
+                    resolve_obstacle_contact(&mut body, obstacle, contact);
+                }
+                body.apply_to(system, i);
+                false
+            }
+        }
+    }
+
+    /// Advances the system by `dt`, stopping at each batch of impacts along the way. `integrate` moves
+    /// every particle by the given time step.
+    pub fn step<F>(
+        &mut self,
+        system: &mut ParticleSystem,
+        obstacles: &[Obstacle],
+        dt: f32,
+        screen: (f32, f32),
+        mut integrate: F,
+    ) where
+        F: FnMut(&mut ParticleSystem, f32),
+    {
+        self.last_substeps = 0;
+        self.last_pair_impacts = 0;
+        self.last_impact_pairs.clear();
+        self.last_wall_impulses = WallImpulses::default();
+        if dt <= 0.0 {
+            // Running backwards: there is nothing sensible to sweep
+            integrate(system, dt);
+            return;
+        }
+        let window = IMPACT_WINDOW * dt;
+        let mut remaining = dt;
+        while remaining > 0.0 && self.last_substeps < MAX_SUBSTEPS {
+            self.last_substeps += 1;
+            match self.find_impacts(system, obstacles, remaining, window, screen) {
+                Some(t) => {
+                    integrate(system, t);
+                    for &(_, impact) in &self.impacts {
+                        if Self::resolve(system, obstacles, impact, &mut self.last_wall_impulses) {
+                            self.last_pair_impacts += 1;
+                            if let Impact::Pair(a, b) = impact {
+                                self.last_impact_pairs.push((a, b));
+                            }
+                        }
+                    }
+                    remaining -= t;
+                }
+                None => {
+                    integrate(system, remaining);
+                    remaining = 0.0;
+                }
+            }
+        }
+        if remaining > 0.0 {
+            integrate(system, remaining);
+        }
+    }
+}
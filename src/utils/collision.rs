@@ -0,0 +1,400 @@
+use crate::models::force_field::ForceFields;
+use crate::models::obstacle::{Contact, Obstacle};
+use crate::models::particle_system::{ParticleSystem, WallImpulses};
+use crate::utils::spatial_hash::{pair_mut, SpatialHash};
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+/// Gap, in px, up to which two particles still count as touching, so that resting contacts
+/// that the solver has just separated are not lost between frames.
+const TOUCH_MARGIN: f32 = 0.5;
+
+/// The part of a particle the contact solver reads and writes, copied out so that worker
+/// threads can solve their band without touching shared particle storage.
+#[derive(Clone, Copy)]
+pub struct ContactBody {
+    pub x: f32,
+    pub y: f32,
+    pub vx: f32,
+    pub vy: f32,
+    pub radius: f32,
+    pub inv_mass: f32,
+    pub restitution: f32,
+    pub friction: f32,
+}
+
+impl ContactBody {
+    /// Copies particle `i`, with infinite mass if it is asleep so that it does not move.
+    pub fn from_system(system: &ParticleSystem, i: usize) -> Self {
+        ContactBody {
+            x: system.x[i],
+            y: system.y[i],
+            vx: system.vx[i],
+            vy: system.vy[i],
+            radius: system.radius[i],
+            inv_mass: if system.sleep[i].is_asleep() {
+                0.0
+            } else {
+                system.inverse_mass(i)
+            },
+            restitution: system.restitution[i],
+            friction: system.friction[i],
+        }
+    }
+
+    pub fn apply_to(&self, system: &mut ParticleSystem, i: usize) {
+        system.x[i] = self.x;
+        system.y[i] = self.y;
+        system.vx[i] = self.vx;
+        system.vy[i] = self.vy;
+    }
+}
+
+/// Whether two bodies overlap or are within `TOUCH_MARGIN` of each other.
+pub fn touching(b1: &ContactBody, b2: &ContactBody) -> bool {
+    let (dx, dy) = (b2.x - b1.x, b2.y - b1.y);
+    let reach = b1.radius + b2.radius + TOUCH_MARGIN;
+    dx * dx + dy * dy <= reach * reach
+}
+
+/// Resolves an overlapping pair: velocity impulse first, then a mass-weighted positional
+/// correction so heavy bodies barely move. Returns whether the pair was approaching, i.e.
+/// whether this was a collision rather than a resting contact being separated.
+pub fn resolve_contact(b1: &mut ContactBody, b2: &mut ContactBody) -> bool {
+    let inv_sum = b1.inv_mass + b2.inv_mass;
+    if inv_sum <= 0.0 {
+        // Two sleeping or immovable bodies
+        return false;
+    }
+    let dx = b2.x - b1.x;
+    let dy = b2.y - b1.y;
+    let d_sq = dx * dx + dy * dy;
+    let r_sum = b1.radius + b2.radius;
+
+    if d_sq > r_sum * r_sum {
+        return false;
+    }
+    let dist = d_sq.sqrt();
+    if dist < 1e-6 {
+        return false;
+    }
+    let nx = dx / dist;
+    let ny = dy / dist;
+
+    let collided = resolve_impulse(b1, b2, nx, ny);
+
+    let overlap = r_sum - dist;
+    let (s1, s2) = (
+        overlap * b1.inv_mass / inv_sum,
+        overlap * b2.inv_mass / inv_sum,
+    );
+    b1.x -= nx * s1;
+    b1.y -= ny * s1;
+    b2.x += nx * s2;
+    b2.y += ny * s2;
+    collided
+}
+
+/// Applies the normal restitution impulse and a Coulomb-clamped friction impulse along the
+/// contact normal `(nx, ny)`, which points from `b1` to `b2`. Returns false, changing
+/// nothing, if the bodies are already separating.
+pub fn resolve_impulse(b1: &mut ContactBody, b2: &mut ContactBody, nx: f32, ny: f32) -> bool {
+    let (inv_m1, inv_m2) = (b1.inv_mass, b2.inv_mass);
+    let inv_sum = inv_m1 + inv_m2;
+    let rvx = b2.vx - b1.vx;
+    let rvy = b2.vy - b1.vy;
+    let vn = rvx * nx + rvy * ny;
+    if vn >= 0.0 {
+        // Already separating
+        return false;
+    }
+
+    let restitution = b1.restitution.max(b2.restitution);
+    let jn = -(1.0 + restitution) * vn / inv_sum;
+    b1.vx -= jn * nx * inv_m1;
+    b1.vy -= jn * ny * inv_m1;
+    b2.vx += jn * nx * inv_m2;
+    b2.vy += jn * ny * inv_m2;
+
+    let friction = (b1.friction * b2.friction).sqrt();
+    if friction <= 0.0 {
+        return true;
+    }
+    let (tx, ty) = (-ny, nx);
+    let vt = (b2.vx - b1.vx) * tx + (b2.vy - b1.vy) * ty;
+    let jt = (-vt / inv_sum).clamp(-friction * jn, friction * jn);
+    b1.vx -= jt * tx * inv_m1;
+    b1.vy -= jt * ty * inv_m1;
+    b2.vx += jt * tx * inv_m2;
+    b2.vy += jt * ty * inv_m2;
+    true
+}
+
+/// Bounces a particle off a static obstacle and pushes it out by the contact depth. The
+/// obstacle takes part in the impulse as a body of infinite mass.
+pub fn resolve_obstacle_contact(body: &mut ContactBody, obstacle: &Obstacle, contact: Contact) {
+    if body.inv_mass <= 0.0 {
+        return;
+    }
+    let mut wall = ContactBody {
+        x: 0.0,
+        y: 0.0,
+        vx: 0.0,
+        vy: 0.0,
+        radius: 0.0,
+        inv_mass: 0.0,
+        restitution: obstacle.restitution,
+        friction: obstacle.friction,
+    };
+    resolve_impulse(&mut wall, body, contact.nx, contact.ny);
+    body.x += contact.nx * contact.depth;
+    body.y += contact.ny * contact.depth;
+}
+
+/// Scratch space for one grid row. A band owns the bodies of its row and the row below, which
+/// are all the particles its home cells can touch with the half-neighbourhood stencil.
+#[derive(Default)]
+struct Band {
+    bodies: Vec<ContactBody>,
+    pairs: Vec<(u32, u32)>,
+    /// Particle indices of the pairs found touching in the last solve
+    touching: Vec<(u32, u32)>,
+    /// Approaching pairs resolved in the last solve
+    collisions: usize,
+}
+
+/// Solves particle contacts on a fixed pool of worker threads.
+///
+/// Rows are processed in two passes, even rows then odd rows. Bands in the same pass cover
+/// disjoint row pairs, so they can run concurrently without sharing any particle, and the result
+/// does not depend on how the bands are scheduled.
+pub struct CollisionSolver {
+    pool: ThreadPool,
+    threads: usize,
+    bands: Vec<Band>,
+    /// Candidate pairs across periodic seams, solved on the calling thread after the bands
+    seam_pairs: Vec<(u32, u32)>,
+    last_collisions: usize,
+    /// Candidate pairs tested in the last solve
+    candidates: usize,
+    touching: Vec<(u32, u32)>,
+}
+
+impl CollisionSolver {
+    pub fn new(threads: usize) -> Self {
+        let threads = threads.max(1);
+        CollisionSolver {
+            pool: ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .expect("failed to build the collision thread pool"),
+            threads,
+            bands: Vec::new(),
+            seam_pairs: Vec::new(),
+            last_collisions: 0,
+            candidates: 0,
+            touching: Vec::new(),
+        }
+    }
+
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    /// Number of colliding pairs, as opposed to resting contacts, in the last `solve`.
+    pub fn last_collisions(&self) -> usize {
+        self.last_collisions
+    }
+
+    /// Number of candidate pairs the broadphase handed to the last `solve` or `solve_pairs`.
+    pub fn last_candidates(&self) -> usize {
+        self.candidates
+    }
+
+    /// Pairs of particle indices touching in the last `solve` or `solve_pairs`, at least one
+    /// of them awake.
+    pub fn touching(&self) -> &[(u32, u32)] {
+        &self.touching
+    }
+
+    /// Integrates every particle in parallel and returns the momentum handed to each screen edge.
+    pub fn integrate(
+        &self,
+        system: &mut ParticleSystem,
+        fields: &ForceFields,
+        dt: f32,
+        screen_w: u32,
+        screen_h: u32,
+    ) -> WallImpulses {
+        self.pool
+            .install(|| system.par_integrate(fields, dt, screen_w, screen_h))
+    }
+
+    /// Resolves all contacts between particles bucketed in `hash`.
+    pub fn solve(&mut self, hash: &SpatialHash, system: &mut ParticleSystem) {
+        let rows = hash.rows();
+        if self.bands.len() < rows {
+            self.bands.resize_with(rows, Band::default);
+        }
+        let bands = &mut self.bands[..rows];
+
+        for parity in 0..2 {
+            let shared: &ParticleSystem = system;
+            self.pool.install(|| {
+                bands.par_chunks_mut(2).enumerate().for_each(|(k, chunk)| {
+                    if let Some(band) = chunk.get_mut(parity) {
+                        Self::solve_band(hash, 2 * k + parity, shared, band);
+                    }
+                })
+            });
+
+            // Write results back in row order
+            for row in (parity..rows).step_by(2) {
+                let span = hash.row_span(row, row + 2);
+                let band = &bands[row];
+                for (body, &idx) in band.bodies.iter().zip(&hash.entries()[span]) {
+                    body.apply_to(system, idx as usize);
+                }
+            }
+        }
+        self.last_collisions = bands.iter().map(|band| band.collisions).sum();
+        self.candidates = bands.iter().map(|band| band.pairs.len()).sum();
+        self.touching.clear();
+        for band in bands.iter() {
+            self.touching.extend_from_slice(&band.touching);
+        }
+        self.last_collisions += self.solve_seams(hash, system);
+    }
+
+    /// Resolves the pairs that touch across a periodic seam. Returns the number of collisions.
+    fn solve_seams(&mut self, hash: &SpatialHash, system: &mut ParticleSystem) -> usize {
+        hash.seam_pairs(&mut self.seam_pairs);
+        self.candidates += self.seam_pairs.len();
+        Self::solve_pair_list(hash, system, &self.seam_pairs, &mut self.touching)
+    }
+
+    /// Resolves contacts between the given pairs of particle indices, as found by a
+    /// `Broadphase`, one after another on the calling thread. `hash` only supplies the
+    /// periodic axes.
+    pub fn solve_pairs(
+        &mut self,
+        hash: &SpatialHash,
+        system: &mut ParticleSystem,
+        pairs: &[(u32, u32)],
+    ) {
+        self.touching.clear();
+        self.candidates = pairs.len();
+        self.last_collisions = Self::solve_pair_list(hash, system, pairs, &mut self.touching);
+    }
+
+    /// Resolves each pair with the second particle moved to its image nearest the first for
+    /// the duration of the contact, and adds those touching to `touching_pairs`. Returns the number
+    /// of collisions.
+    fn solve_pair_list(
+        hash: &SpatialHash,
+        system: &mut ParticleSystem,
+        pairs: &[(u32, u32)],
+        touching_pairs: &mut Vec<(u32, u32)>,
+    ) -> usize {
+        let mut collisions = 0;
+        for &(i, j) in pairs {
+            let (i, j) = (i as usize, j as usize);
+            let mut b1 = ContactBody::from_system(system, i);
+            let mut b2 = ContactBody::from_system(system, j);
+            let (dx, dy) = hash.minimum_image(b2.x - b1.x, b2.y - b1.y);
+            let shift = (b1.x + dx - b2.x, b1.y + dy - b2.y);
+            b2.x += shift.0;
+            b2.y += shift.1;
+            if b1.inv_mass + b2.inv_mass > 0.0 && touching(&b1, &b2) {
+                touching_pairs.push((i as u32, j as u32));
+            }
+            if resolve_contact(&mut b1, &mut b2) {
+                collisions += 1;
+            }
+            b2.x -= shift.0;
+            b2.y -= shift.1;
+            b1.apply_to(system, i);
+            b2.apply_to(system, j);
+        }
+        collisions
+    }
+
+    /// Resolves contacts between particles and the static obstacles registered in `hash`.
+    /// Obstacles never move, so every particle is independent and they run fully in parallel.
+    pub fn solve_obstacles(
+        &self,
+        hash: &SpatialHash,
+        system: &mut ParticleSystem,
+        obstacles: &[Obstacle],
+    ) {
+        if obstacles.is_empty() {
+            return;
+        }
+        let ParticleSystem {
+            x,
+            y,
+            vx,
+            vy,
+            radius,
+            mass,
+            restitution,
+            friction,
+            sleep,
+            ..
+        } = system;
+        let (radius, mass, restitution, friction, sleep) =
+            (&*radius, &*mass, &*restitution, &*friction, &*sleep);
+        self.pool.install(|| {
+            (x, y, vx, vy)
+                .into_par_iter()
+                .enumerate()
+                .for_each(|(i, (x, y, vx, vy))| {
+                    let statics = hash.statics_at(*x, *y);
+                    if statics.is_empty() || sleep[i].is_asleep() {
+                        return;
+                    }
+                    let mut body = ContactBody {
+                        x: *x,
+                        y: *y,
+                        vx: *vx,
+                        vy: *vy,
+                        radius: radius[i],
+                        inv_mass: if mass[i] > 0.0 { 1.0 / mass[i] } else { 0.0 },
+                        restitution: restitution[i],
+                        friction: friction[i],
+                    };
+                    for &k in statics {
+                        let obstacle = &obstacles[k as usize];
+                        if let Some(contact) = obstacle.contact(body.x, body.y, body.radius) {
+                            resolve_obstacle_contact(&mut body, obstacle, contact);
+                        }
+                    }
+                    (*x, *y, *vx, *vy) = (body.x, body.y, body.vx, body.vy);
+                })
+        });
+    }
+
+    fn solve_band(hash: &SpatialHash, row: usize, system: &ParticleSystem, band: &mut Band) {
+        let entries = &hash.entries()[hash.row_span(row, row + 2)];
+        band.bodies.clear();
+        band.bodies.extend(
+            entries
+                .iter()
+                .map(|&idx| ContactBody::from_system(system, idx as usize)),
+        );
+
+        hash.band_pairs(row, &mut band.pairs);
+        band.collisions = 0;
+        band.touching.clear();
+        for &(a, b) in &band.pairs {
+            let (b1, b2) = pair_mut(&mut band.bodies, a as usize, b as usize);
+            if b1.inv_mass + b2.inv_mass > 0.0 && touching(b1, b2) {
+                band.touching
+                    .push((entries[a as usize], entries[b as usize]));
+            }
+            if resolve_contact(b1, b2) {
+                band.collisions += 1;
+            }
+        }
+    }
+}
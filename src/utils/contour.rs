@@ -0,0 +1,65 @@
+/// Edges crossed by the contour for each marching-squares case, as pairs of cell edges
+/// (0 top, 1 right, 2 bottom, 3 left). Corners are numbered top-left 1, top-right 2,
+/// bottom-right 4, bottom-left 8; the two saddle cases are split the same way every time.
+const CASES: [&[(u8, u8)]; 16] = [
+    &[],
+    &[(3, 0)],
+    &[(0, 1)],
+    &[(3, 1)],
+    &[(1, 2)],
+    &[(3, 0), (1, 2)],
+    &[(0, 2)],
+    &[(3, 2)],
+    &[(2, 3)],
+    &[(0, 2)],
+    &[(0, 1), (2, 3)],
+    &[(1, 2)],
+    &[(1, 3)],
+    &[(0, 1)],
+    &[(0, 3)],
+    &[],
+];
+
+/// A line segment between two points.
+pub type Segment = ((f32, f32), (f32, f32));
+
+/// Traces the `level` contour of a scalar field sampled on a `cols` × `rows` grid stored row
+/// by row, appending line segments in grid coordinates (sample `(i, j)` is at `(i, j)`).
+pub fn marching_squares(
+    values: &[f32],
+    cols: usize,
+    rows: usize,
+    level: f32,
+    out: &mut Vec<Segment>,
+) {
+    let at = |i: usize, j: usize| values[j * cols + i];
+    // Where the contour crosses the segment between two samples
+    let cross = |a: f32, b: f32| {
+        let t = (level - a) / (b - a);
+        if t.is_finite() {
+            t.clamp(0.0, 1.0)
+        } else {
+            0.5
+        }
+    };
+
+    for j in 0..rows.saturating_sub(1) {
+        for i in 0..cols.saturating_sub(1) {
+            let (v00, v10, v11, v01) = (at(i, j), at(i + 1, j), at(i + 1, j + 1), at(i, j + 1));
+            let case = (v00 > level) as usize
+                | ((v10 > level) as usize) << 1
+                | ((v11 > level) as usize) << 2
+                | ((v01 > level) as usize) << 3;
+            let (x, y) = (i as f32, j as f32);
+            let point = |edge: u8| match edge {
+                0 => (x + cross(v00, v10), y),
+                1 => (x + 1.0, y + cross(v10, v11)),
+                2 => (x + cross(v01, v11), y + 1.0),
+                _ => (x, y + cross(v00, v01)),
+            };
+            for &(from, to) in CASES[case] {
+                out.push((point(from), point(to)));
+            }
+        }
+    }
+}
@@ -0,0 +1,126 @@
+const JACOBI_MAX_SWEEPS: usize = 100;
+/// Off-diagonal norm, relative to the Frobenius norm of the matrix, below which the matrix
+/// counts as diagonal. Close to what f32 rotations can resolve.
+const JACOBI_TOLERANCE: f32 = 1e-6;
+
+/// Eigen-decomposition of a symmetric matrix using cyclic Jacobi rotations.
+///
+/// Returns the eigenvalues in ascending order together with the matching unit eigenvectors,
+/// `vectors[k]` being the eigenvector of `values[k]`.
+pub fn symmetric_eigen(matrix: &[Vec<f32>]) -> (Vec<f32>, Vec<Vec<f32>>) {
+    let n = matrix.len();
+    let mut a: Vec<Vec<f32>> = matrix.to_vec();
+    // Columns of v accumulate the rotations and end up as the eigenvectors
+    let mut v: Vec<Vec<f32>> = (0..n)
+        .map(|i| (0..n).map(|j| if i == j { 1.0 } else { 0.0 }).collect())
+        .collect();
+    // Rotations leave the Frobenius norm unchanged, so the threshold holds for every sweep
+    let frobenius_sq: f32 = a.iter().flatten().map(|x| x * x).sum();
+    let threshold = JACOBI_TOLERANCE * JACOBI_TOLERANCE * frobenius_sq;
+
+    for _ in 0..JACOBI_MAX_SWEEPS {
+        let off_diagonal: f32 = (0..n)
+            .flat_map(|i| (0..n).filter(move |&j| j != i).map(move |j| (i, j)))
+            .map(|(i, j)| a[i][j] * a[i][j])
+            .sum();
+        if off_diagonal <= threshold {
+            break;
+        }
+
+        for p in 0..n {
+            for q in (p + 1)..n {
+                if a[p][q].abs() < f32::EPSILON {
+                    continue;
+                }
+                let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+
+                for row in a.iter_mut() {
+                    let (akp, akq) = (row[p], row[q]);
+                    row[p] = c * akp - s * akq;
+                    row[q] = s * akp + c * akq;
+                }
+                let (upper, lower) = a.split_at_mut(q);
+                for (apk, aqk) in upper[p].iter_mut().zip(lower[0].iter_mut()) {
+                    let (vp, vq) = (*apk, *aqk);
+                    *apk = c * vp - s * vq;
+                    *aqk = s * vp + c * vq;
+                }
+                for row in v.iter_mut() {
+                    let (vkp, vkq) = (row[p], row[q]);
+                    row[p] = c * vkp - s * vkq;
+                    row[q] = s * vkp + c * vkq;
+                }
+            }
+        }
+    }
+
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by(|&i, &j| a[i][i].total_cmp(&a[j][j]));
+
+    let values = order.iter().map(|&k| a[k][k]).collect();
+    let vectors = order
+        .iter()
+        .map(|&k| (0..n).map(|row| v[row][k]).collect())
+        .collect();
+    (values, vectors)
+}
+
+pub fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Checks that every `(values[k], vectors[k])` satisfies `A v = λ v` and that the
+    /// eigenvectors are orthonormal.
+    fn assert_decomposes(matrix: &[Vec<f32>], expected_values: &[f32]) {
+        let (values, vectors) = symmetric_eigen(matrix);
+        for (value, expected) in values.iter().zip(expected_values) {
+            assert!(
+                (value - expected).abs() < 1e-5,
+                "eigenvalue {value}, expected {expected}"
+            );
+        }
+        for (value, vector) in values.iter().zip(&vectors) {
+            for (row, component) in matrix.iter().zip(vector) {
+                let av = dot(row, vector);
+                assert!(
+                    (av - value * component).abs() < 1e-5,
+                    "A v = {av} but λ v = {} for λ = {value}",
+                    value * component
+                );
+            }
+        }
+        for (i, a) in vectors.iter().enumerate() {
+            for (j, b) in vectors.iter().enumerate() {
+                let expected = if i == j { 1.0 } else { 0.0 };
+                assert!(
+                    (dot(a, b) - expected).abs() < 1e-5,
+                    "v{i} · v{j} = {}",
+                    dot(a, b)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn decomposes_2x2() {
+        assert_decomposes(&[vec![2.0, 1.0], vec![1.0, 2.0]], &[1.0, 3.0]);
+    }
+
+    #[test]
+    fn decomposes_3x3() {
+        let matrix = [
+            vec![2.0, -1.0, 0.0],
+            vec![-1.0, 2.0, -1.0],
+            vec![0.0, -1.0, 2.0],
+        ];
+        let root2 = std::f32::consts::SQRT_2;
+        assert_decomposes(&matrix, &[2.0 - root2, 2.0, 2.0 + root2]);
+    }
+}
@@ -1 +1,19 @@
-pub mod RK4;
\ No newline at end of file
+pub mod RK4;
+pub mod camera;
+pub mod linalg;
+pub mod spatial_hash;
+pub mod collision;
+pub mod ccd;
+pub mod noise;
+pub mod barnes_hut;
+pub mod contour;
+pub mod boris;
+pub mod molecular_dynamics;
+pub mod statistics;
+pub mod pressure;
+pub mod xpbd;
+pub mod rigid_contact;
+pub mod rigid_solver;
+pub mod sleep;
+pub mod broadphase;
+pub mod benchmark;
@@ -0,0 +1,246 @@
+use crate::models::particle_system::ParticleSystem;
+use crate::utils::spatial_hash::SpatialHash;
+use rayon::prelude::*;
+
+/// Interaction range in units of σ. Forces beyond it are dropped and the potential is shifted
+/// so it reaches zero there.
+pub const CUTOFF: f32 = 2.5;
+
+/// How the kinetic energy is steered towards the target temperature.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Thermostat {
+    /// Plain velocity Verlet; total energy is conserved
+    None,
+    /// Rescales velocities each step so the temperature relaxes with time constant `tau`
+    Berendsen { tau: f32 },
+    /// Couples the velocities to a friction variable ξ whose own dynamics has time scale
+    /// `tau`, which samples the canonical ensemble
+    NoseHoover { tau: f32 },
+}
+
+impl Thermostat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Thermostat::None => "none (NVE)",
+            Thermostat::Berendsen { .. } => "Berendsen",
+            Thermostat::NoseHoover { .. } => "Nosé-Hoover",
+        }
+    }
+}
+
+/// Lennard-Jones molecular dynamics in reduced units (σ = ε = k_B = 1) inside a box with
+/// reflecting walls, or periodic ones that wrap around and measure distances to the nearest
+/// image. Positions and velocities live in a `ParticleSystem`; neighbours within the cutoff
+/// come from a cell grid whose cells are at least one cutoff wide.
+pub struct LennardJones {
+    hash: SpatialHash,
+    acceleration: Vec<(f32, f32)>,
+    width: f32,
+    height: f32,
+    periodic: bool,
+    /// Nosé-Hoover friction coefficient
+    xi: f32,
+    /// Shifted potential energy and pair virial Σ r·F from the last force evaluation
+    pub potential_energy: f32,
+    pub virial: f32,
+}
+
+impl LennardJones {
+    pub fn new(width: f32, height: f32) -> Self {
+        let mut hash = SpatialHash::new(CUTOFF, 1, 1);
+        hash.resize_area(CUTOFF, width, height);
+        LennardJones {
+            hash,
+            acceleration: Vec::new(),
+            width,
+            height,
+            periodic: false,
+            xi: 0.0,
+            potential_energy: 0.0,
+            virial: 0.0,
+        }
+    }
+
+    /// Changes the box. Forces must be recomputed with `prepare` before the next step.
+    pub fn resize(&mut self, width: f32, height: f32) {
+        self.width = width;
+        self.height = height;
+        self.hash.resize_area(CUTOFF, width, height);
+        self.xi = 0.0;
+    }
+
+    /// Switches between reflecting and periodic walls. Forces must be recomputed with
+    /// `prepare` before the next step.
+    pub fn set_periodic(&mut self, periodic: bool) {
+        self.periodic = periodic;
+        self.hash.set_periodic(periodic, periodic);
+    }
+
+    pub fn is_periodic(&self) -> bool {
+        self.periodic
+    }
+
+    /// Sets the Nosé-Hoover friction back to zero, so switching thermostats does not kick
+    /// the system.
+    pub fn reset_thermostat(&mut self) {
+        self.xi = 0.0;
+    }
+
+    /// Evaluates forces for the current positions, needed once before the first step.
+    pub fn prepare(&mut self, system: &ParticleSystem) {
+        self.compute_forces(system);
+    }
+
+    fn compute_forces(&mut self, system: &ParticleSystem) {
+        self.hash.build(system.positions());
+        let cutoff_sq = CUTOFF * CUTOFF;
+        let inv_c6 = 1.0 / cutoff_sq.powi(3);
+        let shift = 4.0 * (inv_c6 * inv_c6 - inv_c6);
+        let (hash, s) = (&self.hash, system);
+        self.acceleration.resize(s.len(), (0.0, 0.0));
+        // Each pair is visited from both ends, so energy and virial are halved below
+        let (energy, virial) = self
+            .acceleration
+            .par_iter_mut()
+            .enumerate()
+            .map(|(i, a)| {
+                let (mut fx, mut fy, mut energy, mut virial) = (0.0, 0.0, 0.0, 0.0);
+                hash.for_each_neighbour(s.x[i], s.y[i], |j| {
+                    let (dx, dy) = hash.minimum_image(s.x[i] - s.x[j], s.y[i] - s.y[j]);
+                    let r_sq = dx * dx + dy * dy;
+                    if j == i || r_sq >= cutoff_sq {
+                        return;
+                    }
+                    // Keeps overlapping particles from producing infinities
+                    let r_sq = r_sq.max(0.25);
+                    let inv_r2 = 1.0 / r_sq;
+                    let inv_r6 = inv_r2 * inv_r2 * inv_r2;
+                    // F·r / r² = 24 (2/r¹² - 1/r⁶) / r²
+                    let f_over_r = 24.0 * inv_r6 * (2.0 * inv_r6 - 1.0) * inv_r2;
+                    fx += f_over_r * dx;
+                    fy += f_over_r * dy;
+                    energy += 4.0 * inv_r6 * (inv_r6 - 1.0) - shift;
+                    virial += f_over_r * r_sq;
+                });
+                let inv_m = s.inverse_mass(i);
+                *a = (fx * inv_m, fy * inv_m);
+                (energy, virial)
+            })
+            .reduce(|| (0.0, 0.0), |a, b| (a.0 + b.0, a.1 + b.1));
+        self.potential_energy = 0.5 * energy;
+        self.virial = 0.5 * virial;
+    }
+
+    /// One velocity-Verlet step of length `dt`, with the thermostat holding `target`.
+    pub fn step(
+        &mut self,
+        system: &mut ParticleSystem,
+        dt: f32,
+        thermostat: Thermostat,
+        target: f32,
+    ) {
+        if self.acceleration.len() != system.len() {
+            self.compute_forces(system);
+        }
+        let half = 0.5 * dt;
+        let xi = match thermostat {
+            Thermostat::NoseHoover { .. } => self.xi,
+            _ => 0.0,
+        };
+
+        for (i, &(ax, ay)) in self.acceleration.iter().enumerate() {
+            system.vx[i] += half * (ax - xi * system.vx[i]);
+            system.vy[i] += half * (ay - xi * system.vy[i]);
+            system.x[i] += system.vx[i] * dt;
+            system.y[i] += system.vy[i] * dt;
+            if self.periodic {
+                system.x[i] = system.x[i].rem_euclid(self.width);
+                system.y[i] = system.y[i].rem_euclid(self.height);
+            } else {
+                self.reflect(system, i);
+            }
+        }
+
+        if let Thermostat::NoseHoover { tau } = thermostat {
+            // dξ/dt = (T / T₀ - 1) / τ², driven by the half-step temperature
+            let t = temperature(system);
+            self.xi += dt * (t / target.max(1e-6) - 1.0) / (tau * tau);
+        }
+
+        self.compute_forces(system);
+        let xi = match thermostat {
+            Thermostat::NoseHoover { .. } => self.xi,
+            _ => 0.0,
+        };
+        let damping = 1.0 / (1.0 + half * xi);
+        for (i, &(ax, ay)) in self.acceleration.iter().enumerate() {
+            system.vx[i] = (system.vx[i] + half * ax) * damping;
+            system.vy[i] = (system.vy[i] + half * ay) * damping;
+        }
+
+        if let Thermostat::Berendsen { tau } = thermostat {
+            let t = temperature(system);
+            if t > 0.0 {
+                let scale = (1.0 + dt / tau * (target / t - 1.0)).max(0.0).sqrt();
+                for (vx, vy) in system.vx.iter_mut().zip(system.vy.iter_mut()) {
+                    *vx *= scale;
+                    *vy *= scale;
+                }
+            }
+        }
+    }
+
+    fn reflect(&self, system: &mut ParticleSystem, i: usize) {
+        if system.x[i] < 0.0 {
+            system.x[i] = -system.x[i];
+            system.vx[i] = system.vx[i].abs();
+        } else if system.x[i] > self.width {
+            system.x[i] = 2.0 * self.width - system.x[i];
+            system.vx[i] = -system.vx[i].abs();
+        }
+        if system.y[i] < 0.0 {
+            system.y[i] = -system.y[i];
+            system.vy[i] = system.vy[i].abs();
+        } else if system.y[i] > self.height {
+            system.y[i] = 2.0 * self.height - system.y[i];
+            system.vy[i] = -system.vy[i].abs();
+        }
+    }
+
+    /// Virial pressure in 2D: P A = N T + ½ Σ r·F.
+    pub fn pressure(&self, system: &ParticleSystem) -> f32 {
+        let area = self.width * self.height;
+        (system.len() as f32 * temperature(system) + 0.5 * self.virial) / area
+    }
+
+    /// Accumulates pair distances below `r_max` into `counts` and returns the normalisation
+    /// that turns a count in bin `k` into g(r): divide by `norm * (k + 0.5)`.
+    pub fn pair_histogram(&self, system: &ParticleSystem, r_max: f32, counts: &mut [f32]) -> f32 {
+        let bins = counts.len();
+        let bin_width = r_max / bins as f32;
+        self.hash.for_each_pair(|i, j| {
+            let (dx, dy) = self
+                .hash
+                .minimum_image(system.x[i] - system.x[j], system.y[i] - system.y[j]);
+            let r = dx.hypot(dy);
+            if r < r_max {
+                counts[(r / bin_width) as usize] += 2.0;
+            }
+        });
+        // Ideal-gas count in the ring at r: N ρ 2π r dr, with r = (k + 0.5) dr
+        let n = system.len() as f32;
+        let density = n / (self.width * self.height);
+        n * density * 2.0 * std::f32::consts::PI * bin_width * bin_width
+    }
+}
+
+/// Kinetic temperature in 2D, with two degrees of freedom per particle.
+pub fn temperature(system: &ParticleSystem) -> f32 {
+    if system.is_empty() {
+        return 0.0;
+    }
+    let kinetic: f32 = (0..system.len())
+        .map(|i| system.mass[i] * (system.vx[i] * system.vx[i] + system.vy[i] * system.vy[i]))
+        .sum();
+    0.5 * kinetic / system.len() as f32
+}
@@ -0,0 +1,37 @@
+/// Pseudo-random value in `[-1, 1]` for an integer lattice point.
+fn lattice(x: i32, y: i32, z: i32, seed: u32) -> f32 {
+    let mut h = seed
+        ^ (x as u32).wrapping_mul(0x8da6_b343)
+        ^ (y as u32).wrapping_mul(0xd816_3841)
+        ^ (z as u32).wrapping_mul(0xcb1a_b31f);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0x5bd1_e995);
+    h ^= h >> 15;
+    (h as f32 / u32::MAX as f32) * 2.0 - 1.0
+}
+
+fn smoothstep(t: f32) -> f32 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+/// Smooth 3D value noise in `[-1, 1]` with features about one unit apart. Different seeds give
+/// independent patterns.
+pub fn value_noise3(x: f32, y: f32, z: f32, seed: u32) -> f32 {
+    let (x0, y0, z0) = (x.floor(), y.floor(), z.floor());
+    let (tx, ty, tz) = (smoothstep(x - x0), smoothstep(y - y0), smoothstep(z - z0));
+    let (ix, iy, iz) = (x0 as i32, y0 as i32, z0 as i32);
+
+    let corner = |dx: i32, dy: i32, dz: i32| lattice(ix + dx, iy + dy, iz + dz, seed);
+    let plane = |dz: i32| {
+        lerp(
+            lerp(corner(0, 0, dz), corner(1, 0, dz), tx),
+            lerp(corner(0, 1, dz), corner(1, 1, dz), tx),
+            ty,
+        )
+    };
+    lerp(plane(0), plane(1), tz)
+}
@@ -0,0 +1,119 @@
+use crate::models::particle_system::WallImpulses;
+use std::collections::VecDeque;
+
+/// Wall impulses are averaged over windows this long, in seconds of simulated time, before a
+/// pressure is reported.
+const WINDOW: f32 = 0.5;
+/// Samples kept for the P-V diagram.
+pub const PV_HISTORY: usize = 600;
+
+/// One averaged measurement.
+#[derive(Clone, Copy)]
+pub struct PvSample {
+    pub time: f32,
+    /// Area of the container, the 2D volume
+    pub volume: f32,
+    /// Force per unit wall length
+    pub pressure: f32,
+    /// Thermal kinetic energy per particle, kT
+    pub temperature: f32,
+    pub particles: usize,
+}
+
+impl PvSample {
+    /// P V / N kT, which is 1 for an ideal gas and grows as the discs fill the container.
+    pub fn compressibility(&self) -> f32 {
+        let nkt = self.particles as f32 * self.temperature;
+        if nkt > 0.0 {
+            self.pressure * self.volume / nkt
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Turns the momentum that particles hand to the walls of a rectangular container into
+/// pressures. The container may change width while a window is open, as with a moving
+/// piston; its width and area are then averaged over the window.
+pub struct PressureGauge {
+    /// Pressure on the left, right, top and bottom walls over the last full window
+    pub walls: [f32; 4],
+    pub history: VecDeque<PvSample>,
+    window: WallImpulses,
+    window_time: f32,
+    /// Width integrated over the window
+    window_width: f32,
+    time: f32,
+}
+
+impl PressureGauge {
+    pub fn new() -> Self {
+        PressureGauge {
+            walls: [0.0; 4],
+            history: VecDeque::with_capacity(PV_HISTORY),
+            window: WallImpulses::default(),
+            window_time: 0.0,
+            window_width: 0.0,
+            time: 0.0,
+        }
+    }
+
+    /// Forgets the measurements, for example after the container or its contents changed.
+    pub fn clear(&mut self) {
+        self.walls = [0.0; 4];
+        self.history.clear();
+        self.window = WallImpulses::default();
+        self.window_time = 0.0;
+        self.window_width = 0.0;
+    }
+
+    /// Latest averaged measurement, if a window has completed.
+    pub fn latest(&self) -> Option<&PvSample> {
+        self.history.back()
+    }
+
+    /// Adds the impulses delivered over `dt` seconds to a container `width` by `height`.
+    /// `temperature` and `particles` are attached to the sample closing the window.
+    pub fn record(
+        &mut self,
+        impulses: WallImpulses,
+        dt: f32,
+        (width, height): (f32, f32),
+        temperature: f32,
+        particles: usize,
+    ) {
+        if dt <= 0.0 {
+            return;
+        }
+        self.time += dt;
+        self.window += impulses;
+        self.window_time += dt;
+        self.window_width += width * dt;
+        if self.window_time < WINDOW {
+            return;
+        }
+
+        let t = self.window_time;
+        let width = self.window_width / t;
+        let w = &self.window;
+        self.walls = [
+            w.left / (t * height),
+            w.right / (t * height),
+            w.top / (t * width),
+            w.bottom / (t * width),
+        ];
+        if self.history.len() == PV_HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back(PvSample {
+            time: self.time,
+            volume: width * height,
+            pressure: w.total() / (t * 2.0 * (width + height)),
+            temperature,
+            particles,
+        });
+        self.window = WallImpulses::default();
+        self.window_time = 0.0;
+        self.window_width = 0.0;
+    }
+}
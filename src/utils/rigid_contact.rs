@@ -0,0 +1,218 @@
+use crate::models::obstacle::closest_on_segment;
+use crate::models::rigid_body::{add, dot, length, scale, sub, BodyShape, RigidBody, Vec2};
+
+/// One point where two bodies touch.
+#[derive(Clone, Copy, Debug)]
+pub struct ContactPoint {
+    pub point: Vec2,
+    /// Overlap along the normal
+    pub depth: f32,
+    /// Names the pair of features that made the point, so that it can be recognised in the
+    /// next step and its impulses reused
+    pub id: u32,
+}
+
+/// Contact between two bodies: a shared normal, pointing from the first body to the second,
+/// and up to two points.
+#[derive(Clone, Debug)]
+pub struct Manifold {
+    pub normal: Vec2,
+    pub points: Vec<ContactPoint>,
+}
+
+/// Outward unit normal of the polygon edge from `a` to `b`.
+fn edge_normal(a: Vec2, b: Vec2) -> Vec2 {
+    let e = sub(b, a);
+    scale((e.1, -e.0), 1.0 / length(e).max(1e-9))
+}
+
+/// Contact between two bodies, if they overlap.
+pub fn collide(a: &RigidBody, b: &RigidBody) -> Option<Manifold> {
+    match (&a.shape, &b.shape) {
+        (BodyShape::Circle { radius: ra }, BodyShape::Circle { radius: rb }) => {
+            circles(a.position, *ra, b.position, *rb)
+        }
+        (BodyShape::Polygon { .. }, BodyShape::Circle { radius }) => {
+            polygon_circle(&a.world_points(), b.position, *radius)
+        }
+        (BodyShape::Circle { radius }, BodyShape::Polygon { .. }) => {
+            let mut manifold = polygon_circle(&b.world_points(), a.position, *radius)?;
+            manifold.normal = scale(manifold.normal, -1.0);
+            Some(manifold)
+        }
+        (BodyShape::Polygon { .. }, BodyShape::Polygon { .. }) => {
+            polygons(&a.world_points(), &b.world_points())
+        }
+    }
+}
+
+fn circles(ca: Vec2, ra: f32, cb: Vec2, rb: f32) -> Option<Manifold> {
+    let d = sub(cb, ca);
+    let distance = length(d);
+    if distance >= ra + rb {
+        return None;
+    }
+    let normal = if distance > 1e-6 {
+        scale(d, 1.0 / distance)
+    } else {
+        (0.0, -1.0)
+    };
+    let depth = ra + rb - distance;
+    Some(Manifold {
+        normal,
+        points: vec![ContactPoint {
+            point: add(ca, scale(normal, ra - 0.5 * depth)),
+            depth,
+            id: 0,
+        }],
+    })
+}
+
+/// Contact between a polygon and a circle, with the normal pointing towards the circle.
+fn polygon_circle(corners: &[Vec2], centre: Vec2, radius: f32) -> Option<Manifold> {
+    let n = corners.len();
+    // Face the centre is furthest outside of
+    let (face, separation) = (0..n)
+        .map(|k| {
+            let normal = edge_normal(corners[k], corners[(k + 1) % n]);
+            (k, dot(normal, sub(centre, corners[k])))
+        })
+        .max_by(|p, q| p.1.total_cmp(&q.1))?;
+    if separation > radius {
+        return None;
+    }
+    if separation <= 0.0 {
+        // Centre inside: push out through that face
+        let normal = edge_normal(corners[face], corners[(face + 1) % n]);
+        return Some(Manifold {
+            normal,
+            points: vec![ContactPoint {
+                point: sub(centre, scale(normal, separation)),
+                depth: radius - separation,
+                id: face as u32,
+            }],
+        });
+    }
+    let (k, closest) = (0..n)
+        .map(|k| {
+            (
+                k,
+                closest_on_segment(centre, corners[k], corners[(k + 1) % n]),
+            )
+        })
+        .min_by(|p, q| length(sub(p.1, centre)).total_cmp(&length(sub(q.1, centre))))?;
+    let d = sub(centre, closest);
+    let distance = length(d);
+    if distance >= radius || distance < 1e-6 {
+        return None;
+    }
+    Some(Manifold {
+        normal: scale(d, 1.0 / distance),
+        points: vec![ContactPoint {
+            point: closest,
+            depth: radius - distance,
+            id: k as u32,
+        }],
+    })
+}
+
+/// Edge of `a` along whose normal the polygons overlap least, and the signed separation
+/// along it: positive means a gap, so the polygons do not touch.
+fn max_separation(a: &[Vec2], b: &[Vec2]) -> (usize, f32) {
+    (0..a.len())
+        .map(|k| {
+            let normal = edge_normal(a[k], a[(k + 1) % a.len()]);
+            let deepest = b
+                .iter()
+                .map(|&v| dot(normal, sub(v, a[k])))
+                .fold(f32::MAX, f32::min);
+            (k, deepest)
+        })
+        .max_by(|p, q| p.1.total_cmp(&q.1))
+        .unwrap_or((0, f32::MAX))
+}
+
+/// Keeps the part of the segment `points` on the inner side of the plane through `origin`
+/// with outward `normal`.
+fn clip(points: [(Vec2, u32); 2], normal: Vec2, origin: Vec2) -> Option<[(Vec2, u32); 2]> {
+    let d0 = dot(normal, sub(points[0].0, origin));
+    let d1 = dot(normal, sub(points[1].0, origin));
+    match (d0 <= 0.0, d1 <= 0.0) {
+        (true, true) => Some(points),
+        (false, false) => None,
+        (inside0, _) => {
+            let t = d0 / (d0 - d1);
+            // The new point keeps the name of the corner it replaces, so that the contact is
+            // still recognised when the corner slides in and out past the side of the face
+            let crossing = (
+                add(points[0].0, scale(sub(points[1].0, points[0].0), t)),
+                points[usize::from(inside0)].1,
+            );
+            Some(if inside0 {
+                [points[0], crossing]
+            } else {
+                [crossing, points[1]]
+            })
+        }
+    }
+}
+
+/// Contact between two convex polygons by the separating axis test. The face of least
+/// overlap becomes the reference face; the most anti-parallel edge of the other polygon is
+/// clipped against the sides of the reference face, and the clipped points below the face
+/// become the contact points.
+fn polygons(a: &[Vec2], b: &[Vec2]) -> Option<Manifold> {
+    let (edge_a, separation_a) = max_separation(a, b);
+    if separation_a > 0.0 {
+        return None;
+    }
+    let (edge_b, separation_b) = max_separation(b, a);
+    if separation_b > 0.0 {
+        return None;
+    }
+    // Prefer the first polygon's face unless the other is clearly better, so the choice does
+    // not flicker between steps
+    let flip = separation_b > separation_a + 0.05;
+    let (reference, incident, edge) = if flip { (b, a, edge_b) } else { (a, b, edge_a) };
+
+    let (r0, r1) = (reference[edge], reference[(edge + 1) % reference.len()]);
+    let normal = edge_normal(r0, r1);
+    let m = incident.len();
+    let incident_edge = (0..m)
+        .min_by(|&p, &q| {
+            let np = dot(normal, edge_normal(incident[p], incident[(p + 1) % m]));
+            let nq = dot(normal, edge_normal(incident[q], incident[(q + 1) % m]));
+            np.total_cmp(&nq)
+        })
+        .unwrap_or(0);
+    let segment = [
+        (incident[incident_edge], incident_edge as u32),
+        (
+            incident[(incident_edge + 1) % m],
+            ((incident_edge + 1) % m) as u32,
+        ),
+    ];
+
+    let tangent = scale(sub(r1, r0), 1.0 / length(sub(r1, r0)).max(1e-9));
+    let clipped = clip(segment, scale(tangent, -1.0), r0)?;
+    let clipped = clip(clipped, tangent, r1)?;
+
+    let points: Vec<ContactPoint> = clipped
+        .iter()
+        .filter_map(|&(point, feature)| {
+            let separation = dot(normal, sub(point, r0));
+            (separation <= 0.0).then(|| ContactPoint {
+                point,
+                depth: -separation,
+                id: (edge as u32) << 16 | feature << 1 | u32::from(flip),
+            })
+        })
+        .collect();
+    if points.is_empty() {
+        return None;
+    }
+    Some(Manifold {
+        normal: if flip { scale(normal, -1.0) } else { normal },
+        points,
+    })
+}
@@ -0,0 +1,368 @@
+use crate::models::force_field::GRAVITY;
+use crate::models::rigid_body::{add, cross, dot, length, scale, sub, RigidBody, Vec2};
+use crate::utils::rigid_contact::collide;
+use crate::utils::sleep::{Sleep, SleepState};
+use crate::utils::spatial_hash::pair_mut;
+use sdl2::gfx::primitives::DrawRenderer;
+use sdl2::render::{Canvas, RenderTarget};
+use std::collections::HashMap;
+
+/// Fraction of the remaining overlap removed per step.
+const BAUMGARTE: f32 = 0.2;
+/// Overlap left alone, in px, so that resting contacts stay touching from step to step.
+const SLOP: f32 = 0.5;
+/// Most overlap corrected in one step, in px, so that bodies dropped into each other are
+/// eased apart instead of shot apart.
+const MAX_CORRECTION: f32 = 4.0;
+/// Impacts slower than this, in px/s, do not bounce, so resting bodies do not jitter.
+const RESTITUTION_THRESHOLD: f32 = 60.0;
+/// Bodies slower than this on average, in px/s, count as resting, and fall asleep after
+/// resting this many seconds. Sleepers wake when hit faster than `WAKE_SPEED`.
+const SLEEP_SPEED: f32 = 4.0;
+const WAKE_SPEED: f32 = 20.0;
+const SLEEP_TIME: f32 = 0.5;
+
+/// Solver state of one contact point.
+struct PointConstraint {
+    id: u32,
+    point: Vec2,
+    /// Offsets of the point from the two centres of mass
+    ra: Vec2,
+    rb: Vec2,
+    normal_mass: f32,
+    tangent_mass: f32,
+    /// Separating velocity asked for: overlap correction plus bounce
+    bias: f32,
+    /// Impulses accumulated over the iterations, clamped as totals rather than per iteration
+    normal_impulse: f32,
+    tangent_impulse: f32,
+}
+
+/// Impulses a contact point ended a step with, kept to warm start the next step.
+#[derive(Clone, Copy)]
+struct CachedImpulse {
+    id: u32,
+    normal: f32,
+    tangent: f32,
+}
+
+struct ContactConstraint {
+    a: usize,
+    b: usize,
+    normal: Vec2,
+    friction: f32,
+    points: Vec<PointConstraint>,
+}
+
+/// Sequential-impulse contact solver for rigid bodies (Catto 2005).
+///
+/// Each step finds the touching pairs, then repeatedly applies impulses at every contact point
+/// until the bodies stop approaching, with Coulomb friction limited by the normal impulse.
+/// With warm starting the impulses found in the previous step are applied first, which lets
+/// stacks settle in a few iterations instead of slowly sinking. Sleeping bodies take part in
+/// contacts as static ones for the length of a step.
+pub struct RigidSolver {
+    pub iterations: usize,
+    pub warm_starting: bool,
+    pub gravity: Vec2,
+    pub sleep: Sleep,
+    contacts: Vec<ContactConstraint>,
+    /// Impulses of the last step, by body pair
+    cache: HashMap<(usize, usize), Vec<CachedImpulse>>,
+    /// Sleep state of each body, by index
+    states: Vec<SleepState>,
+    /// Inverse mass and inertia of the sleepers, set aside while they stand in as static
+    frozen: Vec<(usize, f32, f32)>,
+}
+
+impl RigidSolver {
+    pub fn new() -> Self {
+        RigidSolver {
+            iterations: 10,
+            warm_starting: true,
+            gravity: (0.0, GRAVITY),
+            sleep: Sleep::new(SLEEP_SPEED, WAKE_SPEED, SLEEP_TIME),
+            contacts: Vec::new(),
+            cache: HashMap::new(),
+            states: Vec::new(),
+            frozen: Vec::new(),
+        }
+    }
+
+    /// Drops the remembered impulses and wakes every body. Needed when bodies are removed,
+    /// since the cache and the sleep states refer to bodies by index.
+    pub fn forget(&mut self) {
+        self.cache.clear();
+        self.contacts.clear();
+        self.states.clear();
+    }
+
+    pub fn is_asleep(&self, i: usize) -> bool {
+        self.states.get(i).is_some_and(|s| s.is_asleep())
+    }
+
+    /// Wakes every body, for changes the sleepers would not notice, like gravity turning.
+    pub fn wake_all(&mut self) {
+        Sleep::wake_all(&mut self.states);
+    }
+
+    /// Numbers of awake and of sleeping bodies, leaving out the static ones.
+    pub fn sleep_counts(&self, bodies: &[RigidBody]) -> (usize, usize) {
+        let dynamic = bodies.iter().filter(|b| !b.is_static()).count();
+        let asleep = (0..bodies.len()).filter(|&i| self.is_asleep(i)).count();
+        (dynamic - asleep, asleep)
+    }
+
+    /// Half the diagonal of a body's bounding box, which turns an angle or an angular speed
+    /// into a distance or a speed at its rim.
+    fn extent(body: &RigidBody) -> f32 {
+        let (x0, y0, x1, y1) = body.bounds();
+        0.5 * (x1 - x0).hypot(y1 - y0)
+    }
+
+    fn speed(body: &RigidBody) -> f32 {
+        length(body.velocity) + body.angular_velocity.abs() * Self::extent(body)
+    }
+
+    /// Makes the sleepers static until `thaw`.
+    fn freeze(&mut self, bodies: &mut [RigidBody]) {
+        for (i, body) in bodies.iter_mut().enumerate() {
+            if self.states[i].is_asleep() && !body.is_static() {
+                self.frozen
+                    .push((i, body.inverse_mass, body.inverse_inertia));
+                body.inverse_mass = 0.0;
+                body.inverse_inertia = 0.0;
+            }
+        }
+    }
+
+    fn thaw(&mut self, bodies: &mut [RigidBody]) {
+        for (i, inverse_mass, inverse_inertia) in self.frozen.drain(..) {
+            bodies[i].inverse_mass = inverse_mass;
+            bodies[i].inverse_inertia = inverse_inertia;
+        }
+    }
+
+    /// Contact points found in the last step.
+    pub fn contact_count(&self) -> usize {
+        self.contacts.iter().map(|c| c.points.len()).sum()
+    }
+
+    /// Pairs whose bounding boxes overlap, found by sorting on the left edges and sweeping.
+    fn candidate_pairs(bodies: &[RigidBody]) -> Vec<(usize, usize)> {
+        let bounds: Vec<(f32, f32, f32, f32)> = bodies.iter().map(|b| b.bounds()).collect();
+        let mut order: Vec<usize> = (0..bodies.len()).collect();
+        order.sort_by(|&i, &j| bounds[i].0.total_cmp(&bounds[j].0));
+        let mut pairs = Vec::new();
+        for (k, &i) in order.iter().enumerate() {
+            for &j in &order[k + 1..] {
+                if bounds[j].0 > bounds[i].2 {
+                    break;
+                }
+                let overlap_y = bounds[i].1 <= bounds[j].3 && bounds[j].1 <= bounds[i].3;
+                if overlap_y && !(bodies[i].is_static() && bodies[j].is_static()) {
+                    pairs.push((i.min(j), i.max(j)));
+                }
+            }
+        }
+        pairs
+    }
+
+    fn find_contacts(&mut self, bodies: &[RigidBody]) {
+        self.contacts.clear();
+        for (a, b) in Self::candidate_pairs(bodies) {
+            let Some(manifold) = collide(&bodies[a], &bodies[b]) else {
+                continue;
+            };
+            let (body_a, body_b) = (&bodies[a], &bodies[b]);
+            self.contacts.push(ContactConstraint {
+                a,
+                b,
+                normal: manifold.normal,
+                friction: (body_a.friction * body_b.friction).sqrt(),
+                points: manifold
+                    .points
+                    .iter()
+                    .map(|p| PointConstraint {
+                        id: p.id,
+                        point: p.point,
+                        ra: sub(p.point, body_a.position),
+                        rb: sub(p.point, body_b.position),
+                        normal_mass: 0.0,
+                        tangent_mass: 0.0,
+                        bias: p.depth,
+                        normal_impulse: 0.0,
+                        tangent_impulse: 0.0,
+                    })
+                    .collect(),
+            });
+        }
+    }
+
+    /// Effective masses and target velocities, then warm starting. The approach speeds that
+    /// decide the bounce are all read before any impulse is applied, so that the warm start of
+    /// one contact does not look like an impact at the next.
+    fn prepare(&mut self, bodies: &mut [RigidBody], dt: f32) {
+        for contact in &mut self.contacts {
+            let (a, b) = (&bodies[contact.a], &bodies[contact.b]);
+            let n = contact.normal;
+            let restitution = a.restitution.max(b.restitution);
+            for p in &mut contact.points {
+                let effective_mass = |axis: Vec2| {
+                    let (rna, rnb) = (cross(p.ra, axis), cross(p.rb, axis));
+                    let k = a.inverse_mass
+                        + b.inverse_mass
+                        + a.inverse_inertia * rna * rna
+                        + b.inverse_inertia * rnb * rnb;
+                    if k > 0.0 {
+                        1.0 / k
+                    } else {
+                        0.0
+                    }
+                };
+                p.normal_mass = effective_mass(n);
+                p.tangent_mass = effective_mass((-n.1, n.0));
+
+                // `bias` holds the depth until now
+                let depth = p.bias;
+                let vn = dot(sub(b.velocity_at(p.point), a.velocity_at(p.point)), n);
+                p.bias = BAUMGARTE / dt * (depth - SLOP).clamp(0.0, MAX_CORRECTION);
+                if vn < -RESTITUTION_THRESHOLD {
+                    p.bias = p.bias.max(-restitution * vn);
+                }
+            }
+        }
+
+        if !self.warm_starting {
+            return;
+        }
+        for contact in &mut self.contacts {
+            let Some(cached) = self.cache.get(&(contact.a, contact.b)) else {
+                continue;
+            };
+            let (a, b) = pair_mut(bodies, contact.a, contact.b);
+            let n = contact.normal;
+            let t = (-n.1, n.0);
+            for p in &mut contact.points {
+                if let Some(c) = cached.iter().find(|c| c.id == p.id) {
+                    p.normal_impulse = c.normal;
+                    p.tangent_impulse = c.tangent;
+                    let impulse = add(scale(n, c.normal), scale(t, c.tangent));
+                    a.apply_impulse(scale(impulse, -1.0), p.ra);
+                    b.apply_impulse(impulse, p.rb);
+                }
+            }
+        }
+    }
+
+    fn apply_impulses(&mut self, bodies: &mut [RigidBody]) {
+        for contact in &mut self.contacts {
+            let (a, b) = pair_mut(bodies, contact.a, contact.b);
+            let n = contact.normal;
+            let t = (-n.1, n.0);
+            for p in &mut contact.points {
+                // Friction first, limited by the normal impulse of the previous iteration
+                let dv = sub(b.velocity_at(p.point), a.velocity_at(p.point));
+                let limit = contact.friction * p.normal_impulse;
+                let total = (p.tangent_impulse - p.tangent_mass * dot(dv, t)).clamp(-limit, limit);
+                let change = total - p.tangent_impulse;
+                p.tangent_impulse = total;
+                a.apply_impulse(scale(t, -change), p.ra);
+                b.apply_impulse(scale(t, change), p.rb);
+
+                let dv = sub(b.velocity_at(p.point), a.velocity_at(p.point));
+                let total = (p.normal_impulse + p.normal_mass * (p.bias - dot(dv, n))).max(0.0);
+                let change = total - p.normal_impulse;
+                p.normal_impulse = total;
+                a.apply_impulse(scale(n, -change), p.ra);
+                b.apply_impulse(scale(n, change), p.rb);
+            }
+        }
+    }
+
+    /// Advances the bodies by `dt`.
+    pub fn step(&mut self, bodies: &mut [RigidBody], dt: f32) {
+        if dt <= 0.0 {
+            return;
+        }
+        self.states.resize(bodies.len(), SleepState::default());
+        // Sleepers given a velocity since the last step, by a grab for instance, wake first
+        let pushed = (0..bodies.len())
+            .filter(|&i| bodies[i].velocity != (0.0, 0.0) || bodies[i].angular_velocity != 0.0);
+        self.sleep.wake(&mut self.states, pushed);
+
+        // Sleepers are static to the rest, so pairs of them are not even looked at. Bodies
+        // that hit a sleeper hard enough wake its island, which is then solved with them.
+        self.freeze(bodies);
+        self.find_contacts(bodies);
+        let hits = self.contacts.iter().map(|c| (c.a, c.b));
+        let speeds: Vec<f32> = bodies.iter().map(Self::speed).collect();
+        if self
+            .sleep
+            .wake_touched(&mut self.states, |i| speeds[i], hits)
+        {
+            self.thaw(bodies);
+            self.freeze(bodies);
+            self.find_contacts(bodies);
+        }
+
+        for body in bodies.iter_mut().filter(|b| !b.is_static()) {
+            body.velocity = add(body.velocity, scale(self.gravity, dt));
+        }
+        self.prepare(bodies, dt);
+        for _ in 0..self.iterations {
+            self.apply_impulses(bodies);
+        }
+        for body in bodies.iter_mut().filter(|b| !b.is_static()) {
+            body.position = add(body.position, scale(body.velocity, dt));
+            body.angle += body.angular_velocity * dt;
+        }
+
+        self.cache.clear();
+        for contact in &self.contacts {
+            self.cache.insert(
+                (contact.a, contact.b),
+                contact
+                    .points
+                    .iter()
+                    .map(|p| CachedImpulse {
+                        id: p.id,
+                        normal: p.normal_impulse,
+                        tangent: p.tangent_impulse,
+                    })
+                    .collect(),
+            );
+        }
+        self.thaw(bodies);
+
+        let pairs = self.contacts.iter().map(|c| (c.a, c.b));
+        self.sleep.update(
+            &mut self.states,
+            |i| {
+                let body = &bodies[i];
+                let turn = body.angle * Self::extent(body);
+                (!body.is_static()).then_some([body.position.0, body.position.1, turn])
+            },
+            pairs,
+            dt,
+        );
+        for (body, state) in bodies.iter_mut().zip(&self.states) {
+            if state.is_asleep() {
+                body.velocity = (0.0, 0.0);
+                body.angular_velocity = 0.0;
+            }
+        }
+    }
+
+    /// Marks the contact points of the last step, with a tick along each normal.
+    pub fn render_contacts<T: RenderTarget>(&self, canvas: &mut Canvas<T>) {
+        for contact in &self.contacts {
+            for p in &contact.points {
+                let (x, y) = (p.point.0 as i16, p.point.1 as i16);
+                let tip = add(p.point, scale(contact.normal, 8.0));
+                let _ = canvas.filled_circle(x, y, 2, (255, 80, 80, 255));
+                let _ = canvas.line(x, y, tip.0 as i16, tip.1 as i16, (255, 80, 80, 255));
+            }
+        }
+    }
+}
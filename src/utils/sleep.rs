@@ -0,0 +1,244 @@
+use std::collections::{HashMap, HashSet};
+
+/// Where a body is, for telling whether it rests: its centre and its angle times its size, so
+/// that turning in place counts as moving. Particles, which do not turn, leave the angle at 0.
+pub type Pose = [f32; 3];
+
+/// How long a body has been resting, and whether it sleeps.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct SleepState {
+    /// Time spent near `anchor`, frozen while asleep
+    pub timer: f32,
+    /// Pose the body came to rest at
+    pub anchor: Pose,
+    /// Island the body fell asleep with, `None` while awake
+    pub island: Option<u32>,
+}
+
+impl SleepState {
+    pub fn is_asleep(&self) -> bool {
+        self.island.is_some()
+    }
+}
+
+/// Puts resting bodies to sleep so that they are neither moved nor collided with each other.
+///
+/// A body rests while its average velocity since it came to rest stays below
+/// `speed_threshold`, that is while it stays within `speed_threshold * time_to_sleep` of where
+/// it came to rest. The instantaneous velocity is no guide in a pile held up by positional
+/// correction, whose particles bounce in place at a good fraction of g·dt per layer.
+///
+/// Bodies that have rested for `time_to_sleep` and touch each other form an island, found with
+/// a union-find over the contact pairs, and fall asleep together or join the sleeping island
+/// they touch. Sleeping bodies are immovable to the rest, like the ground, so the quiet parts
+/// of a pile go to sleep first and take the weight off the rest, which then settles too. A
+/// sleeping island wakes as a whole when an awake body faster than `wake_speed` touches it, or
+/// when the caller wakes one of its bodies. Bodies are identified by index into the slice of
+/// states, which callers keep in step with their bodies.
+pub struct Sleep {
+    pub enabled: bool,
+    /// Average speed, in px/s, below which a body counts as resting
+    pub speed_threshold: f32,
+    /// Speed, in px/s, above which a body touching a sleeping island wakes it. Bodies resting
+    /// on a sleeping island pick up some speed from gravity every step, which must not count.
+    pub wake_speed: f32,
+    /// Seconds a body must rest before it sleeps
+    pub time_to_sleep: f32,
+    parent: Vec<usize>,
+    next_island: u32,
+    islands: usize,
+    // Scratch buffers, kept so that a frame does not allocate
+    /// Label of the group whose union-find root is at each slot
+    labels: Vec<Option<u32>>,
+    /// First body met of each sleeping island
+    first_of_island: HashMap<u32, usize>,
+    /// Islands being woken
+    woken: HashSet<u32>,
+    /// Sleepers touched hard enough to wake
+    touched: Vec<usize>,
+}
+
+impl Sleep {
+    pub fn new(speed_threshold: f32, wake_speed: f32, time_to_sleep: f32) -> Self {
+        Sleep {
+            enabled: true,
+            speed_threshold,
+            wake_speed,
+            time_to_sleep,
+            parent: Vec::new(),
+            next_island: 0,
+            islands: 0,
+            labels: Vec::new(),
+            first_of_island: HashMap::new(),
+            woken: HashSet::new(),
+            touched: Vec::new(),
+        }
+    }
+
+    /// Number of sleeping islands after the last `update`.
+    pub fn islands(&self) -> usize {
+        self.islands
+    }
+
+    /// Numbers of awake and of sleeping bodies.
+    pub fn counts(states: &[SleepState]) -> (usize, usize) {
+        let asleep = states.iter().filter(|s| s.is_asleep()).count();
+        (states.len() - asleep, asleep)
+    }
+
+    /// Wakes every body and restarts their timers.
+    pub fn wake_all(states: &mut [SleepState]) {
+        for state in states {
+            state.island = None;
+            state.timer = 0.0;
+        }
+    }
+
+    /// Wakes the given bodies, if asleep, with their islands, which then have to rest for
+    /// `time_to_sleep` again. Returns whether any woke.
+    pub fn wake(&mut self, states: &mut [SleepState], bodies: impl Iterator<Item = usize>) -> bool {
+        self.woken.clear();
+        self.woken.extend(bodies.filter_map(|i| states[i].island));
+        if self.woken.is_empty() {
+            return false;
+        }
+        for state in states.iter_mut() {
+            if state
+                .island
+                .is_some_and(|island| self.woken.contains(&island))
+            {
+                state.island = None;
+                state.timer = 0.0;
+            }
+        }
+        true
+    }
+
+    /// Wakes the sleeping islands touched in `pairs` by awake bodies faster than `wake_speed`.
+    /// Returns whether any woke.
+    pub fn wake_touched<S>(
+        &mut self,
+        states: &mut [SleepState],
+        speed: S,
+        pairs: impl Iterator<Item = (usize, usize)>,
+    ) -> bool
+    where
+        S: Fn(usize) -> f32,
+    {
+        if !self.enabled {
+            return false;
+        }
+        let hit = |sleeper: usize, other: usize| {
+            (states[sleeper].is_asleep() && !states[other].is_asleep())
+                .then_some(sleeper)
+                .filter(|_| speed(other) > self.wake_speed)
+        };
+        let mut touched = std::mem::take(&mut self.touched);
+        touched.clear();
+        touched.extend(pairs.filter_map(|(i, j)| hit(i, j).or_else(|| hit(j, i))));
+        let woke = self.wake(states, touched.iter().copied());
+        self.touched = touched;
+        woke
+    }
+
+    /// Advances the timers by `dt`, then groups the bodies that have rested long enough into
+    /// islands through `pairs` and puts them to sleep. `pose(i)` is `None` for bodies that
+    /// never move, like walls and ground, which neither sleep nor join islands, so a pile
+    /// resting on the ground is its own island.
+    pub fn update<P>(
+        &mut self,
+        states: &mut [SleepState],
+        pose: P,
+        pairs: impl Iterator<Item = (usize, usize)>,
+        dt: f32,
+    ) where
+        P: Fn(usize) -> Option<Pose>,
+    {
+        if !self.enabled {
+            Self::wake_all(states);
+            self.islands = 0;
+            return;
+        }
+
+        let drift = self.speed_threshold * self.time_to_sleep;
+        for (i, state) in states.iter_mut().enumerate() {
+            let Some(pose) = pose(i).filter(|_| !state.is_asleep()) else {
+                continue;
+            };
+            let moved_sq: f32 = pose
+                .iter()
+                .zip(&state.anchor)
+                .map(|(p, a)| (p - a) * (p - a))
+                .sum();
+            if moved_sq > drift * drift {
+                state.timer = 0.0;
+                state.anchor = pose;
+            } else {
+                state.timer += dt;
+            }
+        }
+
+        // Bodies that have rested long enough join each other and the sleeping islands they
+        // touch. Sleeping islands have no contacts among themselves, so their bodies are joined
+        // by label.
+        let time_to_sleep = self.time_to_sleep;
+        let ready = |state: &SleepState| state.timer >= time_to_sleep || state.is_asleep();
+        self.parent.clear();
+        self.parent.extend(0..states.len());
+        for (i, j) in pairs {
+            if ready(&states[i]) && ready(&states[j]) {
+                self.union(i, j);
+            }
+        }
+        self.first_of_island.clear();
+        for (i, state) in states.iter().enumerate() {
+            if let Some(island) = state.island {
+                let first = *self.first_of_island.entry(island).or_insert(i);
+                self.union(first, i);
+            }
+        }
+
+        // Each group keeps the label of one of the islands in it, or gets a new one
+        self.labels.clear();
+        self.labels.resize(states.len(), None);
+        self.islands = 0;
+        for (i, state) in states.iter().enumerate() {
+            if let Some(island) = state.island {
+                let root = self.find(i);
+                if self.labels[root].replace(island).is_none() {
+                    self.islands += 1;
+                }
+            }
+        }
+        for (i, state) in states.iter_mut().enumerate() {
+            if ready(state) {
+                let root = self.find(i);
+                let island = match self.labels[root] {
+                    Some(island) => island,
+                    None => {
+                        self.next_island = self.next_island.wrapping_add(1);
+                        self.labels[root] = Some(self.next_island);
+                        self.islands += 1;
+                        self.next_island
+                    }
+                };
+                state.island = Some(island);
+            }
+        }
+    }
+
+    fn find(&mut self, mut i: usize) -> usize {
+        while self.parent[i] != i {
+            self.parent[i] = self.parent[self.parent[i]];
+            i = self.parent[i];
+        }
+        i
+    }
+
+    fn union(&mut self, i: usize, j: usize) {
+        let (a, b) = (self.find(i), self.find(j));
+        if a != b {
+            self.parent[a] = b;
+        }
+    }
+}
@@ -0,0 +1,522 @@
+use crate::models::obstacle::Obstacle;
+use std::ops::Range;
+
+/// Uniform grid broadphase stored in flat arrays.
+///
+/// Particles are bucketed with a counting sort: `entries` holds every particle index exactly once,
+/// grouped by cell in row-major order, and `cell_start[c]..cell_start[c + 1]` is the slice of
+/// `entries` belonging to cell `c`. All buffers are reused between frames, so rebuilding does not
+/// allocate once they have grown to the particle count.
+///
+/// Static obstacles are kept in a second table of the same layout, `static_start` and
+/// `static_entries`, which only changes when the obstacles or the grid do.
+///
+/// Either axis can be periodic. Its cells are then stretched so that a whole number of them
+/// tiles the period, the cells at opposite edges count as neighbours, and distances should be
+/// taken with `minimum_image`.
+pub struct SpatialHash {
+    cell_size: f32,
+    /// Cell extents, equal to `cell_size` except along periodic axes
+    cell_w: f32,
+    cell_h: f32,
+    cols: usize,
+    rows: usize,
+    width: f32,
+    height: f32,
+    periodic: (bool, bool),
+    cell_start: Vec<u32>,
+    entries: Vec<u32>,
+    particle_cell: Vec<u32>,
+    static_start: Vec<u32>,
+    static_entries: Vec<u32>,
+}
+
+/// Smallest grid cell, used when all particles are tiny or there are none.
+pub(crate) const MIN_CELL_SIZE: f32 = 8.0;
+
+/// Half of the 8-neighbourhood, so every adjacent pair of cells is visited exactly once.
+const NEIGHBOUR_OFFSETS: [(isize, isize); 4] = [(1, 0), (-1, 1), (0, 1), (1, 1)];
+
+impl SpatialHash {
+    pub fn new(cell_size: f32, width: u32, height: u32) -> Self {
+        let mut hash = SpatialHash {
+            cell_size: 0.0,
+            cell_w: 0.0,
+            cell_h: 0.0,
+            cols: 0,
+            rows: 0,
+            width: 0.0,
+            height: 0.0,
+            periodic: (false, false),
+            cell_start: Vec::new(),
+            entries: Vec::new(),
+            particle_cell: Vec::new(),
+            static_start: Vec::new(),
+            static_entries: Vec::new(),
+        };
+        hash.resize(cell_size, width, height);
+        hash
+    }
+
+    /// Changes the cell size and covered area. Buffers are only reallocated if the grid grows.
+    /// Static obstacles are dropped and must be registered again with `build_static`.
+    pub fn resize(&mut self, cell_size: f32, width: u32, height: u32) {
+        self.resize_area(cell_size, width as f32, height as f32);
+    }
+
+    /// Same as `resize` for an area that is not a whole number of pixels, such as a box in
+    /// reduced units. Periodic axes wrap at exactly `width` and `height`.
+    pub fn resize_area(&mut self, cell_size: f32, width: f32, height: f32) {
+        self.cell_size = cell_size;
+        self.width = width;
+        self.height = height;
+        (self.cols, self.cell_w) = Self::divide_axis(cell_size, self.width, self.periodic.0);
+        (self.rows, self.cell_h) = Self::divide_axis(cell_size, self.height, self.periodic.1);
+        self.cell_start.resize(self.cols * self.rows + 1, 0);
+        self.static_start.clear();
+        self.static_entries.clear();
+    }
+
+    /// Number of cells along an axis and their extent. A periodic axis is covered exactly by
+    /// cells at least `cell_size` wide; otherwise there is a partial cell past the far edge.
+    fn divide_axis(cell_size: f32, length: f32, periodic: bool) -> (usize, f32) {
+        if periodic {
+            let count = ((length / cell_size) as usize).max(1);
+            (count, length / count as f32)
+        } else {
+            ((length / cell_size) as usize + 1, cell_size)
+        }
+    }
+
+    /// Makes the axes wrap around or not. Returns whether the grid changed, in which case
+    /// static obstacles must be registered again.
+    pub fn set_periodic(&mut self, x: bool, y: bool) -> bool {
+        if self.periodic == (x, y) {
+            return false;
+        }
+        self.periodic = (x, y);
+        self.resize_area(self.cell_size, self.width, self.height);
+        true
+    }
+
+    /// Whether neighbours wrap across the x and y seams. Axes less than three cells long never
+    /// do, since every cell along them is already adjacent to every other.
+    fn wraps(&self) -> (bool, bool) {
+        (
+            self.periodic.0 && self.cols >= 3,
+            self.periodic.1 && self.rows >= 3,
+        )
+    }
+
+    /// Shortest offset equivalent to `(dx, dy)` under the periodic axes.
+    pub fn minimum_image(&self, mut dx: f32, mut dy: f32) -> (f32, f32) {
+        if self.periodic.0 {
+            dx -= self.width * (dx / self.width).round();
+        }
+        if self.periodic.1 {
+            dy -= self.height * (dy / self.height).round();
+        }
+        (dx, dy)
+    }
+
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Cell coordinates of a point, clamped to the grid.
+    pub fn cell_of(&self, x: f32, y: f32) -> (usize, usize) {
+        let col = ((x / self.cell_w).max(0.0) as usize).min(self.cols - 1);
+        let row = ((y / self.cell_h).max(0.0) as usize).min(self.rows - 1);
+        (col, row)
+    }
+
+    /// Buckets the given positions (one per particle, in index order) into cells.
+    pub fn build<I>(&mut self, positions: I)
+    where
+        I: ExactSizeIterator<Item = (f32, f32)>,
+    {
+        let cell_count = self.cols * self.rows;
+        let n = positions.len();
+        self.cell_start.clear();
+        self.cell_start.resize(cell_count + 1, 0);
+        self.particle_cell.clear();
+        self.entries.clear();
+        self.entries.resize(n, 0);
+
+        for (x, y) in positions {
+            let (col, row) = self.cell_of(x, y);
+            let cell = row * self.cols + col;
+            self.particle_cell.push(cell as u32);
+            self.cell_start[cell] += 1;
+        }
+
+        // Inclusive prefix sum: cell_start[c] becomes the end of cell c
+        let mut running = 0;
+        for start in self.cell_start.iter_mut() {
+            running += *start;
+            *start = running;
+        }
+
+        // Walking backwards moves each end down to the start and keeps indices ascending per cell
+        for i in (0..n).rev() {
+            let cell = self.particle_cell[i] as usize;
+            self.cell_start[cell] -= 1;
+            self.entries[self.cell_start[cell] as usize] = i as u32;
+        }
+    }
+
+    /// Registers each obstacle in every cell that a particle touching it can have as its home
+    /// cell, assuming particle radii are at most half a cell as for `build`.
+    pub fn build_static(&mut self, obstacles: &[Obstacle]) {
+        // A particle touching the obstacle has its centre within half a cell of it, and that
+        // centre is within half a cell diagonal of its home cell's centre
+        let cell = self.cell_w.max(self.cell_h);
+        let reach = cell * 0.5 * (1.0 + std::f32::consts::SQRT_2);
+        let mut cell_obstacles: Vec<(u32, u32)> = Vec::new();
+        for (k, obstacle) in obstacles.iter().enumerate() {
+            let (min_x, min_y, max_x, max_y) = obstacle.bounds();
+            let (c0, r0) = self.cell_of(min_x - reach, min_y - reach);
+            let (c1, r1) = self.cell_of(max_x + reach, max_y + reach);
+            for row in r0..=r1 {
+                for col in c0..=c1 {
+                    let cx = (col as f32 + 0.5) * self.cell_w;
+                    let cy = (row as f32 + 0.5) * self.cell_h;
+                    if obstacle.contact(cx, cy, reach).is_some() {
+                        cell_obstacles.push(((row * self.cols + col) as u32, k as u32));
+                    }
+                }
+            }
+        }
+        cell_obstacles.sort_unstable();
+
+        let cell_count = self.cols * self.rows;
+        self.static_start.clear();
+        self.static_start.resize(cell_count + 1, 0);
+        self.static_entries.clear();
+        for &(cell, k) in &cell_obstacles {
+            self.static_start[cell as usize + 1] += 1;
+            self.static_entries.push(k);
+        }
+        for c in 0..cell_count {
+            self.static_start[c + 1] += self.static_start[c];
+        }
+    }
+
+    /// Obstacles registered in the home cell of a particle at `(x, y)`.
+    pub fn statics_at(&self, x: f32, y: f32) -> &[u32] {
+        if self.static_start.is_empty() {
+            return &[];
+        }
+        let (col, row) = self.cell_of(x, y);
+        let cell = row * self.cols + col;
+        &self.static_entries[self.static_start[cell] as usize..self.static_start[cell + 1] as usize]
+    }
+
+    /// Position range in `entries()` of the given cell.
+    fn cell_range(&self, col: usize, row: usize) -> Range<usize> {
+        let cell = row * self.cols + col;
+        self.cell_start[cell] as usize..self.cell_start[cell + 1] as usize
+    }
+
+    /// Position range in `entries()` covering rows `first..last` (exclusive), clamped to the grid.
+    pub fn row_span(&self, first: usize, last: usize) -> Range<usize> {
+        let first = first.min(self.rows);
+        let last = last.min(self.rows);
+        self.cell_start[first * self.cols] as usize..self.cell_start[last * self.cols] as usize
+    }
+
+    /// Indices of the cell at `index` and its neighbours along an axis of `count` cells.
+    fn neighbourhood(index: usize, count: usize, wraps: bool) -> impl Iterator<Item = usize> {
+        let (first, last) = if wraps {
+            (index + count - 1, index + count + 1)
+        } else {
+            (index.saturating_sub(1), (index + 1).min(count - 1))
+        };
+        (first..=last).map(move |k| k % count)
+    }
+
+    /// Calls `f` with every particle whose home cell is the cell of `(x, y)` or one of its eight
+    /// neighbours, which covers everything within one cell size of the point. Across a
+    /// periodic seam the neighbours are the cells at the opposite edge.
+    pub fn for_each_neighbour<F: FnMut(usize)>(&self, x: f32, y: f32, mut f: F) {
+        let (col, row) = self.cell_of(x, y);
+        let (wrap_x, wrap_y) = self.wraps();
+        for r in Self::neighbourhood(row, self.rows, wrap_y) {
+            if wrap_x {
+                for c in Self::neighbourhood(col, self.cols, true) {
+                    for &i in &self.entries[self.cell_range(c, r)] {
+                        f(i as usize);
+                    }
+                }
+                continue;
+            }
+            // Cells of a row are contiguous in `entries`, so each row is a single slice
+            let (c0, c1) = (col.saturating_sub(1), (col + 1).min(self.cols - 1));
+            let span = self.cell_range(c0, r).start..self.cell_range(c1, r).end;
+            for &i in &self.entries[span] {
+                f(i as usize);
+            }
+        }
+    }
+
+    /// Calls `f` with every particle whose home cell overlaps the box from `min` to `max`. The
+    /// box is clamped to the grid and does not wrap across periodic seams.
+    pub fn for_each_in_box<F: FnMut(usize)>(&self, min: (f32, f32), max: (f32, f32), mut f: F) {
+        let (c0, r0) = self.cell_of(min.0, min.1);
+        let (c1, r1) = self.cell_of(max.0, max.1);
+        for r in r0..=r1 {
+            let span = self.cell_range(c0, r).start..self.cell_range(c1, r).end;
+            for &i in &self.entries[span] {
+                f(i as usize);
+            }
+        }
+    }
+
+    /// Particle indices grouped by cell in row-major order.
+    pub fn entries(&self) -> &[u32] {
+        &self.entries
+    }
+
+    /// Calls `f` with the `entries()` positions of every candidate pair whose home cell lies in
+    /// `row`, checking the row itself and the row below.
+    fn for_each_row_pair<F: FnMut(usize, usize)>(&self, row: usize, mut f: F) {
+        for col in 0..self.cols {
+            let home = self.cell_range(col, row);
+            for a in home.clone() {
+                for b in (a + 1)..home.end {
+                    f(a, b);
+                }
+            }
+            for (dc, dr) in NEIGHBOUR_OFFSETS {
+                let (Some(nc), Some(nr)) = (col.checked_add_signed(dc), row.checked_add_signed(dr))
+                else {
+                    continue;
+                };
+                if nc >= self.cols || nr >= self.rows {
+                    continue;
+                }
+                let neighbour = self.cell_range(nc, nr);
+                for a in home.clone() {
+                    for b in neighbour.clone() {
+                        f(a, b);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Collects the candidate pairs whose home cell lies in `row`. Pairs are given as positions
+    /// relative to `row_span(row, row + 2).start`, so they index straight into a band gathered
+    /// in `entries()` order.
+    pub fn band_pairs(&self, row: usize, out: &mut Vec<(u32, u32)>) {
+        out.clear();
+        let offset = self.row_span(row, row + 2).start;
+        self.for_each_row_pair(row, |a, b| {
+            out.push(((a - offset) as u32, (b - offset) as u32))
+        });
+    }
+
+    /// Calls `f` with the `entries()` positions of every candidate pair whose cells are only
+    /// adjacent across a periodic seam. These are exactly the pairs the row stencil misses.
+    fn for_each_seam_pair<F: FnMut(usize, usize)>(&self, mut f: F) {
+        let (wrap_x, wrap_y) = self.wraps();
+        if !wrap_x && !wrap_y {
+            return;
+        }
+        let (cols, rows) = (self.cols as isize, self.rows as isize);
+        for row in 0..rows {
+            for col in 0..cols {
+                for (dc, dr) in NEIGHBOUR_OFFSETS {
+                    let (nc, nr) = (col + dc, row + dr);
+                    let (inside_c, inside_r) = ((0..cols).contains(&nc), (0..rows).contains(&nr));
+                    if inside_c && inside_r || !inside_c && !wrap_x || !inside_r && !wrap_y {
+                        continue;
+                    }
+                    let home = self.cell_range(col as usize, row as usize);
+                    let neighbour =
+                        self.cell_range(nc.rem_euclid(cols) as usize, nr.rem_euclid(rows) as usize);
+                    for a in home {
+                        for b in neighbour.clone() {
+                            f(a, b);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Collects the candidate pairs across periodic seams as particle indices. Their distance
+    /// must be taken with `minimum_image`.
+    pub fn seam_pairs(&self, out: &mut Vec<(u32, u32)>) {
+        out.clear();
+        self.for_each_seam_pair(|a, b| out.push((self.entries[a], self.entries[b])));
+    }
+
+    /// Calls `f` with every pair of particles in the same or adjacent cells as particle indices,
+    /// including across periodic seams. Each unordered pair appears once and never pairs a
+    /// particle with itself.
+    pub fn for_each_pair<F: FnMut(usize, usize)>(&self, mut f: F) {
+        let entries = &self.entries;
+        for row in 0..self.rows {
+            self.for_each_row_pair(row, |a, b| f(entries[a] as usize, entries[b] as usize));
+        }
+        self.for_each_seam_pair(|a, b| f(entries[a] as usize, entries[b] as usize));
+    }
+
+    /// Collects the pairs of `for_each_pair` into `out`.
+    pub fn pairs(&self, out: &mut Vec<(u32, u32)>) {
+        out.clear();
+        self.for_each_pair(|i, j| out.push((i as u32, j as u32)));
+    }
+}
+
+/// Cells must be at least as wide as the largest particle so that any two overlapping
+/// particles always land in the same or adjacent cells.
+pub(crate) fn cell_size_for_radius(max_radius: f32) -> f32 {
+    (2.0 * max_radius).max(MIN_CELL_SIZE)
+}
+
+/// Borrows two distinct elements of a slice mutably at once.
+///
+/// Panics if `i == j`, which would otherwise alias.
+pub fn pair_mut<T>(slice: &mut [T], i: usize, j: usize) -> (&mut T, &mut T) {
+    assert_ne!(i, j, "cannot borrow the same element twice");
+    if i < j {
+        let (head, tail) = slice.split_at_mut(j);
+        (&mut head[i], &mut tail[0])
+    } else {
+        let (head, tail) = slice.split_at_mut(i);
+        (&mut tail[0], &mut head[j])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::collections::HashSet;
+
+    /// At least as large as any particle the collision scene spawns
+    const MAX_RADIUS: f32 = 40.0;
+
+    struct Population {
+        positions: Vec<(f32, f32)>,
+        radii: Vec<f32>,
+    }
+
+    /// Mostly small particles with a few up to `MAX_RADIUS`, packed densely enough that many
+    /// of them overlap.
+    fn population(seed: u64, width: f32, height: f32) -> Population {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let count = 800;
+        let radii: Vec<f32> = (0..count)
+            .map(|k| {
+                if k % 50 == 0 {
+                    rng.gen_range(MAX_RADIUS / 2.0..=MAX_RADIUS)
+                } else {
+                    rng.gen_range(1.0..8.0)
+                }
+            })
+            .collect();
+        let positions = (0..count)
+            .map(|_| (rng.gen_range(0.0..width), rng.gen_range(0.0..height)))
+            .collect();
+        Population { positions, radii }
+    }
+
+    /// Every overlapping or touching pair, lower index first.
+    fn overlapping(population: &Population, hash: &SpatialHash) -> HashSet<(u32, u32)> {
+        let Population { positions, radii } = population;
+        let mut pairs = HashSet::new();
+        for i in 0..positions.len() {
+            for j in i + 1..positions.len() {
+                let (dx, dy) = hash.minimum_image(
+                    positions[j].0 - positions[i].0,
+                    positions[j].1 - positions[i].1,
+                );
+                let reach = radii[i] + radii[j];
+                if dx * dx + dy * dy <= reach * reach {
+                    pairs.insert((i as u32, j as u32));
+                }
+            }
+        }
+        pairs
+    }
+
+    fn ordered((i, j): (u32, u32)) -> (u32, u32) {
+        (i.min(j), i.max(j))
+    }
+
+    /// Checks that `pairs` lists each unordered pair once, never pairs a particle with itself
+    /// and contains every pair in `expected`.
+    fn assert_covers(pairs: &[(u32, u32)], expected: &HashSet<(u32, u32)>, what: &str) {
+        let mut seen = HashSet::new();
+        for &(i, j) in pairs {
+            assert_ne!(i, j, "{what} paired particle {i} with itself");
+            assert!(seen.insert(ordered((i, j))), "{what} repeated ({i}, {j})");
+        }
+        for pair in expected {
+            assert!(
+                seen.contains(pair),
+                "{what} missed overlapping pair {pair:?}"
+            );
+        }
+    }
+
+    /// Candidate pairs of every row's band, plus the seam pairs, as particle indices.
+    fn band_and_seam_pairs(hash: &SpatialHash) -> Vec<(u32, u32)> {
+        let (mut all, mut band) = (Vec::new(), Vec::new());
+        for row in 0..hash.rows() {
+            hash.band_pairs(row, &mut band);
+            let entries = &hash.entries()[hash.row_span(row, row + 2)];
+            all.extend(
+                band.iter()
+                    .map(|&(a, b)| (entries[a as usize], entries[b as usize])),
+            );
+        }
+        hash.seam_pairs(&mut band);
+        all.extend_from_slice(&band);
+        all
+    }
+
+    fn check(periodic: bool) {
+        let (width, height) = (640.0, 480.0);
+        for seed in 0..5 {
+            let population = population(seed, width, height);
+            let max_radius = population.radii.iter().copied().fold(0.0, f32::max);
+            let mut hash = SpatialHash::new(
+                cell_size_for_radius(max_radius),
+                width as u32,
+                height as u32,
+            );
+            hash.set_periodic(periodic, periodic);
+            hash.build(population.positions.iter().copied());
+            let expected = overlapping(&population, &hash);
+            assert!(!expected.is_empty());
+
+            let mut pairs = Vec::new();
+            hash.pairs(&mut pairs);
+            assert_covers(&pairs, &expected, "pairs()");
+            assert_covers(
+                &band_and_seam_pairs(&hash),
+                &expected,
+                "band_pairs() and seam_pairs()",
+            );
+        }
+    }
+
+    #[test]
+    fn no_overlapping_pair_is_missed() {
+        check(false);
+    }
+
+    #[test]
+    fn no_overlapping_pair_is_missed_across_periodic_seams() {
+        check(true);
+    }
+}
@@ -0,0 +1,146 @@
+use crate::models::particle_system::ParticleSystem;
+
+pub const SPEED_BINS: usize = 40;
+/// Collisions are counted over windows this long, in seconds of simulated time, before the
+/// rate is updated.
+const RATE_WINDOW: f32 = 0.5;
+/// The histogram spans this many RMS thermal speeds.
+const SPEED_RANGE: f32 = 3.0;
+
+/// Kinetic-theory view of a particle system: the distribution of thermal speeds against the
+/// 2D Maxwell–Boltzmann distribution at the same temperature, and collision statistics.
+///
+/// Speeds are taken relative to the centre-of-mass velocity, so a gas that is moving as a
+/// whole still shows its thermal distribution.
+pub struct GasStatistics {
+    /// Measured and Maxwell–Boltzmann probability densities per unit speed, one per bin
+    pub histogram: [f32; SPEED_BINS],
+    pub fitted: [f32; SPEED_BINS],
+    pub bin_width: f32,
+    /// Total kinetic energy in the lab frame
+    pub kinetic_energy: f32,
+    /// Thermal kinetic energy per particle, which is kT for two degrees of freedom
+    pub temperature: f32,
+    pub mean_speed: f32,
+    /// Collisions per second over the last full window
+    pub collision_rate: f32,
+    /// Mean speed over the collision frequency of a single particle
+    pub mean_free_path: f32,
+    /// Hard-disc prediction 1 / (2√2 n d) for the current number density and mean diameter
+    pub predicted_mean_free_path: f32,
+    window_collisions: usize,
+    window_time: f32,
+}
+
+impl GasStatistics {
+    pub fn new() -> Self {
+        GasStatistics {
+            histogram: [0.0; SPEED_BINS],
+            fitted: [0.0; SPEED_BINS],
+            bin_width: 1.0,
+            kinetic_energy: 0.0,
+            temperature: 0.0,
+            mean_speed: 0.0,
+            collision_rate: 0.0,
+            mean_free_path: f32::INFINITY,
+            predicted_mean_free_path: f32::INFINITY,
+            window_collisions: 0,
+            window_time: 0.0,
+        }
+    }
+
+    /// Adds the collisions that happened over `dt` seconds of simulated time.
+    pub fn record_collisions(&mut self, count: usize, dt: f32) {
+        if dt <= 0.0 {
+            return;
+        }
+        self.window_collisions += count;
+        self.window_time += dt;
+        if self.window_time >= RATE_WINDOW {
+            self.collision_rate = self.window_collisions as f32 / self.window_time;
+            self.window_collisions = 0;
+            self.window_time = 0.0;
+        }
+    }
+
+    /// Recomputes the distributions and derived quantities for a system filling `area`.
+    pub fn update(&mut self, system: &ParticleSystem, area: f32) {
+        self.histogram = [0.0; SPEED_BINS];
+        self.fitted = [0.0; SPEED_BINS];
+        let n = system.len();
+        if n == 0 {
+            self.kinetic_energy = 0.0;
+            self.temperature = 0.0;
+            self.mean_speed = 0.0;
+            self.mean_free_path = f32::INFINITY;
+            self.predicted_mean_free_path = f32::INFINITY;
+            return;
+        }
+
+        let total_mass: f32 = system.mass.iter().sum();
+        let (mut px, mut py, mut kinetic) = (0.0, 0.0, 0.0);
+        for i in 0..n {
+            let m = system.mass[i];
+            px += m * system.vx[i];
+            py += m * system.vy[i];
+            kinetic += 0.5 * m * (system.vx[i] * system.vx[i] + system.vy[i] * system.vy[i]);
+        }
+        self.kinetic_energy = kinetic;
+        let (cx, cy) = if total_mass > 0.0 {
+            (px / total_mass, py / total_mass)
+        } else {
+            (0.0, 0.0)
+        };
+
+        let thermal = |i: usize| (system.vx[i] - cx).hypot(system.vy[i] - cy);
+        let thermal_energy: f32 = (0..n)
+            .map(|i| 0.5 * system.mass[i] * thermal(i).powi(2))
+            .sum();
+        let kt = thermal_energy / n as f32;
+        self.temperature = kt;
+        let mean_sq: f32 = (0..n).map(|i| thermal(i).powi(2)).sum::<f32>() / n as f32;
+        self.bin_width = (SPEED_RANGE * mean_sq.sqrt() / SPEED_BINS as f32).max(1e-3);
+
+        let mut speed_sum = 0.0;
+        for i in 0..n {
+            let speed = thermal(i);
+            speed_sum += speed;
+            let bin = (speed / self.bin_width) as usize;
+            if bin < SPEED_BINS {
+                self.histogram[bin] += 1.0;
+            }
+        }
+        self.mean_speed = speed_sum / n as f32;
+        let scale = 1.0 / (n as f32 * self.bin_width);
+        for count in self.histogram.iter_mut() {
+            *count *= scale;
+        }
+
+        // Each mass has its own 2D Maxwell–Boltzmann distribution (m v / kT) exp(-m v² / 2kT);
+        // the fit is their average over the particles, all at the measured temperature
+        if kt > 0.0 {
+            for (k, fitted) in self.fitted.iter_mut().enumerate() {
+                let v = (k as f32 + 0.5) * self.bin_width;
+                let sum: f32 = system
+                    .mass
+                    .iter()
+                    .map(|&m| m * v / kt * (-m * v * v / (2.0 * kt)).exp())
+                    .sum();
+                *fitted = sum / n as f32;
+            }
+        }
+
+        // Every collision involves two particles
+        let frequency = 2.0 * self.collision_rate / n as f32;
+        self.mean_free_path = if frequency > 0.0 {
+            self.mean_speed / frequency
+        } else {
+            f32::INFINITY
+        };
+        let mean_diameter = 2.0 * system.radius.iter().sum::<f32>() / n as f32;
+        let number_density = n as f32 / area.max(1.0);
+        self.predicted_mean_free_path =
+            1.0 / (2.0 * std::f32::consts::SQRT_2 * number_density * mean_diameter);
+    }
+}
+
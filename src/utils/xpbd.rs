@@ -0,0 +1,181 @@
+use crate::models::force_field::{FieldSample, ForceFields};
+use crate::models::particle_system::ParticleSystem;
+
+/// First part of an extended position-based dynamics (XPBD) step: moves the particles with
+/// nonzero `weights` (inverse masses) along their velocities after applying the fields, and
+/// remembers where they started in `previous`. The step goes on by resetting the constraint
+/// multipliers, solving the constraints and calling `update_velocities`.
+pub fn predict(
+    system: &mut ParticleSystem,
+    weights: &[f32],
+    fields: &ForceFields,
+    dt: f32,
+    previous: &mut Vec<(f32, f32)>,
+) {
+    previous.clear();
+    previous.extend(system.positions());
+    let (gx, gy) = fields.uniform_acceleration();
+    for (i, &weight) in weights.iter().enumerate() {
+        if weight == 0.0 {
+            continue;
+        }
+        let (mut ax, mut ay) = (gx, gy);
+        if fields.has_local() {
+            let (lx, ly) = fields.local_acceleration(&FieldSample {
+                x: system.x[i],
+                y: system.y[i],
+                vx: system.vx[i],
+                vy: system.vy[i],
+                radius: system.radius[i],
+                inv_mass: weight,
+            });
+            ax += lx;
+            ay += ly;
+        }
+        system.vx[i] += ax * dt;
+        system.vy[i] += ay * dt;
+        system.x[i] += system.vx[i] * dt;
+        system.y[i] += system.vy[i] * dt;
+    }
+}
+
+/// Sets the velocities to the distance moved over the step.
+pub fn update_velocities(system: &mut ParticleSystem, previous: &[(f32, f32)], dt: f32) {
+    if dt <= 0.0 {
+        return;
+    }
+    for (i, &(x, y)) in previous.iter().enumerate() {
+        system.vx[i] = (system.x[i] - x) / dt;
+        system.vy[i] = (system.y[i] - y) / dt;
+    }
+}
+
+/// Keeps particles `i` and `j` at `rest_length` apart.
+///
+/// The compliance is the inverse of the stiffness, in px per unit force. It is divided by dt²
+/// when solved, so the stiffness does not depend on the step size or the number of
+/// iterations; zero gives a rigid rod.
+#[derive(Clone, Copy, Debug)]
+pub struct Distance {
+    pub i: usize,
+    pub j: usize,
+    pub rest_length: f32,
+    pub compliance: f32,
+    /// Only resist stretching, like a string that goes slack when pushed
+    pub tension_only: bool,
+}
+
+impl Distance {
+    /// Constraint holding `i` and `j` at their current distance.
+    pub fn new(system: &ParticleSystem, i: usize, j: usize, compliance: f32) -> Self {
+        Distance {
+            i,
+            j,
+            rest_length: (system.x[j] - system.x[i]).hypot(system.y[j] - system.y[i]),
+            compliance,
+            tension_only: false,
+        }
+    }
+
+    /// Relative stretch, positive when longer than at rest.
+    pub fn strain(&self, system: &ParticleSystem) -> f32 {
+        let length =
+            (system.x[self.j] - system.x[self.i]).hypot(system.y[self.j] - system.y[self.i]);
+        length / self.rest_length.max(1e-6) - 1.0
+    }
+
+    /// One iteration over a step of `dt`. `lambda` accumulates the multiplier over the
+    /// iterations of the step.
+    pub fn solve(&self, system: &mut ParticleSystem, weights: &[f32], dt: f32, lambda: &mut f32) {
+        let (i, j) = (self.i, self.j);
+        let (wi, wj) = (weights[i], weights[j]);
+        let (dx, dy) = (system.x[j] - system.x[i], system.y[j] - system.y[i]);
+        let length = dx.hypot(dy);
+        let alpha = self.compliance / (dt * dt);
+        if wi + wj + alpha <= 0.0 || length < 1e-6 {
+            return;
+        }
+        let c = length - self.rest_length;
+        if self.tension_only && c < 0.0 {
+            return;
+        }
+        let delta = -(c + alpha * *lambda) / (wi + wj + alpha);
+        *lambda += delta;
+        let (nx, ny) = (dx / length, dy / length);
+        system.x[i] -= nx * delta * wi;
+        system.y[i] -= ny * delta * wi;
+        system.x[j] += nx * delta * wj;
+        system.y[j] += ny * delta * wj;
+    }
+}
+
+/// Signed area enclosed by the closed polygon through the given particles.
+pub fn area(system: &ParticleSystem, ring: &[usize]) -> f32 {
+    let n = ring.len();
+    0.5 * (0..n)
+        .map(|k| {
+            let (a, b) = (ring[k], ring[(k + 1) % n]);
+            system.x[a] * system.y[b] - system.x[b] * system.y[a]
+        })
+        .sum::<f32>()
+}
+
+/// Keeps the area enclosed by a ring of particles at `target`, the way a gas at fixed amount
+/// and temperature pushes out on a balloon.
+#[derive(Clone, Debug)]
+pub struct Area {
+    pub target: f32,
+    pub compliance: f32,
+    /// Scratch for the corner gradients, kept so that an iteration does not allocate
+    gradients: Vec<(f32, f32)>,
+}
+
+impl Area {
+    pub fn new(target: f32, compliance: f32) -> Self {
+        Area {
+            target,
+            compliance,
+            gradients: Vec::new(),
+        }
+    }
+
+    /// One iteration over a step of `dt` for the polygon through `ring`, which must wind the
+    /// same way as when `target` was measured.
+    pub fn solve(
+        &mut self,
+        system: &mut ParticleSystem,
+        ring: &[usize],
+        weights: &[f32],
+        dt: f32,
+        lambda: &mut f32,
+    ) {
+        let n = ring.len();
+        if n < 3 {
+            return;
+        }
+        // Gradient of the area with respect to each corner
+        let gradient = |system: &ParticleSystem, k: usize| {
+            let (prev, next) = (ring[(k + n - 1) % n], ring[(k + 1) % n]);
+            (
+                0.5 * (system.y[next] - system.y[prev]),
+                0.5 * (system.x[prev] - system.x[next]),
+            )
+        };
+        self.gradients.clear();
+        self.gradients.extend((0..n).map(|k| gradient(system, k)));
+        let mut denominator = self.compliance / (dt * dt);
+        for (&i, &(gx, gy)) in ring.iter().zip(&self.gradients) {
+            denominator += weights[i] * (gx * gx + gy * gy);
+        }
+        if denominator <= 0.0 {
+            return;
+        }
+        let c = area(system, ring) - self.target;
+        let delta = -(c + self.compliance / (dt * dt) * *lambda) / denominator;
+        *lambda += delta;
+        for (&i, &(gx, gy)) in ring.iter().zip(&self.gradients) {
+            system.x[i] += gx * delta * weights[i];
+            system.y[i] += gy * delta * weights[i];
+        }
+    }
+}